@@ -0,0 +1,101 @@
+//! 从 zip 压缩包中取出工作簿并转换（需启用 `zip` feature）
+
+use crate::{
+    ConversionReport, ConvertExcelToParquetOptions, ExcelFormat, check_options,
+    convert_reader_to_parquet,
+};
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// 转换 zip 压缩包中的工作簿
+///
+/// 按压缩包中的顺序选取第一个扩展名为 `.xlsx` 或 `.xlsb` 的条目；指定 `entry_pattern` 时
+/// 只选取完整路径（如 `exports/2024-01.xlsx`）匹配该 glob 的条目，`*` 匹配任意字符（含 `/`），
+/// `?` 匹配单个字符。`__MACOSX/` 下的条目总是被忽略。条目会完整解压到内存后再解析，
+/// `options.excel_file` 仅用于日志输出。
+pub fn convert_zip(
+    zip_path: &Path,
+    entry_pattern: Option<&str>,
+    options: ConvertExcelToParquetOptions,
+) -> Result<ConversionReport> {
+    check_options(&options)?;
+    let file = File::open(zip_path)
+        .with_context(|| format!("Failed to open zip archive {}", zip_path.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {}", zip_path.display()))?;
+
+    let (name, format) = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .filter(|name| entry_pattern.is_none_or(|pattern| glob_match(pattern, name)))
+        .find_map(|name| {
+            Some((
+                name.to_string(),
+                ExcelFormat::from_extension(Path::new(name)).ok()?,
+            ))
+        })
+        .ok_or_else(|| match entry_pattern {
+            Some(pattern) => anyhow!(
+                "No spreadsheet entry matching '{}' in {}",
+                pattern,
+                zip_path.display()
+            ),
+            None => anyhow!("No spreadsheet entry in {}", zip_path.display()),
+        })?;
+    info!("Extracting '{}' from {}", name, zip_path.display());
+
+    let mut entry = archive.by_name(&name)?;
+    let mut buffer = Vec::with_capacity(entry.size() as usize);
+    entry
+        .read_to_end(&mut buffer)
+        .with_context(|| format!("Failed to extract '{}'", name))?;
+
+    convert_reader_to_parquet(Cursor::new(buffer), format, options)
+}
+
+/// 简单的 glob 匹配：`*` 匹配任意长度的字符，`?` 匹配单个字符，区分大小写
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置及其当前匹配到的文本位置，失配时回溯
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.xlsx", "exports/data.xlsx"));
+        assert!(glob_match("exports/2024-??.xlsx", "exports/2024-01.xlsx"));
+        assert!(glob_match("*sales*", "q1/sales.xlsb"));
+        assert!(!glob_match("*.xlsx", "data.xlsb"));
+        assert!(!glob_match("2024-?.xlsx", "2024-01.xlsx"));
+    }
+}
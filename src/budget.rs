@@ -0,0 +1,94 @@
+//! 工作线程预算，限制共享同一预算的并发转换的工作线程总数
+
+use std::sync::{Arc, Condvar, Mutex};
+
+/// 工作线程预算：计数信号量，每个存活的工作线程占用一个许可
+///
+/// 克隆得到的句柄共享同一预算。通过 `ConvertExcelToParquetOptions::thread_budget` 传给各个转换，
+/// 只有持有同一预算的转换之间互相限制。
+#[derive(Clone)]
+pub struct ThreadBudget(Arc<BudgetInner>);
+
+struct BudgetInner {
+    limit: usize,
+    state: Mutex<BudgetState>,
+    released: Condvar,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    in_use: usize,
+    peak: usize,
+}
+
+impl ThreadBudget {
+    /// 创建最多允许 `max_workers` 个工作线程同时存活的预算，`0` 按 1 处理
+    pub fn new(max_workers: usize) -> Self {
+        Self(Arc::new(BudgetInner {
+            limit: max_workers.max(1),
+            state: Mutex::new(BudgetState::default()),
+            released: Condvar::new(),
+        }))
+    }
+
+    /// 自创建以来同时存活的工作线程峰值，可用于确认预算的大小是否合适
+    pub fn peak(&self) -> usize {
+        self.0.state.lock().unwrap().peak
+    }
+}
+
+static GLOBAL_BUDGET: Mutex<Option<ThreadBudget>> = Mutex::new(None);
+
+/// 设置进程内默认的工作线程预算，`0` 表示不限制
+///
+/// 未设置 `thread_budget` 的转换在创建时取用当时的默认预算，之后的修改不影响已创建的转换。
+/// 需要互不干扰的多组转换（如测试或多租户服务）应为各组分别传入 [`ThreadBudget`]。
+pub fn set_global_thread_budget(max_workers: usize) {
+    *GLOBAL_BUDGET.lock().unwrap() = (max_workers > 0).then(|| ThreadBudget::new(max_workers));
+}
+
+/// 当前的默认预算，未设置时为 `None`
+pub fn global_thread_budget() -> Option<ThreadBudget> {
+    GLOBAL_BUDGET.lock().unwrap().clone()
+}
+
+/// 工作线程许可，随线程结束而释放
+pub(crate) struct WorkerPermit(Option<ThreadBudget>);
+
+impl Drop for WorkerPermit {
+    fn drop(&mut self) {
+        if let Some(ThreadBudget(budget)) = &self.0 {
+            budget.state.lock().unwrap().in_use -= 1;
+            budget.released.notify_all();
+        }
+    }
+}
+
+/// 为一次转换获取最多 `wanted` 个许可：阻塞直到拿到第一个，其余只取当前空闲的。
+///
+/// 每次转换至少等待到一个许可，再尽量获取更多，因此预算较小时转换会以较少的工作线程运行，
+/// 而不是互相等待。没有预算时不做限制
+pub(crate) fn acquire_worker_permits(
+    budget: Option<&ThreadBudget>,
+    wanted: usize,
+) -> Vec<WorkerPermit> {
+    let Some(budget) = budget else {
+        return (0..wanted).map(|_| WorkerPermit(None)).collect();
+    };
+
+    let inner = &budget.0;
+    let mut state = inner
+        .released
+        .wait_while(inner.state.lock().unwrap(), |state| {
+            state.in_use >= inner.limit
+        })
+        .unwrap();
+    let granted = wanted.min(inner.limit - state.in_use).max(1);
+    state.in_use += granted;
+    state.peak = state.peak.max(state.in_use);
+    drop(state);
+
+    (0..granted)
+        .map(|_| WorkerPermit(Some(budget.clone())))
+        .collect()
+}
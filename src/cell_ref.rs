@@ -0,0 +1,183 @@
+//! A1 样式单元格引用的解析
+
+use calamine::Dimensions;
+
+/// Excel 支持的最大行号（0 起始）
+const MAX_ROW: u32 = 1_048_575;
+/// Excel 支持的最大列号（0 起始）
+const MAX_COL: u32 = 16_383;
+
+/// 解析列字母（如 `AB`），返回 0 起始的列号
+pub(crate) fn parse_column_letters(letters: &str) -> Option<u32> {
+    if letters.is_empty() {
+        return None;
+    }
+    let mut col: u32 = 0;
+    for ch in letters.chars() {
+        if !ch.is_ascii_alphabetic() {
+            return None;
+        }
+        col = col
+            .checked_mul(26)?
+            .checked_add(ch.to_ascii_uppercase() as u32 - 'A' as u32 + 1)?;
+    }
+    Some(col - 1)
+}
+
+/// 将区域限制在 Sheet 范围内，避免整行/整列引用（如 `3:10`、`A:C`）展开到 Excel 的最大行列。
+///
+/// 按行、列分别求交集；某一方向不相交时保留原区域，但整行/整列引用的开放端收缩到起点
+pub(crate) fn clamp_area(area: Dimensions, bounds: Dimensions) -> Dimensions {
+    let clamp = |start: u32, end: u32, lo: u32, hi: u32, max: u32| {
+        if start <= hi && end >= lo {
+            (start.max(lo), end.min(hi))
+        } else if end == max {
+            (start, start)
+        } else {
+            (start, end)
+        }
+    };
+    let rows = clamp(
+        area.start.0,
+        area.end.0,
+        bounds.start.0,
+        bounds.end.0,
+        MAX_ROW,
+    );
+    let cols = clamp(
+        area.start.1,
+        area.end.1,
+        bounds.start.1,
+        bounds.end.1,
+        MAX_COL,
+    );
+    Dimensions::new((rows.0, cols.0), (rows.1, cols.1))
+}
+
+/// 将 0 起始的列号转为列字母（如 `27` -> `AB`），与 [`parse_column_letters`] 互逆
+pub(crate) fn column_letters(col: u32) -> String {
+    let mut letters = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        letters.push(b'A' + ((n - 1) % 26) as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap()
+}
+
+/// 将 0 起始的 (row, col) 转为 A1 样式的单元格地址（如 `(41, 2)` -> `C42`）
+pub(crate) fn cell_address(row: u32, col: u32) -> String {
+    format!("{}{}", column_letters(col), row + 1)
+}
+
+/// 区域是否为整行引用（如 `$1:$2`），即覆盖全部列
+pub(crate) fn is_whole_rows(area: &Dimensions) -> bool {
+    area.start.1 == 0 && area.end.1 == MAX_COL
+}
+
+/// 解析区域的一端，返回 0 起始的 (row, col)，整行/整列引用对应的部分为 `None`
+fn parse_endpoint(reference: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let reference = reference.replace('$', "");
+    let split = reference
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(reference.len());
+    let (letters, digits) = reference.split_at(split);
+
+    let col = match letters {
+        "" => None,
+        letters => Some(parse_column_letters(letters)?),
+    };
+    let row = match digits {
+        "" => None,
+        digits => Some(digits.parse::<u32>().ok()?.checked_sub(1)?),
+    };
+    if row.is_none() && col.is_none() {
+        return None;
+    }
+    Some((row, col))
+}
+
+/// 解析区域引用（如 `'My Sheet'!$A$1:$C$10`、`Sheet1!$A:$C`），
+/// 返回所属 Sheet 名称（若有）以及 0 起始的区域
+pub(crate) fn parse_area(reference: &str) -> Option<(Option<String>, Dimensions)> {
+    let reference = reference.trim();
+    let (sheet, range) = match reference.rfind('!') {
+        Some(pos) => {
+            let sheet = reference[..pos].trim_matches('\'').replace("''", "'");
+            (Some(sheet), &reference[pos + 1..])
+        }
+        None => (None, reference),
+    };
+
+    let (first, last) = range.split_once(':').unwrap_or((range, range));
+    let (first_row, first_col) = parse_endpoint(first)?;
+    let (last_row, last_col) = parse_endpoint(last)?;
+
+    let start = (first_row.unwrap_or(0), first_col.unwrap_or(0));
+    let end = (last_row.unwrap_or(MAX_ROW), last_col.unwrap_or(MAX_COL));
+    if start.0 > end.0 || start.1 > end.1 {
+        return None;
+    }
+    Some((sheet, Dimensions::new(start, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_area() {
+        let (sheet, area) = parse_area("'Q1 Data'!$B$2:$D$10").unwrap();
+        assert_eq!(sheet.as_deref(), Some("Q1 Data"));
+        assert_eq!((area.start, area.end), ((1, 1), (9, 3)));
+
+        let (sheet, area) = parse_area("Sheet1!$A:$C").unwrap();
+        assert_eq!(sheet.as_deref(), Some("Sheet1"));
+        assert_eq!((area.start, area.end), ((0, 0), (MAX_ROW, 2)));
+
+        let (sheet, area) = parse_area("AA5").unwrap();
+        assert_eq!(sheet, None);
+        assert_eq!((area.start, area.end), ((4, 26), (4, 26)));
+
+        assert!(parse_area("Sheet1!#REF!").is_none());
+
+        let (_, rows) = parse_area("Sheet1!$1:$2").unwrap();
+        assert!(is_whole_rows(&rows));
+        let (_, columns) = parse_area("Sheet1!$A:$B").unwrap();
+        assert!(!is_whole_rows(&columns));
+    }
+
+    #[test]
+    fn test_clamp_area() {
+        let sheet = Dimensions::new((0, 1), (19, 4));
+
+        let (_, rows) = parse_area("Sheet1!$3:$10").unwrap();
+        let rows = clamp_area(rows, sheet);
+        assert_eq!((rows.start, rows.end), ((2, 1), (9, 4)));
+
+        let (_, columns) = parse_area("Sheet1!$A:$C").unwrap();
+        let columns = clamp_area(columns, sheet);
+        assert_eq!((columns.start, columns.end), ((0, 1), (19, 2)));
+
+        // 不相交的方向：明确的边界保持不变，整行引用的列收缩到起点
+        let (_, below) = parse_area("Sheet1!$30:$40").unwrap();
+        let below = clamp_area(below, sheet);
+        assert_eq!((below.start, below.end), ((29, 1), (39, 4)));
+        let (_, beside) = parse_area("Sheet1!$H:$J").unwrap();
+        let beside = clamp_area(beside, sheet);
+        assert_eq!((beside.start, beside.end), ((0, 7), (19, 9)));
+    }
+
+    #[test]
+    fn test_column_letters() {
+        assert_eq!(column_letters(0), "A");
+        assert_eq!(column_letters(25), "Z");
+        assert_eq!(column_letters(27), "AB");
+        assert_eq!(column_letters(MAX_COL), "XFD");
+        assert_eq!(cell_address(41, 2), "C42");
+        for col in [0, 26, 701, 702, MAX_COL] {
+            assert_eq!(parse_column_letters(&column_letters(col)), Some(col));
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! 读取 Linux cgroup 的内存用量，作为写入线程的内存压力信号（需启用 `cgroups` feature）
+
+use crate::MemoryPressureCallback;
+use std::path::Path;
+use std::sync::Arc;
+
+/// cgroup v2 与 v1 的（当前用量, 上限）统计文件
+const MEMORY_FILES: [(&str, &str); 2] = [
+    ("/sys/fs/cgroup/memory.current", "/sys/fs/cgroup/memory.max"),
+    (
+        "/sys/fs/cgroup/memory/memory.usage_in_bytes",
+        "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+    ),
+];
+
+/// 当前 cgroup 的内存用量达到上限的 `threshold` 比例（如 `0.8`）时报告内存压力，
+/// 用作 `ConvertExcelToParquetOptions::memory_pressure`
+///
+/// 每次调用都重新读取统计文件，依次尝试 cgroup v2 与 v1；文件不存在（非 Linux 或不在容器中）
+/// 或未设置上限（`max`）时总是返回 `false`。
+pub fn cgroup_memory_pressure(threshold: f64) -> MemoryPressureCallback {
+    Arc::new(move || {
+        MEMORY_FILES
+            .iter()
+            .find_map(|(current, max)| memory_usage(Path::new(current), Path::new(max)))
+            .is_some_and(|(current, max)| current as f64 >= max as f64 * threshold)
+    })
+}
+
+/// 读取一组统计文件，返回（当前用量, 上限）字节数
+fn memory_usage(current: &Path, max: &Path) -> Option<(u64, u64)> {
+    let read = |path: &Path| std::fs::read_to_string(path).ok()?.trim().parse().ok();
+    Some((read(current)?, read(max)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let (current, max) = (dir.path().join("current"), dir.path().join("max"));
+        std::fs::write(&current, "838860800\n").unwrap();
+        std::fs::write(&max, "1073741824\n").unwrap();
+        assert_eq!(
+            memory_usage(&current, &max),
+            Some((838_860_800, 1_073_741_824))
+        );
+
+        // 未设置上限
+        std::fs::write(&max, "max\n").unwrap();
+        assert_eq!(memory_usage(&current, &max), None);
+        assert_eq!(memory_usage(&dir.path().join("missing"), &max), None);
+    }
+}
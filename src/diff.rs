@@ -0,0 +1,228 @@
+//! 两个 Sheet 按键列对比，输出新增、删除与修改的行
+
+use crate::output::AtomicOutput;
+use crate::writer::SharedBatches;
+use crate::{
+    ConvertExcelToParquetOptions, OutputTarget, build_writer_properties, check_options,
+    convert_file_to_target,
+};
+use anyhow::{Context, Result};
+use arrow::array::{Array, AsArray, StringArray};
+use arrow::compute::concat_batches;
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_writer::ArrowWriter;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 输出中标记每行对比结果的列名，取值为 `added`、`removed` 或 `changed`
+pub const DIFF_STATUS_COLUMN: &str = "diff_status";
+
+/// [`convert_sheet_diff`] 的结果统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SheetDiffReport {
+    /// 只在 B 中出现的键
+    pub added: usize,
+    /// 只在 A 中出现的键
+    pub removed: usize,
+    /// 两边都有但至少一列取值不同的键
+    pub changed: usize,
+    /// 两边完全相同的键，不输出
+    pub unchanged: usize,
+}
+
+/// 一个 Sheet 的全部数据
+struct SheetTable {
+    name: String,
+    columns: Vec<String>,
+    batch: RecordBatch,
+}
+
+impl SheetTable {
+    /// 按 `columns` 的顺序取出各列，本表中没有的列为 `None`
+    fn resolve(&self, columns: &[String]) -> Vec<Option<&StringArray>> {
+        columns
+            .iter()
+            .map(|column| {
+                let index = self.columns.iter().position(|name| name == column)?;
+                Some(self.batch.column(index).as_string::<i32>())
+            })
+            .collect()
+    }
+
+    /// 键值到行号的映射，键为空或重复时报错
+    fn key_index(&self, key_column: &str) -> Result<HashMap<&str, usize>> {
+        let index = self
+            .columns
+            .iter()
+            .position(|name| name == key_column)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Key column '{}' not found in sheet '{}'",
+                    key_column,
+                    self.name
+                )
+            })?;
+        let keys = self.batch.column(index).as_string::<i32>();
+        let mut rows = HashMap::with_capacity(keys.len());
+        for row in 0..keys.len() {
+            if keys.is_null(row) {
+                return Err(anyhow::anyhow!(
+                    "Row {} of sheet '{}' has no key",
+                    row,
+                    self.name
+                ));
+            }
+            if rows.insert(keys.value(row), row).is_some() {
+                return Err(anyhow::anyhow!(
+                    "Duplicate key '{}' in sheet '{}'",
+                    keys.value(row),
+                    self.name
+                ));
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// 取出一个单元格的值，列不存在或为 null 时为 `None`
+fn cell(array: Option<&StringArray>, row: usize) -> Option<&str> {
+    array
+        .filter(|array| array.is_valid(row))
+        .map(|array| array.value(row))
+}
+
+/// 读取一个 Sheet 的全部批次并合并
+fn read_sheet(
+    file: &Path,
+    sheet: &str,
+    options: &ConvertExcelToParquetOptions,
+) -> Result<SheetTable> {
+    let sheet_options = ConvertExcelToParquetOptions {
+        excel_file: file,
+        sheet_name: Some(sheet.to_string()),
+        sheet_index: None,
+        ..options.clone()
+    };
+    let batches = SharedBatches::default();
+    convert_file_to_target(&sheet_options, OutputTarget::Batches(batches.clone()))
+        .with_context(|| format!("Failed to read sheet '{}'", sheet))?;
+    let batches = batches.take();
+    let Some(first) = batches.first() else {
+        return Err(anyhow::anyhow!("Sheet '{}' has no header row", sheet));
+    };
+    let schema = first.schema();
+    Ok(SheetTable {
+        name: sheet.to_string(),
+        columns: schema.fields().iter().map(|f| f.name().clone()).collect(),
+        batch: concat_batches(&schema, &batches)?,
+    })
+}
+
+/// 对比两个 Sheet，将差异写为 Parquet
+///
+/// 两个 Sheet 按相同的 `options`（表头、`skip_rows` 等）读取，按 `key_column` 对齐，
+/// 键在各自 Sheet 中必须唯一且非空。输出列为 [`DIFF_STATUS_COLUMN`] 加上两边列的并集（A 的列在前），
+/// 新增与修改的行取 B 的值并按 B 中的顺序排列，删除的行取 A 的值排在最后；相同的行不输出。
+/// 只在一个 Sheet 中出现的列不参与比较（否则每个键都会算作修改），另一个 Sheet 的行在这些列中为 null。
+/// `options.output_path` 为输出路径，`excel_file`、`sheet_name`、`sheet_index` 不起作用。
+/// 两个 Sheet 都会完整读入内存，内存占用与两者大小之和成正比。
+pub fn convert_sheet_diff(
+    file_a: &Path,
+    sheet_a: &str,
+    file_b: &Path,
+    sheet_b: &str,
+    key_column: &str,
+    options: ConvertExcelToParquetOptions,
+) -> Result<SheetDiffReport> {
+    check_options(&options)?;
+    let a = read_sheet(file_a, sheet_a, &options)?;
+    let b = read_sheet(file_b, sheet_b, &options)?;
+    let a_rows = a.key_index(key_column)?;
+    let b_rows = b.key_index(key_column)?;
+
+    let mut columns = a.columns.clone();
+    columns.extend(b.columns.iter().filter(|c| !a.columns.contains(c)).cloned());
+    if columns.iter().any(|name| name == DIFF_STATUS_COLUMN) {
+        return Err(anyhow::anyhow!(
+            "Column '{}' conflicts with the diff status column",
+            DIFF_STATUS_COLUMN
+        ));
+    }
+
+    // 列下标只解析一次；键列在两边都存在（`key_index` 已检查）
+    let a_arrays = a.resolve(&columns);
+    let b_arrays = b.resolve(&columns);
+    let key = columns.iter().position(|name| name == key_column).unwrap();
+    let shared: Vec<usize> = (0..columns.len())
+        .filter(|&i| a_arrays[i].is_some() && b_arrays[i].is_some())
+        .collect();
+
+    // 每个输出行：(状态, 来源表的各列, 行号)
+    let mut report = SheetDiffReport::default();
+    let mut output_rows: Vec<(&str, &[Option<&StringArray>], usize)> = Vec::new();
+    for row in 0..b.batch.num_rows() {
+        let key = cell(b_arrays[key], row).unwrap();
+        match a_rows.get(key) {
+            None => {
+                report.added += 1;
+                output_rows.push(("added", &b_arrays, row));
+            }
+            Some(&a_row) => {
+                let changed = shared
+                    .iter()
+                    .any(|&i| cell(a_arrays[i], a_row) != cell(b_arrays[i], row));
+                if changed {
+                    report.changed += 1;
+                    output_rows.push(("changed", &b_arrays, row));
+                } else {
+                    report.unchanged += 1;
+                }
+            }
+        }
+    }
+    for row in 0..a.batch.num_rows() {
+        if !b_rows.contains_key(cell(a_arrays[key], row).unwrap()) {
+            report.removed += 1;
+            output_rows.push(("removed", &a_arrays, row));
+        }
+    }
+
+    let mut fields = vec![Field::new(DIFF_STATUS_COLUMN, DataType::Utf8, false)];
+    fields.extend(
+        columns
+            .iter()
+            .map(|name| Field::new(name, DataType::Utf8, true)),
+    );
+    let schema = Arc::new(Schema::new(fields));
+    let mut arrays = vec![Arc::new(
+        output_rows
+            .iter()
+            .map(|(status, _, _)| Some(*status))
+            .collect::<StringArray>(),
+    ) as _];
+    for i in 0..columns.len() {
+        let values: StringArray = output_rows
+            .iter()
+            .map(|(_, arrays, row)| cell(arrays[i], *row))
+            .collect();
+        arrays.push(Arc::new(values) as _);
+    }
+    let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+
+    let output = AtomicOutput::new(options.output_path);
+    let file = File::create(output.temp_path()).context("Failed to create output file")?;
+    let mut writer = ArrowWriter::try_new(file, schema, Some(build_writer_properties(&options)?))
+        .context("Failed to create parquet writer")?;
+    writer.write(&batch)?;
+    writer.close()?;
+    output.commit()?;
+
+    info!(
+        "Diff of '{}' and '{}': {} added, {} removed, {} changed",
+        sheet_a, sheet_b, report.added, report.removed, report.changed
+    );
+    Ok(report)
+}
@@ -0,0 +1,96 @@
+//! 读取 xlsx 中隐藏的行与列（需启用 `hidden` feature）
+//!
+//! calamine 不解析行列的隐藏状态，这里直接从压缩包中读取 Sheet 的 XML，
+//! 只查找 `<row hidden="1">` 与 `<col hidden="1">`，不做完整的 XML 解析。
+
+use crate::xlsx_xml::{attribute, local_name, scan_tags, sheet_part};
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Sheet 中隐藏的行与列（0 起始）
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct HiddenCells {
+    pub(crate) rows: HashSet<u32>,
+    /// 升序，不重复
+    pub(crate) columns: Vec<u32>,
+}
+
+/// 读取 `path` 中名为 `sheet_name` 的 Sheet 的隐藏行列，需要额外完整解压一遍该 Sheet
+pub(crate) fn read_hidden(path: &Path, sheet_name: &str) -> Result<HiddenCells> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = ZipArchive::new(file).context("Failed to read workbook archive")?;
+    let part = sheet_part(&mut archive, sheet_name)?;
+    let entry = archive
+        .by_name(&part)
+        .with_context(|| format!("Worksheet part '{}' not found", part))?;
+    scan_sheet(BufReader::new(entry))
+}
+
+fn is_hidden(tag: &str) -> bool {
+    matches!(attribute(tag, "hidden"), Some("1" | "true"))
+}
+
+/// 逐个标签扫描 Sheet 的 XML，读到 `</sheetData>` 为止
+fn scan_sheet(reader: impl BufRead) -> Result<HiddenCells> {
+    let mut hidden = HiddenCells::default();
+    let mut columns = HashSet::new();
+    // 上一个 `<row>` 的行号（1 起始），省略 `r` 属性时行号顺延
+    let mut last_row = 0u32;
+    scan_tags(reader, |tag| {
+        if let Some(closing) = tag.strip_prefix('/') {
+            return Ok(local_name(closing) != "sheetData");
+        }
+        match local_name(tag) {
+            "row" => {
+                last_row = match attribute(tag, "r") {
+                    Some(r) => r.parse().context("Invalid row number in worksheet")?,
+                    None => last_row + 1,
+                };
+                if is_hidden(tag) {
+                    hidden.rows.insert(last_row.saturating_sub(1));
+                }
+            }
+            "col" if is_hidden(tag) => {
+                let bound = |name| -> Result<u32> {
+                    attribute(tag, name)
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| anyhow!("Invalid column range in worksheet"))
+                };
+                columns.extend(bound("min")?.saturating_sub(1)..bound("max")?);
+            }
+            _ => {}
+        }
+        Ok(true)
+    })?;
+    hidden.columns = columns.into_iter().collect();
+    hidden.columns.sort_unstable();
+    Ok(hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_sheet() {
+        let xml = r#"<?xml version="1.0"?>
+<x:worksheet xmlns:x="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+  <x:cols><x:col min="2" max="3" width="0" hidden="1"/><x:col min="5" max="5" width="9"/></x:cols>
+  <x:sheetData>
+    <x:row r="1"><x:c r="A1"><x:v>1</x:v></x:c></x:row>
+    <x:row r="4" hidden="1"><x:c r="A4" t="inlineStr"><x:is><x:t>a &lt; b</x:t></x:is></x:c></x:row>
+    <x:row hidden="true" spans="1:2"/>
+    <x:row r="9"/>
+  </x:sheetData>
+  <x:rowBreaks><x:brk id="9" hidden="1"/></x:rowBreaks>
+  <x:row r="not a number"/>
+</x:worksheet>"#;
+        let hidden = scan_sheet(xml.as_bytes()).unwrap();
+        assert_eq!(hidden.rows, HashSet::from([3, 4]));
+        assert_eq!(hidden.columns, vec![1, 2]);
+    }
+}
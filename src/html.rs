@@ -0,0 +1,454 @@
+//! 转换 HTML 表格形式的“Excel”导出（需启用 `html` feature）
+//!
+//! 不少旧系统导出的 `.xls` 实际是一段 `<table>` 标记。这里只做够用的标签扫描，不构建 DOM：
+//! 识别 `table`、`tr`、`td`/`th` 及其 `rowspan`/`colspan`，单元格内的其余标签被去掉，只保留文本。
+
+use crate::{
+    ConversionReport, ConvertExcelToParquetOptions, check_options, convert_range_to_parquet,
+};
+use anyhow::{Context, Result, anyhow};
+use calamine::{Data, Range};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// 转换 HTML 文件中的表格
+///
+/// 未指定 `table_id` 时取文档中的第一个表格，否则取 `id` 属性等于该值的表格；嵌套表格的文本并入
+/// 外层单元格。表格的第一行按表头处理，其后与 [`convert_range_to_parquet`] 相同：所有值都是文本，
+/// 空单元格输出为 null。跨行、跨列的单元格会把值填入它覆盖的每个位置。文件按 UTF-8 读取，
+/// 无效字节替换为 U+FFFD；`options.excel_file` 仅用于日志输出，`options.sheet_name` 为报告中的名称。
+pub fn convert_html_table(
+    html_path: &Path,
+    table_id: Option<&str>,
+    options: ConvertExcelToParquetOptions,
+) -> Result<ConversionReport> {
+    check_options(&options)?;
+    let bytes = std::fs::read(html_path)
+        .with_context(|| format!("Failed to read HTML file {}", html_path.display()))?;
+    let range = read_table(&bytes, table_id, html_path.display())?;
+    convert_range_to_parquet(&range, options)
+}
+
+/// 与 [`convert_html_table`] 相同，但从任意 `Read` 数据源（如内存缓冲区）读取 HTML
+///
+/// 错误信息与日志中以 `options.excel_file` 指代数据源。
+pub fn convert_html_reader<R: Read>(
+    mut reader: R,
+    table_id: Option<&str>,
+    options: ConvertExcelToParquetOptions,
+) -> Result<ConversionReport> {
+    check_options(&options)?;
+    let range = read_html(&mut reader, table_id, options.excel_file.display())?;
+    convert_range_to_parquet(&range, options)
+}
+
+/// 读取 `reader` 的全部内容并解析其中的表格
+pub(crate) fn read_html(
+    reader: &mut impl Read,
+    table_id: Option<&str>,
+    source: impl Display,
+) -> Result<Range<Data>> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to read HTML from {}", source))?;
+    read_table(&bytes, table_id, source)
+}
+
+/// 解析 HTML 中的表格，按单元格位置放入 `Range`；`source` 用于错误信息与日志
+fn read_table(bytes: &[u8], table_id: Option<&str>, source: impl Display) -> Result<Range<Data>> {
+    let html = String::from_utf8_lossy(bytes);
+    let rows = parse_table(&html, table_id).ok_or_else(|| match table_id {
+        Some(id) => anyhow!("No table with id '{}' in {}", id, source),
+        None => anyhow!("No table in {}", source),
+    })?;
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if width == 0 {
+        return Err(anyhow!("Table in {} has no cells", source));
+    }
+    info!(
+        "Parsed HTML table from {}: {} rows, {} columns",
+        source,
+        rows.len(),
+        width
+    );
+
+    let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+    for (r, row) in rows.into_iter().enumerate() {
+        for (c, value) in row.into_iter().enumerate() {
+            if !value.is_empty() {
+                range.set_value((r as u32, c as u32), Data::String(value));
+            }
+        }
+    }
+    Ok(range)
+}
+
+/// 判断内容是否为 HTML：跳过 UTF-8 BOM 与空白后以 `<` 开头，XML 声明（`<?xml`）除外
+pub(crate) fn looks_like_html(head: &[u8]) -> bool {
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = head
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(head.len());
+    let head = &head[start..];
+    head.starts_with(b"<") && !head.starts_with(b"<?xml")
+}
+
+/// 读取文件开头判断是否为 HTML，文件无法读取时为 `false`
+pub(crate) fn sniff_file(path: &Path) -> bool {
+    let mut head = Vec::with_capacity(512);
+    File::open(path)
+        .and_then(|file| file.take(512).read_to_end(&mut head))
+        .is_ok_and(|_| looks_like_html(&head))
+}
+
+/// 扫描得到的一个片段
+enum Token<'a> {
+    /// 开始或结束标签：（小写标签名, 是否为结束标签, 属性原文）
+    Tag(String, bool, &'a str),
+    Text(&'a str),
+}
+
+/// 将 HTML 切分为标签与文本，跳过注释、声明以及 `script`/`style` 的内容
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = tag_end(rest);
+        let inner = rest[1..end].trim_end_matches('>').trim_end_matches('/');
+        rest = &rest[end..];
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let name_len = inner
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(inner.len());
+        let name = inner[..name_len].to_ascii_lowercase();
+        if name.is_empty() || !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // `<!DOCTYPE>`、`<?xml?>` 或不成对的 `<`
+            continue;
+        }
+        if !closing && (name == "script" || name == "style") {
+            let close = format!("</{}", name);
+            rest = find_ignore_case(rest, &close).map_or("", |pos| &rest[pos..]);
+            continue;
+        }
+        tokens.push(Token::Tag(name, closing, &inner[name_len..]));
+    }
+    tokens
+}
+
+/// 标签结束位置（`>` 之后），忽略引号内的 `>`
+fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    tag.len()
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// 读取属性值（属性名不区分大小写，值可带引号）
+fn attribute<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attrs;
+    loop {
+        rest = rest.trim_start();
+        let name_len = rest.find(|c: char| c.is_whitespace() || c == '=')?;
+        let attr_name = &rest[..name_len];
+        rest = rest[name_len..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, remaining) = match value.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let end = value[1..].find(q).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = remaining;
+                value
+            }
+            None => "",
+        };
+        if attr_name.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+    }
+}
+
+/// 解析 `rowspan`/`colspan`，缺省或无效时为 1
+fn span(attrs: &str, name: &str) -> usize {
+    attribute(attrs, name)
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(1)
+}
+
+/// 解码常见的字符实体，无法识别的实体原样保留
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let ch = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = entity.strip_prefix('#')?;
+                let code = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (ch, entity) {
+            (Some(ch), Some(entity)) => {
+                decoded.push(ch);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// 按 HTML 的空白规则合并单元格文本：连续空白视为一个空格，`<br>` 保留为换行
+fn normalize_text(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// 解析得到的单元格：（原始文本, rowspan, colspan）
+type HtmlCell = (String, usize, usize);
+
+/// 解析目标表格，返回已展开跨行跨列的行；找不到表格时返回 `None`
+fn parse_table(html: &str, table_id: Option<&str>) -> Option<Vec<Vec<String>>> {
+    let mut rows: Vec<Vec<HtmlCell>> = Vec::new();
+    let mut cell: Option<HtmlCell> = None;
+    // 目标表格内的嵌套深度，0 表示尚未进入目标表格
+    let mut depth = 0usize;
+    let mut found = false;
+
+    for token in tokenize(html) {
+        match token {
+            Token::Tag(name, false, attrs) if name == "table" => {
+                if depth > 0 {
+                    depth += 1;
+                } else if !found && table_id.is_none_or(|id| attribute(attrs, "id") == Some(id)) {
+                    found = true;
+                    depth = 1;
+                }
+            }
+            Token::Tag(name, true, _) if name == "table" && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ if depth != 1 => {
+                // 嵌套表格的内容只取文本，并入当前单元格
+                if let (Token::Text(text), Some((value, _, _)), true) =
+                    (&token, &mut cell, depth > 1)
+                {
+                    value.push_str(text);
+                    value.push(' ');
+                }
+            }
+            Token::Tag(name, closing, attrs) => match (name.as_str(), closing) {
+                ("tr", false) => {
+                    finish_cell(&mut rows, &mut cell);
+                    rows.push(Vec::new());
+                }
+                ("td" | "th", false) => {
+                    finish_cell(&mut rows, &mut cell);
+                    cell = Some((
+                        String::new(),
+                        span(attrs, "rowspan"),
+                        span(attrs, "colspan"),
+                    ));
+                }
+                ("tr" | "td" | "th", true) => finish_cell(&mut rows, &mut cell),
+                ("br", false) => {
+                    if let Some((value, _, _)) = &mut cell {
+                        value.push('\n');
+                    }
+                }
+                _ => {}
+            },
+            Token::Text(text) => {
+                if let Some((value, _, _)) = &mut cell {
+                    value.push_str(text);
+                }
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+    finish_cell(&mut rows, &mut cell);
+    Some(expand_spans(rows))
+}
+
+/// 结束当前单元格，放入最后一行（表格没有 `<tr>` 时新建一行）
+fn finish_cell(rows: &mut Vec<Vec<HtmlCell>>, cell: &mut Option<HtmlCell>) {
+    if let Some((value, rowspan, colspan)) = cell.take() {
+        if rows.is_empty() {
+            rows.push(Vec::new());
+        }
+        let value = normalize_text(&decode_entities(&value));
+        rows.last_mut().unwrap().push((value, rowspan, colspan));
+    }
+}
+
+/// 上方跨行单元格仍占用的列：（剩余行数, 值）
+type Pending = Vec<Option<(usize, String)>>;
+
+/// 若第 `col` 列仍被上方的跨行单元格占用，取出其值并减少剩余行数
+fn take_pending(pending: &mut Pending, col: usize) -> Option<String> {
+    let slot = pending.get_mut(col)?;
+    let (remaining, value) = slot.as_mut()?;
+    let value = value.clone();
+    *remaining -= 1;
+    if *remaining == 0 {
+        *slot = None;
+    }
+    Some(value)
+}
+
+/// 将跨行、跨列的单元格展开到它覆盖的每个位置
+fn expand_spans(rows: Vec<Vec<HtmlCell>>) -> Vec<Vec<String>> {
+    let mut pending: Pending = Vec::new();
+    let mut grid = Vec::with_capacity(rows.len());
+    for cells in rows {
+        let mut row: Vec<String> = Vec::new();
+        for (value, rowspan, colspan) in cells {
+            while let Some(above) = take_pending(&mut pending, row.len()) {
+                row.push(above);
+            }
+            for _ in 0..colspan {
+                if rowspan > 1 {
+                    if pending.len() <= row.len() {
+                        pending.resize(row.len() + 1, None);
+                    }
+                    pending[row.len()] = Some((rowspan - 1, value.clone()));
+                }
+                row.push(value.clone());
+            }
+        }
+        // 行尾之后仍被跨行单元格占用的列，中间的空位补空值
+        let width = pending
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |i| i + 1);
+        while row.len() < width {
+            let above = take_pending(&mut pending, row.len());
+            row.push(above.unwrap_or_default());
+        }
+        grid.push(row);
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table() {
+        let html = r#"
+            <html><head><style>td { color: red; }</style></head><body>
+            <!-- <table><tr><td>comment</td></tr></table> -->
+            <table id="summary"><tr><td>skip</td></tr></table>
+            <TABLE id='data' border=1>
+              <tr><th>region</th><th colspan="2">amount</th></tr>
+              <tr><td rowspan=2>North</td><td>1</td><td><b>2</b></td>
+              <tr><td>3 &amp; 4</td><td>5<br>6</td></tr>
+              <tr><td>South&nbsp;</td><td></td><td>&#x41;&#66;&unknown;</td></tr>
+            </TABLE></body></html>"#;
+        assert_eq!(
+            parse_table(html, Some("data")).unwrap(),
+            vec![
+                vec!["region", "amount", "amount"],
+                vec!["North", "1", "2"],
+                vec!["North", "3 & 4", "5\n6"],
+                vec!["South", "", "AB&unknown;"],
+            ]
+        );
+        assert_eq!(
+            parse_table(html, None).unwrap(),
+            vec![vec!["skip".to_string()]]
+        );
+        assert!(parse_table(html, Some("missing")).is_none());
+    }
+
+    #[test]
+    fn test_expand_spans_past_row_end() {
+        let rows = vec![
+            vec![("a".to_string(), 1, 1), ("b".to_string(), 3, 1)],
+            vec![("c".to_string(), 1, 1)],
+            vec![],
+        ];
+        assert_eq!(
+            expand_spans(rows),
+            vec![vec!["a", "b"], vec!["c", "b"], vec!["", "b"]]
+        );
+    }
+
+    #[test]
+    fn test_looks_like_html() {
+        assert!(looks_like_html(b"<!DOCTYPE html><table>"));
+        assert!(looks_like_html(b"\xEF\xBB\xBF\r\n  <table><tr><td>1"));
+        assert!(looks_like_html(
+            b"<html xmlns:x=\"urn:schemas-microsoft-com:office:excel\">"
+        ));
+        assert!(!looks_like_html(b"<?xml version=\"1.0\"?><Workbook>"));
+        assert!(!looks_like_html(b"PK\x03\x04"));
+        assert!(!looks_like_html(b"   "));
+    }
+}
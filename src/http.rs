@@ -0,0 +1,66 @@
+//! 通过 HTTP(S) 下载远程工作簿并转换（需启用 `http` feature）
+
+use crate::{
+    ConversionReport, ConvertExcelToParquetOptions, ExcelFormat, check_options,
+    convert_reader_to_parquet,
+};
+use anyhow::{Context, Result, anyhow};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// 下载 `url` 指向的工作簿并转换为 Parquet
+///
+/// 格式由 URL 路径的扩展名决定（忽略查询参数与片段），启用 `html` feature 时没有可识别的扩展名
+/// 也会按下载的内容识别 HTML 表格。自动跟随重定向。
+/// 文件会完整下载到内存后再解析，`options.excel_file` 仅用于日志输出。
+pub fn convert_url(url: &str, options: ConvertExcelToParquetOptions) -> Result<ConversionReport> {
+    check_options(&options)?;
+    let format = format_from_url(url);
+    #[cfg(not(feature = "html"))]
+    let format = format?;
+
+    let response = ureq::get(url).call().map_err(|e| match e {
+        ureq::Error::StatusCode(code) => anyhow!("Server returned HTTP {} for {}", code, url),
+        e => anyhow!("Failed to download {}: {}", url, e),
+    })?;
+
+    let mut buffer = Vec::new();
+    response
+        .into_body()
+        .into_reader()
+        .read_to_end(&mut buffer)
+        .with_context(|| format!("Failed to read response body from {}", url))?;
+
+    #[cfg(feature = "html")]
+    let format = match format {
+        Err(_) if crate::html::looks_like_html(&buffer) => ExcelFormat::Html,
+        format => format?,
+    };
+    convert_reader_to_parquet(Cursor::new(buffer), format, options)
+}
+
+/// 从 URL 路径的最后一段推断文件格式
+fn format_from_url(url: &str) -> Result<ExcelFormat> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    ExcelFormat::from_path(Path::new(file_name))
+        .with_context(|| format!("Cannot determine file format from URL: {}", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_url() {
+        assert_eq!(
+            format_from_url("https://example.com/data/book.XLSX?token=a.xlsb#x").unwrap(),
+            ExcelFormat::Xlsx
+        );
+        assert_eq!(
+            format_from_url("http://example.com/book.xlsb").unwrap(),
+            ExcelFormat::Xlsb
+        );
+        assert!(format_from_url("http://example.com/download?id=1").is_err());
+    }
+}
@@ -1,465 +1,8674 @@
 use anyhow::{Context, Result};
-use arrow::array::{ArrayRef, StringArray};
+use arrow::array::{Array, ArrayRef, BooleanArray, Float64Array, StringArray};
+use arrow::compute::{SortOptions, filter_record_batch, not, sort_to_indices, take_record_batch};
 use arrow::datatypes::{DataType, Field, Schema};
 use arrow::record_batch::RecordBatch;
-use calamine::{Reader, Xlsb, Xlsx, open_workbook};
+use calamine::{Data, ExcelDateTime, ExcelDateTimeType, Range, Reader, Xlsb, Xlsx, open_workbook};
+use output::AtomicOutput;
+use parquet::arrow::arrow_reader::{ArrowReaderOptions, ParquetRecordBatchReaderBuilder};
 use parquet::arrow::arrow_writer::ArrowWriter;
-use parquet::basic::{Compression, ZstdLevel};
-use parquet::file::properties::WriterProperties;
-use std::collections::HashMap;
+use parquet::basic::Compression;
+#[cfg(feature = "zstd")]
+use parquet::basic::ZstdLevel;
+use parquet::file::properties::{WriterProperties, WriterPropertiesBuilder, WriterVersion};
+use parquet::schema::types::ColumnPath;
+use profile::ProfileAccumulator;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, mpsc};
 use std::thread;
+use std::time::{Duration, Instant};
+use trace::TraceContext;
+use writer::{
+    BatchSink, ColumnPart, DeferredParquet, OrderedWriter, RowGroupSplit, SharedBatches,
+    SharedBuffer, WriterOutput, spawn_writer,
+};
 
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "zip")]
+mod archive;
+mod budget;
+mod cell_ref;
+#[cfg(feature = "cgroups")]
+mod cgroup;
+mod diff;
+#[cfg(feature = "hidden")]
+mod hidden;
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "http")]
+mod http;
+mod output;
+mod profile;
+mod spill;
+#[cfg(feature = "styles")]
+mod styles;
+mod writer;
+#[cfg(any(feature = "hidden", feature = "styles"))]
+mod xlsx_xml;
+
+#[cfg(feature = "zip")]
+pub use archive::convert_zip;
+pub use budget::{ThreadBudget, global_thread_budget, set_global_thread_budget};
+#[cfg(feature = "cgroups")]
+pub use cgroup::cgroup_memory_pressure;
+pub use diff::{DIFF_STATUS_COLUMN, SheetDiffReport, convert_sheet_diff};
+#[cfg(feature = "html")]
+pub use html::{convert_html_reader, convert_html_table};
+#[cfg(feature = "http")]
+pub use http::convert_url;
+pub use profile::ColumnProfile;
+#[cfg(not(target_arch = "wasm32"))]
+pub use writer::ParquetBatchWriter;
+
+#[derive(Clone)]
 pub struct ConvertExcelToParquetOptions<'a> {
     pub excel_file: &'a Path,
     pub output_path: &'a Path,
     pub skip_rows: usize,
-    pub batch_size: usize,
+    /// 丢弃最后 N 个非空数据行（如合计行），与之相连的空白行一并丢弃。读取时始终暂存最后 N 行，
+    /// 读到 Sheet 末尾才能确定哪些行被丢弃，丢弃的行不计入 `total_rows`
+    pub skip_trailing_rows: usize,
+    /// 丢弃最后一个非空数据行之后只有格式的空白行。关闭时这些行照常输出（单元格为空字符串）；
+    /// 表头之后只有空白行时无论是否开启都输出 0 行
+    pub drop_trailing_blank_rows: bool,
+    /// 每个批次（即每个 row group）的行数。为 `None` 时根据前若干行的平均宽度自动选择，
+    /// 使每个 row group 未压缩时约为 128MB，且不超过 65536 行
+    pub batch_size: Option<usize>,
     pub sheet_name: Option<String>,
     pub sheet_index: Option<usize>,
+    /// 按指定列排序输出。启用后不再流式写入：所有行会先缓存在内存中，
+    /// 排序后再统一写出，内存占用与整个数据集大小成正比。按字符串排序时会在 row group
+    /// 元数据中记录排序列（`sorting_columns`），按数值排序或按列拆分输出时不记录
+    pub sort_by: Option<(String, SortOrder)>,
+    /// 写入 Parquet footer 的 `created_by`，默认为本 crate 的名称和版本
+    pub created_by: Option<String>,
+    /// 将 footer 中列统计信息的 min/max 截断到最多此字节数，避免很长的文本撑大 footer；
+    /// 截断后的 max 会向上进位，统计信息不再是精确值
+    pub statistics_truncate_length: Option<usize>,
+    /// 仅转换 Sheet 的打印区域（`_xlnm.Print_Area`），覆盖 `dimensions()` 给出的范围，
+    /// 用于忽略远离数据区域的零散格式单元格。未定义打印区域时回退到原始范围
+    pub respect_print_area: bool,
+    /// 只转换工作簿中此名称（defined name，不区分大小写）所指的区域，Sheet 由该名称的引用决定，
+    /// 覆盖 `sheet_name`、`sheet_index` 与 `respect_print_area`。引用多个区域时只取第一个
+    pub named_range: Option<String>,
+    /// 以非空单元格的最小外接矩形作为转换范围，不信任 `dimensions()`：零散的格式单元格常使其
+    /// 延伸到第 1048576 行或远处的列，产生大量空白的 `Field_N` 列并拖慢进度估计。
+    /// 需要在转换前额外完整读取一遍 Sheet；与 `respect_print_area` 同时使用时只在打印区域内查找。
+    /// 开头的空行也会被裁掉，`skip_rows` 从第一个非空行算起
+    pub auto_trim_region: bool,
+    /// 只转换可见的行列，与 Excel「仅复制可见单元格」一致：隐藏的列（含筛选隐藏）不出现在输出中，
+    /// 表头之后隐藏的行不作为数据输出。calamine 不提供隐藏状态，因此直接从文件中读取 Sheet 的 XML，
+    /// 需要额外完整解压一遍该 Sheet。只支持从文件打开的 `.xlsx`：`.xlsb` 以及从内存、
+    /// zip 压缩包或 URL 读取的工作簿会返回错误
+    #[cfg(feature = "hidden")]
+    pub skip_hidden: bool,
+    /// 非致命错误的处理方式
+    pub error_mode: ErrorMode,
+    /// 错误值单元格（`#N/A`、`#DIV/0!` 等）的输出方式
+    pub error_cell_policy: ErrorCellPolicy,
+    /// 按表头名称为个别列指定错误值的输出方式，优先于 `error_cell_policy`；
+    /// 找不到的列名会在读取表头后报错
+    pub column_error_overrides: HashMap<String, ErrorCellPolicy>,
+    /// 公式单元格输出缓存的计算结果还是公式文本
+    pub formula_results: FormulaMode,
+    /// 工作簿使用的日期纪元。日期单元格统一输出为 1900 纪元的序列号，
+    /// 为 `None` 时根据工作簿的 `date1904` 标记自动判断，标记不可靠时可强制指定
+    pub date_system: Option<DateSystem>,
+    /// 浮点数单元格保留的小数位数，如 `Some(2)` 时 `0.1 + 0.2` 输出为 `0.30`。
+    /// 为 `None` 时输出能精确还原该值的最短表示；整数、日期与公式文本不受影响
+    pub float_precision: Option<usize>,
+    /// 布尔单元格输出的文本（真, 假），如 `("1", "0")` 或 `("Y", "N")`。
+    /// 为 `None` 时输出 `true` / `false`；公式的布尔结果同样适用
+    pub bool_string_format: Option<(String, String)>,
+    /// 只含空白字符（空格、制表符、换行等）的单元格输出为 null，包括空字符串。
+    /// 只影响是否为 null，非空值按原样输出，不做修剪
+    pub whitespace_as_null: bool,
+    /// 按表头名称指定的向下填充列：空单元格取该列上方最近一个非空值，用于分类标签只写在
+    /// 每组第一行（或合并单元格）的分层报表。只看单元格的值，不需要合并单元格信息；
+    /// 填充在读取线程中进行，跨批次延续。完全空白的行保持空白；找不到的列名会在读取表头后报错
+    pub forward_fill_columns: Vec<String>,
+    /// 数据单元格中出现 Unicode 替换字符（U+FFFD，通常意味着上游编码损坏）时的处理方式，
+    /// 受影响的单元格数见 `ConversionReport::replacement_char_cells`
+    pub on_replacement_char: ReplacementPolicy,
+    /// 为每个数据列额外输出一个 `<列名>__type` 列，记录每个单元格在 Excel 中的原始类型
+    /// （calamine `DataRef` 的变体名，如 `Int`、`Float`、`String`、`DateTime`、`Error`、`Empty`），
+    /// 以便无损还原。列数会翻倍；派生列没有对应的类型列
+    pub type_sidecar_columns: bool,
+    /// `convert_all_sheets_to_parquet` 中 Sheet 名称到输出文件名的映射方式
+    pub sheet_filename_sanitize: FilenameSanitize,
+    /// `convert_all_sheets_to_parquet` 中要转换的 Sheet 及其顺序，未列出的 Sheet 不转换。
+    /// 为 `None` 时按工作簿中的顺序转换所有 Sheet
+    pub sheet_order: Option<Vec<String>>,
+    /// 多文件转换（`convert_all_sheets_to_parquet`）结束后写出的 JSON 清单路径，
+    /// 列出每个输出文件的路径、Sheet 名称、行数与字节数
+    pub write_manifest: Option<PathBuf>,
+    /// 表头去重时不区分大小写，如 `ID` 与 `id` 输出为 `ID` 与 `id_2`，
+    /// 避免在列名不区分大小写的下游系统中冲突
+    pub case_insensitive_dedup: bool,
+    /// 重复表头的处理方式，默认依次加 `_2`、`_3` 后缀
+    pub dedup_strategy: DedupStrategy,
+    /// 空表头列的命名方式，数据区域不从 A 列开始时可改用实际列字母
+    pub header_index_base: HeaderIndexBase,
+    /// `skip_rows` 之后的第一行是否为表头，可设为没有表头或按单元格类型猜测，
+    /// 判断结果见 `ConversionReport::has_header`。除 `FirstRow` 外不能与 `units_row` 同时使用
+    pub header_detection: HeaderDetection,
+    /// 只输出表头从第一个名称到第二个名称（含两端）之间的列，按表头名称解析；
+    /// 任一名称不存在或两者顺序颠倒时报错。不能与 `overflow_column` 同时使用
+    pub column_window: Option<(String, String)>,
+    /// 表头最后一个非空单元格右侧的单元格不再各自成列，而是按列顺序编码为 JSON 字符串数组
+    /// （如 `["x","y"]`），写入此名称的列，紧跟在数据列之后；没有多余单元格的行为 null。
+    /// 适用于个别行末尾多出若干值的数据，避免为此生成大量 `Field_N` 列
+    pub overflow_column: Option<String>,
+    /// 追加一列（名称, 算法），值为每行内容的十六进制哈希，用于增量加载时识别变化的行。
+    /// 哈希覆盖全部数据列（按列名排序，与列在 Sheet 中的位置无关），不含类型列、派生列与常量列；
+    /// null 与空字符串的哈希不同。同样的内容在不同运行、不同平台上总是得到相同的哈希
+    pub row_hash_column: Option<(String, ChecksumAlgo)>,
+    /// 由已有列拼接生成、追加在输出末尾的派生列
+    pub derived_columns: Vec<DerivedColumn>,
+    /// 在输出末尾（派生列之后）追加一个以此命名的列，每行都是当前 Sheet 的名称，
+    /// 便于合并多个 Sheet 的输出后区分来源
+    pub include_sheet_column: Option<String>,
+    /// 追加在 Sheet 名称列之后、按列名排序的常量列，如批次号、加载日期。值中的模板在转换开始时展开：
+    /// `{source_file}` 为输入文件名，`{sheet}` 为 Sheet 名称，`{now}` 为 UTC 时间（`2024-01-31T08:00:00Z`，
+    /// `wasm32` 目标上没有系统时钟，使用时报错）；其余花括号内容原样保留
+    pub constant_columns: HashMap<String, String>,
+    /// 读取 Sheet 的打印标题行（`_xlnm.Print_Titles` 中的行引用，如 `$1:$2`），将其中位于表头之上的
+    /// 每个非空单元格作为常量列追加在 `constant_columns` 之后，列名为 `print_title_<单元格地址>`
+    /// （如 `print_title_A1`）。打印标题中的列引用被忽略；表头行本身即使在标题行中也不重复输出，
+    /// 因此标题行通常需要配合 `skip_rows` 跳过。未定义打印标题时不追加任何列
+    pub print_title_columns: bool,
+    /// 按表头名称指定的掩码列，只保留该列为真值的行。空值、`0`、`false`（不区分大小写）视为假，
+    /// 其余值视为真。被过滤的行数见 `ConversionReport::masked_rows`
+    pub mask_column: Option<String>,
+    /// 是否在输出中保留掩码列，默认不保留
+    pub keep_mask_column: bool,
+    /// 跳过数据中重复出现的表头行，只比较非空单元格且要求完全相同。
+    /// 跳过的行不计入 `total_rows`，数量见 `ConversionReport::repeated_headers_skipped`
+    pub repeated_header: Option<RepeatedHeader>,
+    /// 跳过数据块之间的标题行（如合并单元格写出的「华北区」），按 [`TitleBand`] 的规则识别。
+    /// 真实数据中只填了个别单元格的行同样会被跳过，仅在数据行基本填满时使用。
+    /// 跳过的行不计入 `total_rows`，数量见 `ConversionReport::title_rows_skipped`
+    pub skip_title_bands: Option<TitleBand>,
+    /// 只输出按规则抽中的数据行，用于从整个文件中取得有代表性的样本。抽样在读取线程中按行进行，
+    /// 位于 `skip_trailing_rows` 之后；空白行不参与抽样也不输出。
+    /// 未抽中的行不计入 `total_rows`，数量见 `ConversionReport::rows_not_sampled`
+    pub sample: Option<SampleSpec>,
+    /// `SampleSpec::RandomFraction` 的随机种子，相同种子对同一文件总是抽中相同的行。
+    /// 为 `None` 时以当前时间为种子（`wasm32` 目标上没有系统时钟，以 0 为种子）
+    pub sample_seed: Option<u64>,
+    /// 将表头下方的第一行视为单位行（如 `°C`）：不作为数据输出，
+    /// 非空的单位写入对应字段的元数据（键为 [`UNIT_METADATA_KEY`]）并列在 `ConversionReport::units` 中
+    pub units_row: bool,
+    /// 按输出列名为字段附加说明，写入字段元数据（键为 [`DESCRIPTION_METADATA_KEY`]），供下游数据目录读取。
+    /// 可用于派生列、常量列等任何输出列，找不到的列名会在读取表头后报错
+    pub column_descriptions: HashMap<String, String>,
+    /// 输出列的顺序，默认与 Sheet 中一致。在表头去重、重命名并追加派生列、常量列等之后应用，
+    /// 对全部输出列生效
+    pub output_column_order: OutputColumnOrder,
+    /// 丢弃重复的输出行，保留最先出现的一行，丢弃的行数见 `ConversionReport::duplicate_rows_removed`。
+    /// 去重在写入线程中进行，已见过的键全部保存在内存中，内存占用与不同键的数量及长度成正比
+    pub deduplicate: Option<DedupMode>,
+    /// 对输出列逐行校验的约束，在 worker 中与批次构建一同进行。`ErrorMode::FailFast` 下
+    /// 第一个违规即中止转换；`Collect` 下违规记入 `ConversionReport::errors`（受 `max_errors` 限制）
+    /// 并照常输出该行，总数见 `ConversionReport::constraint_violations`。约束的列名找不到或
+    /// 正则表达式无效时在读取表头后报错
+    pub constraints: Vec<ColumnConstraint>,
+    /// 被拒绝的行不再丢弃，而是写入此路径的另一个 Parquet 文件供人工检查：包括 `mask_column`
+    /// 为假值的行，以及 `ErrorMode::Collect` 下违反 `constraints` 的行（此时不再出现在主输出中）。
+    /// 文件的列为主输出的全部列加上 [`REJECT_REASON_COLUMN`]，行数见 `ConversionReport::rejected_rows`。
+    /// 由单独的写入线程按批次顺序写出，不参与排序与去重；只在写出 Parquet 文件时生效
+    pub rejects_output: Option<PathBuf>,
+    /// 将输出按列拆分为多个文件 `<文件名>.part-cols-<K>.parquet`（K 从 1 开始），
+    /// 每个文件包含全部键列及其余列中按顺序的一段，供有列数上限的下游系统使用。
+    /// 启用后不写出 `output_path` 本身，各文件路径见 `ConversionReport::output_files`
+    pub column_split: Option<ColumnSplit>,
+    /// 与主输出同时写出的额外输出，如供调试查看的 NDJSON。读取与批次构建只进行一次，
+    /// 写入线程把写入主输出的每个批次（排序、去重之后）依次写入每个额外输出，Parquet 输出使用相同的
+    /// 写入属性与 row group 划分。任一输出写入失败都会使整个转换失败；启用 `atomic_output` 时
+    /// 所有输出都先写临时文件，只在转换成功后一并重命名，不会留下部分写出的文件。
+    /// 只在写出 Parquet 文件时生效
+    pub fan_out: Vec<FanOutTarget>,
+    /// 丢弃从表头行开始没有任何非空值的列（如导出文件末尾的空列）。
+    /// 需要在转换前额外完整读取一遍 Sheet 以确定这些列
+    pub skip_empty_columns: bool,
+    /// 在报告中附带每个 row group 的压缩前后大小（`ConversionReport::row_group_stats`），
+    /// 并在日志中输出压缩比，用于调整压缩参数
+    pub collect_stats: bool,
+    /// 写入完成后重新读取输出文件，确认行数与写入线程报告的写出行数（`ConversionReport::rows_written`）一致，
+    /// 不一致时返回错误。需要额外完整读取一遍输出文件
+    pub verify_output: bool,
+    /// 构建每个批次时额外检查所有列的长度都等于该批次的行数，不一致时返回带批次编号的错误。
+    /// 用于发现单元格分发逻辑的问题；启用后 Collect 模式下构建批次的错误也会中止转换，不再跳过该批次
+    pub strict_batch_validation: bool,
+    /// 先写入同目录下的临时文件 `<文件名>.tmp-<pid>`，成功后再重命名为目标文件，
+    /// 避免失败时在目标路径留下不完整的 Parquet 文件。默认开启
+    pub atomic_output: bool,
+    /// 输出文件外包一层此容量（字节）的 `BufWriter`，把 Parquet 写出的零散小块合并为较大的写入。
+    /// 为 `None` 时直接写文件；输出内容与是否缓冲无关
+    pub write_buffer_size: Option<usize>,
+    /// 输出文件的字节上限。每写出一个 row group 后按已写出部分的平均行大小预估，写入下一个批次
+    /// 将超出上限时停止读取，丢弃之后的行并正常关闭文件，结果见 `ConversionReport::output_limit_reached`。
+    /// 上限只计 row group 数据，不含关闭文件时写出的页索引与 footer；第一个 row group 总会写出。
+    /// 不能与 `sort_by` 同时使用
+    pub max_output_bytes: Option<usize>,
+    /// 写入属性的预设组合，取代默认的 ZSTD 压缩；`created_by`、`encryption` 仍然生效，
+    /// 设置了 `writer_properties` 时不起作用
+    pub preset: Option<ConvertPreset>,
+    /// 直接指定 Parquet 写入属性，用于本 crate 未单独封装的参数（页大小、字典编码、统计信息等）。
+    /// 设置后完全取代默认属性：`created_by`、ZSTD 压缩与 `encryption` 均不再生效，需要时应在此处自行设置。
+    /// 其中的 row group 大小不被采用，总是由 `max_row_group_size` 或 `batch_size` 决定
+    pub writer_properties: Option<WriterProperties>,
+    /// row group 的行数上限。未设置时按 `batch_size` 推算（`coalesce_small_tail` 与 `fast-write`
+    /// 预设会相应放大），设置后优先于推算值与 `writer_properties`
+    pub max_row_group_size: Option<usize>,
+    /// 每个批次写入后立即结束当前 row group，使 row group 与批次一一对应，
+    /// 文件布局不受 `max_row_group_size` 的影响。
+    /// 批次较小时会产生大量 row group，增加 footer 体积并降低压缩率
+    pub one_row_group_per_batch: bool,
+    /// 最后一个批次不足 `batch_size` 的 1/10 时并入前一个 row group，避免文件末尾出现过小的 row group。
+    /// 写入线程会推迟结束每个 row group，直到确认下一个批次足够大
+    pub coalesce_small_tail: bool,
+    /// 第一个批次中不同值个数超过此值的列关闭字典编码并记录警告，列名见
+    /// `ConversionReport::high_cardinality_columns`。写入属性需在写出第一个批次前确定，
+    /// 因此只统计第一个批次，之后的批次不再检查
+    pub max_distinct_per_column: Option<usize>,
+    /// 有列超过 `max_distinct_per_column` 时返回错误，而不是关闭字典编码后继续
+    pub strict_distinct_check: bool,
+    /// 不启动任何线程，在读取循环中直接构建并写出每个批次。
+    /// 输出与多线程模式逐字节相同，但批次的处理顺序固定，便于复现问题。
+    /// `wasm32` 目标上没有线程，总是使用此模式
+    pub single_threaded: bool,
+    /// worker 线程的栈大小（字节），为 `None` 时使用标准库的默认值。
+    /// 极宽的行在个别数组构建路径上可能耗尽默认栈时使用
+    pub worker_stack_size: Option<usize>,
+    /// 写入线程将乱序到达的批次以 Arrow IPC 文件暂存到系统临时目录（`TMPDIR`）下的
+    /// `data-to-parquet-spill-<pid>-<随机后缀>` 目录（每次新建，Unix 上权限为 0700），
+    /// 而不是留在内存中，轮到时再读回。
+    /// 某个 worker 严重落后时可限制内存峰值，代价是额外的磁盘读写。目录在写入结束或出错时删除
+    pub spill_to_disk: bool,
+    /// 内存压力信号，写入线程每收到一个批次调用一次。返回 `true` 时立即结束当前 row group，
+    /// 并把内存中暂存的乱序批次溢出到磁盘（同 `spill_to_disk`），此后乱序批次都直接溢出。
+    /// 排序输出时缓存的行无法释放。启用 `cgroups` feature 后可用 `cgroup_memory_pressure` 构造
+    pub memory_pressure: Option<MemoryPressureCallback>,
+    /// 进度回调，每发出一个批次及转换结束时在读取线程中调用
+    pub progress: Option<ProgressCallback>,
+    /// 输出文件写完、关闭并重命名到目标路径后，对 `ConversionReport::output_files` 中的每个文件调用一次，
+    /// 参数为文件路径与本次转换的报告，用于上传或登记输出文件。转换失败、输出不是文件
+    /// （如 `convert_reader_to_bytes`）或没有表头而未生成文件时不调用；转换所有 Sheet 时每个 Sheet 各自调用
+    pub on_complete: Option<CompletionCallback>,
+    /// 本次转换的工作线程从中获取许可的预算，克隆同一个 [`ThreadBudget`] 传给多个转换即可限制它们的线程总数。
+    /// 为 `None` 时使用创建转换时 `set_global_thread_budget` 设置的默认预算（若有）
+    pub thread_budget: Option<ThreadBudget>,
+    /// Parquet 模块化加密配置，需启用 `encryption` feature
+    #[cfg(feature = "encryption")]
+    pub encryption: Option<ParquetEncryption>,
+}
+
+impl<'a> ConvertExcelToParquetOptions<'a> {
+    /// 使用默认值创建选项，其余字段可通过结构体更新语法覆盖
+    pub fn new(excel_file: &'a Path, output_path: &'a Path) -> Self {
+        Self {
+            excel_file,
+            output_path,
+            skip_rows: 0,
+            skip_trailing_rows: 0,
+            drop_trailing_blank_rows: false,
+            batch_size: None,
+            sheet_name: None,
+            sheet_index: None,
+            sort_by: None,
+            created_by: None,
+            statistics_truncate_length: None,
+            respect_print_area: false,
+            named_range: None,
+            auto_trim_region: false,
+            #[cfg(feature = "hidden")]
+            skip_hidden: false,
+            error_mode: ErrorMode::FailFast,
+            error_cell_policy: ErrorCellPolicy::default(),
+            column_error_overrides: HashMap::new(),
+            formula_results: FormulaMode::CachedValue,
+            date_system: None,
+            float_precision: None,
+            bool_string_format: None,
+            whitespace_as_null: false,
+            forward_fill_columns: Vec::new(),
+            on_replacement_char: ReplacementPolicy::default(),
+            type_sidecar_columns: false,
+            sheet_filename_sanitize: FilenameSanitize::default(),
+            sheet_order: None,
+            write_manifest: None,
+            case_insensitive_dedup: false,
+            dedup_strategy: DedupStrategy::default(),
+            header_index_base: HeaderIndexBase::default(),
+            header_detection: HeaderDetection::default(),
+            column_window: None,
+            overflow_column: None,
+            row_hash_column: None,
+            derived_columns: Vec::new(),
+            include_sheet_column: None,
+            constant_columns: HashMap::new(),
+            print_title_columns: false,
+            mask_column: None,
+            keep_mask_column: false,
+            repeated_header: None,
+            skip_title_bands: None,
+            sample: None,
+            sample_seed: None,
+            units_row: false,
+            column_descriptions: HashMap::new(),
+            output_column_order: OutputColumnOrder::default(),
+            deduplicate: None,
+            constraints: Vec::new(),
+            rejects_output: None,
+            fan_out: Vec::new(),
+            column_split: None,
+            skip_empty_columns: false,
+            collect_stats: false,
+            verify_output: false,
+            strict_batch_validation: false,
+            atomic_output: true,
+            write_buffer_size: None,
+            max_output_bytes: None,
+            preset: None,
+            writer_properties: None,
+            max_row_group_size: None,
+            one_row_group_per_batch: false,
+            coalesce_small_tail: false,
+            max_distinct_per_column: None,
+            strict_distinct_check: false,
+            single_threaded: false,
+            worker_stack_size: None,
+            spill_to_disk: false,
+            memory_pressure: None,
+            progress: None,
+            on_complete: None,
+            thread_budget: None,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+        }
+    }
+}
+
+/// 不打开任何文件，检查选项之间的冲突与依赖关系，返回发现的全部问题
+///
+/// 既包括无法转换的组合（如 `max_output_bytes` 与 `sort_by`），也包括其中一个选项不起作用的组合
+/// （如同时设置 `sheet_name` 与 `sheet_index`）。所有转换入口在打开文件前都会调用此函数，
+/// 有冲突时以合并的错误失败；单独调用可在不转换的情况下一次性拿到全部问题。
+/// 依赖表头的检查（列名是否存在、是否重名）仍在读取表头后进行
+pub fn validate_options(
+    options: &ConvertExcelToParquetOptions,
+) -> std::result::Result<(), Vec<OptionConflict>> {
+    let mut conflicts = Vec::new();
+    let mut conflict = |options: &[&'static str], message: String| {
+        conflicts.push(OptionConflict {
+            options: options.to_vec(),
+            message,
+        })
+    };
+
+    if options.named_range.is_some() {
+        for (set, name) in [
+            (options.sheet_name.is_some(), "sheet_name"),
+            (options.sheet_index.is_some(), "sheet_index"),
+            (options.respect_print_area, "respect_print_area"),
+        ] {
+            if set {
+                conflict(
+                    &["named_range", name],
+                    format!("named_range overrides {}, which has no effect", name),
+                );
+            }
+        }
+    } else if options.sheet_name.is_some() && options.sheet_index.is_some() {
+        conflict(
+            &["sheet_name", "sheet_index"],
+            "sheet_name takes precedence over sheet_index, which has no effect".to_string(),
+        );
+    }
+    if options.batch_size == Some(0) {
+        conflict(&["batch_size"], "batch_size must be at least 1".to_string());
+    }
+    if options.max_row_group_size == Some(0) {
+        conflict(
+            &["max_row_group_size"],
+            "max_row_group_size must be at least 1".to_string(),
+        );
+    }
+    if options.units_row && !header_is_first_row(options.header_detection) {
+        conflict(
+            &["units_row", "header_detection"],
+            "units_row requires header_detection to be FirstRow".to_string(),
+        );
+    }
+    if options.max_output_bytes.is_some() && options.sort_by.is_some() {
+        conflict(
+            &["max_output_bytes", "sort_by"],
+            "max_output_bytes cannot be combined with sort_by".to_string(),
+        );
+    }
+    if options.sort_by.is_some() {
+        // 排序时所有行在结束时一次写出，不存在按批次划分的 row group 与乱序批次
+        for (set, name) in [
+            (options.one_row_group_per_batch, "one_row_group_per_batch"),
+            (options.coalesce_small_tail, "coalesce_small_tail"),
+            (options.spill_to_disk, "spill_to_disk"),
+        ] {
+            if set {
+                conflict(
+                    &["sort_by", name],
+                    format!(
+                        "sort_by writes all rows at the end, so {} has no effect",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+    if options.column_window.is_some() && options.overflow_column.is_some() {
+        conflict(
+            &["column_window", "overflow_column"],
+            "column_window cannot be combined with overflow_column".to_string(),
+        );
+    }
+    if options.keep_mask_column && options.mask_column.is_none() {
+        conflict(
+            &["keep_mask_column", "mask_column"],
+            "keep_mask_column has no effect without mask_column".to_string(),
+        );
+    }
+    match options.sample {
+        Some(SampleSpec::SystematicEvery(0)) => conflict(
+            &["sample"],
+            "Sample interval must be at least 1".to_string(),
+        ),
+        Some(SampleSpec::RandomFraction(f)) if !(0.0..=1.0).contains(&f) => conflict(
+            &["sample"],
+            format!("Sample fraction {} is not between 0 and 1", f),
+        ),
+        Some(SampleSpec::RandomFraction(_)) => {}
+        _ if options.sample_seed.is_some() => conflict(
+            &["sample_seed", "sample"],
+            "sample_seed only applies to a RandomFraction sample".to_string(),
+        ),
+        _ => {}
+    }
+    if let Some(split) = &options.column_split
+        && split.max_columns <= split.key_columns.len()
+    {
+        conflict(
+            &["column_split"],
+            format!(
+                "Column split max_columns ({}) must be greater than the number of key columns ({})",
+                split.max_columns,
+                split.key_columns.len()
+            ),
+        );
+    }
+    if options.rejects_output.as_deref() == Some(options.output_path) {
+        conflict(
+            &["rejects_output", "output_path"],
+            "rejects_output must differ from output_path".to_string(),
+        );
+    }
+    for target in &options.fan_out {
+        if target.path == options.output_path
+            || options.rejects_output.as_ref() == Some(&target.path)
+        {
+            conflict(
+                &["fan_out", "output_path"],
+                format!(
+                    "fan_out path {} is already used by another output",
+                    target.path.display()
+                ),
+            );
+        }
+    }
+    if options.strict_distinct_check && options.max_distinct_per_column.is_none() {
+        conflict(
+            &["strict_distinct_check", "max_distinct_per_column"],
+            "strict_distinct_check has no effect without max_distinct_per_column".to_string(),
+        );
+    }
+    if options.writer_properties.is_some() {
+        #[cfg(feature = "encryption")]
+        let encryption = options.encryption.is_some();
+        #[cfg(not(feature = "encryption"))]
+        let encryption = false;
+        for (set, name) in [
+            (options.preset.is_some(), "preset"),
+            (options.created_by.is_some(), "created_by"),
+            (
+                options.statistics_truncate_length.is_some(),
+                "statistics_truncate_length",
+            ),
+            (encryption, "encryption"),
+        ] {
+            if set {
+                conflict(
+                    &["writer_properties", name],
+                    format!("writer_properties replaces {}, which has no effect", name),
+                );
+            }
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// 转换入口使用的 [`validate_options`]，全部冲突合并为一个错误
+pub(crate) fn check_options(options: &ConvertExcelToParquetOptions) -> Result<()> {
+    validate_options(options).map_err(|conflicts| {
+        let messages: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+        anyhow::anyhow!("Invalid options: {}", messages.join("; "))
+    })
+}
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// 非致命错误的处理方式
+///
+/// 无法打开文件、无法写入输出等致命错误在任何模式下都会中止转换。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorMode {
+    /// 默认行为：批次构建失败时立即中止；错误值单元格与超出表头范围的单元格按原有方式处理，不做记录
+    #[default]
+    FailFast,
+    /// 将非致命错误记录到 [`ConversionReport`] 并继续，失败的批次会被跳过。
+    /// 最多保留 `max_errors` 条详情，`error_count` 仍统计全部数量
+    Collect { max_errors: usize },
+}
+
+/// 错误值单元格的输出方式
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ErrorCellPolicy {
+    /// 默认行为：输出错误类型名称，如 `NA`、`Div0`
+    #[default]
+    ErrorName,
+    /// 输出为 null
+    Null,
+    /// 替换为指定的字符串
+    Replace(String),
+}
+
+/// 将若干源列按分隔符拼接得到的派生列，如由 `region` 与 `id` 组成的复合键
+#[derive(Debug, Clone)]
+pub struct DerivedColumn {
+    pub name: String,
+    /// 源列名称，按顺序拼接
+    pub source_columns: Vec<String>,
+    pub separator: String,
+    pub null_handling: NullHandling,
+}
+
+/// 派生列遇到源列为空值（null）时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullHandling {
+    /// 跳过空值及其分隔符；所有源列都为空时结果为 null
+    #[default]
+    Skip,
+    /// 将空值视为空字符串参与拼接
+    AsEmpty,
+}
+
+/// 表头行的识别方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderDetection {
+    /// 默认行为：`skip_rows` 之后的第一行是表头
+    #[default]
+    FirstRow,
+    /// 没有表头，第一行也是数据，列名全部按 `header_index_base` 生成（`Field_0`、`Field_1`……）
+    None,
+    /// 根据第一行及其下一行的单元格类型猜测：第一行含数字、布尔、日期等非文本值，
+    /// 或第一行在下一行有值的列上为空时视为数据，否则视为表头。
+    /// 两行都是完整的文本时无法区分，按有表头处理
+    Guess,
+    /// 按单元格样式识别（需启用 `styles` feature）：`skip_rows` 之后的前 10 行中，
+    /// 第一个非空单元格过半加粗的行是表头，其上的行被跳过；没有这样的行时按 `FirstRow` 处理。
+    /// 样式从 xlsx 文件中额外读取，`.xlsb` 以及从内存、压缩包或 URL 读取的工作簿按 `FirstRow` 处理
+    #[cfg(feature = "styles")]
+    ByStyle,
+}
+
+/// 含 Unicode 替换字符（U+FFFD）的单元格的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplacementPolicy {
+    /// 默认行为：原样输出
+    #[default]
+    Keep,
+    /// 整个单元格输出为 null
+    Null,
+    /// 删除替换字符，保留其余内容
+    Strip,
+    /// 视为错误：`FailFast` 下中止转换，`Collect` 下记录后原样输出
+    Error,
+}
+
+/// 行哈希列使用的算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgo {
+    /// 64 位 xxHash（种子为 0），16 个十六进制字符，速度快，不抗碰撞攻击
+    XxHash64,
+    /// SHA-256，64 个十六进制字符
+    Sha256,
+}
+
+/// 写入属性的预设，见 `ConvertExcelToParquetOptions::preset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertPreset {
+    /// 写入最快：Snappy 压缩、关闭字典编码，按大小划分 row group 时每个 row group 容纳 4 个批次。
+    /// 适合写完即被读取的临时中间文件
+    FastWrite,
+    /// 文件最小：ZSTD 级别 19，保留字典编码，写入明显变慢
+    SmallFile,
+    /// 兼容较旧的读取端：Snappy 压缩，Parquet writer version 1.0
+    Compatibility,
+}
+
+/// 按列拆分输出的方式，见 `ConvertExcelToParquetOptions::column_split`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSplit {
+    /// 每个文件最多包含的列数，含键列，须大于键列数
+    pub max_columns: usize,
+    /// 复制到每个文件中用于重新关联的列，按表头名称指定，可以为空
+    pub key_columns: Vec<String>,
+}
+
+/// `ConvertExcelToParquetOptions::fan_out` 中额外输出的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanOutFormat {
+    /// 与主输出相同的 Parquet 文件
+    Parquet,
+    /// 每行一个 JSON 对象（newline-delimited JSON），null 值写为 `null`
+    Ndjson,
+}
+
+/// 与主输出同时写出的一个额外输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanOutTarget {
+    pub format: FanOutFormat,
+    pub path: PathBuf,
+}
+
+/// 对某一列逐行校验的约束
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnConstraint {
+    /// 表头名称，只能是数据列，不含派生列与常量列
+    pub column: String,
+    pub rule: ConstraintRule,
+}
+
+/// 约束规则。null 与空字符串视为缺失值：`NotNull` 视其为违规，其余规则跳过
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintRule {
+    /// 不允许缺失值
+    NotNull,
+    /// 值须匹配该正则表达式（`regex` 语法，需要整值匹配时自行加 `^...$`）
+    Matches(String),
+    /// 值须为数字且位于闭区间 `[min, max]` 内
+    Range { min: f64, max: f64 },
+}
+
+/// 单元格的原始类型，对应 calamine `DataRef` 的变体
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellKind {
+    Int,
+    Float,
+    String,
+    Bool,
+    DateTime,
+    DurationIso,
+    DateTimeIso,
+    Error,
+    Empty,
+    /// `FormulaMode::AsFormula` 下输出的公式文本
+    Formula,
+}
+
+impl CellKind {
+    fn of(value: &calamine::DataRef) -> Self {
+        use calamine::DataRef;
+
+        match value {
+            DataRef::Int(_) => Self::Int,
+            DataRef::Float(_) => Self::Float,
+            DataRef::String(_) | DataRef::SharedString(_) => Self::String,
+            DataRef::Bool(_) => Self::Bool,
+            DataRef::DateTime(_) => Self::DateTime,
+            DataRef::DurationIso(_) => Self::DurationIso,
+            DataRef::DateTimeIso(_) => Self::DateTimeIso,
+            DataRef::Error(_) => Self::Error,
+            DataRef::Empty => Self::Empty,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Int => "Int",
+            Self::Float => "Float",
+            Self::String => "String",
+            Self::Bool => "Bool",
+            Self::DateTime => "DateTime",
+            Self::DurationIso => "DurationIso",
+            Self::DateTimeIso => "DateTimeIso",
+            Self::Error => "Error",
+            Self::Empty => "Empty",
+            Self::Formula => "Formula",
+        }
+    }
+}
+
+/// 输出批次的列布局，在表头确定后由所有 worker 共享
+struct BatchLayout {
+    /// 输出的数据列（Sheet 中的列号，升序）
+    columns: Vec<u32>,
+    /// 是否在每个数据列后紧跟一个类型列
+    type_sidecar: bool,
+    /// 溢出列收集的第一个 Sheet 列号，其后的非空单元格编码为 JSON 数组
+    overflow: Option<u32>,
+    /// 追加在末尾的派生列
+    derived: Vec<ResolvedDerivedColumn>,
+    /// 行哈希覆盖的数据列（`columns` 中的下标，按列名排序）及算法，追加在派生列之后
+    row_hash: Option<(Vec<usize>, ChecksumAlgo)>,
+    /// 追加在派生列之后的常量列的值（Sheet 名称列与 `constant_columns`）
+    constants: Vec<String>,
+    /// 掩码列（Sheet 中的列号），不一定在输出列中
+    mask: Option<u32>,
+    /// 布尔值 TRUE 输出的文本（见 `bool_string_format`），掩码列中的布尔单元格据此还原原值
+    bool_true_text: String,
+    /// 有意不输出的列（隐藏列与按 `DedupStrategy::KeepFirstDropRest` 丢弃的重复表头列，
+    /// Sheet 中的列号，升序），其中的单元格不记为超出表头范围
+    ignored_columns: Vec<u32>,
+    /// 与 `columns` 一一对应的错误值输出方式
+    error_policies: Vec<ErrorCellPolicy>,
+    /// 只含空白字符的单元格是否输出为 null
+    whitespace_as_null: bool,
+    /// 是否统计构建耗时（`collect_stats`）
+    timed: bool,
+    /// 已解析为列下标的约束
+    constraints: Vec<ResolvedConstraint>,
+    /// 启用 `rejects_output` 时拒绝行文件的 schema，被拒绝的行从批次中分出
+    rejects: Option<Arc<Schema>>,
+    /// 是否在构建批次后检查各列长度
+    strict_validation: bool,
+    /// 按 `output_column_order` 重排后各输出列在原顺序中的下标，不重排时为 `None`
+    column_order: Option<Vec<usize>>,
+}
+
+/// 已解析的约束，`column` 为 `BatchLayout::columns` 中的下标
+struct ResolvedConstraint {
+    column: usize,
+    name: String,
+    check: ConstraintCheck,
+}
+
+enum ConstraintCheck {
+    NotNull,
+    Matches(regex::Regex),
+    Range { min: f64, max: f64 },
+}
+
+impl ResolvedConstraint {
+    /// 校验一个值，违规时返回说明
+    fn violation(&self, value: Option<&str>) -> Option<String> {
+        let value = value.filter(|value| !value.is_empty());
+        match (&self.check, value) {
+            (ConstraintCheck::NotNull, None) => Some(format!("Column '{}' is null", self.name)),
+            (ConstraintCheck::Matches(pattern), Some(value)) if !pattern.is_match(value) => {
+                Some(format!(
+                    "Column '{}' value '{}' does not match '{}'",
+                    self.name,
+                    value,
+                    pattern.as_str()
+                ))
+            }
+            (ConstraintCheck::Range { min, max }, Some(value)) => match value.parse::<f64>() {
+                Ok(number) if number >= *min && number <= *max => None,
+                Ok(_) => Some(format!(
+                    "Column '{}' value '{}' is outside [{}, {}]",
+                    self.name, value, min, max
+                )),
+                Err(_) => Some(format!(
+                    "Column '{}' value '{}' is not a number",
+                    self.name, value
+                )),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 构建批次的线程共享的计数
+#[derive(Default)]
+struct BatchCounters {
+    /// 因 `mask_column` 为假值而被过滤掉的行数
+    masked_rows: AtomicUsize,
+    /// 构建批次累计耗时（纳秒）
+    build_nanos: AtomicU64,
+    /// 违反 `constraints` 的单元格数
+    constraint_violations: AtomicUsize,
+    /// 因违反 `constraints` 移入拒绝行输出的行数，不含被掩码过滤的行
+    constraint_rejected_rows: AtomicUsize,
+}
+
+/// 已解析为列下标的派生列
+struct ResolvedDerivedColumn {
+    sources: Vec<usize>,
+    separator: String,
+    null_handling: NullHandling,
+}
+
+/// 公式单元格的输出方式
+///
+/// 本工具不会重新计算公式：`CachedValue` 使用保存文件时写入的结果，
+/// 从未被计算过的公式（如部分程序生成的文件）可能为空。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormulaMode {
+    /// 默认行为：使用文件中缓存的计算结果
+    #[default]
+    CachedValue,
+    /// 输出公式文本（如 `=SUM(A1:A3)`），共享公式会展开为各单元格自己的引用，便于审计计算过程
+    AsFormula,
+}
+
+/// Excel 日期序列号的纪元
+///
+/// 早期 Mac 版 Excel 保存的文件使用 1904 纪元，同一日期的序列号比 1900 纪元小 1462。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateSystem {
+    /// 以 1899-12-30 为第 0 天（Windows 版 Excel 的默认值）
+    Excel1900,
+    /// 以 1904-01-01 为第 0 天
+    Excel1904,
+}
+
+/// 输出行的去重方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DedupMode {
+    /// 丢弃所有列都与之前某行相同的行
+    FullRow,
+    /// 按表头名称指定的键列去重，每个键组合只保留第一行
+    ByColumns(Vec<String>),
+}
+
+/// 数据中重复出现的表头行（如打印报表每页开头的表头）的识别方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepeatedHeader {
+    /// 与表头行的非空值完全相同的行
+    SameAsHeader,
+    /// 非空值依次等于给定文本的行
+    Text(Vec<String>),
+}
+
+/// 标题行的识别规则：非空单元格不超过 `max_cells` 个的行
+///
+/// 输出列数不超过 `max_cells` 的 Sheet 无法区分标题与数据，此时不跳过任何行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TitleBand {
+    pub max_cells: usize,
+    /// 要求第一个非空单元格位于第一列
+    pub require_first_column: bool,
+}
+
+impl Default for TitleBand {
+    /// 只有第一列有值的行
+    fn default() -> Self {
+        Self {
+            max_cells: 1,
+            require_first_column: true,
+        }
+    }
+}
+
+/// 数据行的抽样方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSpec {
+    /// 保留第 1、n+1、2n+1…个数据行
+    SystematicEvery(usize),
+    /// 每行以概率 f（0 到 1）独立保留，由 `sample_seed` 决定
+    RandomFraction(f64),
+}
+
+/// 按 [`SampleSpec`] 逐行决定是否保留
+struct RowSampler {
+    spec: SampleSpec,
+    seed: u64,
+    next_index: u64,
+}
+
+impl RowSampler {
+    /// `spec` 的取值范围已由 [`validate_options`] 检查
+    fn new(spec: SampleSpec, seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            current_time().map_or(0, |now| {
+                now.duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+            })
+        });
+        Self {
+            spec,
+            seed,
+            next_index: 0,
+        }
+    }
+
+    /// 下一个数据行是否保留。随机抽样对行序号做种子哈希，结果只取决于种子与行的位置
+    fn keep(&mut self) -> bool {
+        let index = self.next_index;
+        self.next_index += 1;
+        match self.spec {
+            SampleSpec::SystematicEvery(n) => index.is_multiple_of(n as u64),
+            SampleSpec::RandomFraction(f) => {
+                let hash = twox_hash::XxHash64::oneshot(self.seed, &index.to_le_bytes());
+                // 取高 53 位映射到 [0, 1)
+                ((hash >> 11) as f64 / (1u64 << 53) as f64) < f
+            }
+        }
+    }
+}
+
+/// 转换过程中记录的非致命错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionIssue {
+    /// 工作表中的行号（0 起始），无法定位到单元格时为 `None`
+    pub row: Option<u32>,
+    /// 工作表中的列号（0 起始）
+    pub col: Option<u32>,
+    pub message: String,
+}
+
+impl ConversionIssue {
+    /// 出错单元格的 A1 样式地址（如 `C42`），行或列未知时为 `None`
+    pub fn cell_address(&self) -> Option<String> {
+        Some(cell_ref::cell_address(self.row?, self.col?))
+    }
+}
+
+/// [`validate_options`] 发现的一处选项冲突
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionConflict {
+    /// 涉及的选项字段名
+    pub options: Vec<&'static str>,
+    /// 冲突原因
+    pub message: String,
+}
+
+impl std::fmt::Display for OptionConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// 单次转换的结果报告
+#[derive(Debug, Clone, Default)]
+pub struct ConversionReport {
+    pub sheet_name: String,
+    /// 转换的数据行数。`ErrorMode::Collect` 下因构建失败而被跳过的批次中的行不计入
+    pub total_rows: usize,
+    /// `ErrorMode::Collect` 下收集到的错误详情
+    pub errors: Vec<ConversionIssue>,
+    /// 发生的非致命错误总数，可能大于 `errors.len()`
+    pub error_count: usize,
+    /// 实际使用的 batch size（自动模式下为推算结果）
+    pub batch_size: usize,
+    /// 输出文件中每个 row group 的行数，取自写入完成后的 footer 元数据
+    pub row_group_rows: Vec<usize>,
+    /// 启用 `verify_output` 时，重新读取输出文件得到的行数
+    pub verified_rows: Option<usize>,
+    /// 启用 `collect_stats` 时，每个 row group 的大小统计
+    pub row_group_stats: Vec<RowGroupStats>,
+    /// 因 `mask_column` 为假值而被过滤掉的行数，已计入 `total_rows`
+    pub masked_rows: usize,
+    /// 按 `repeated_header` 跳过的重复表头行数，未计入 `total_rows`
+    pub repeated_headers_skipped: usize,
+    /// 是否将第一行用作表头，`header_detection` 为 `Guess` 时即猜测的结果
+    pub has_header: bool,
+    /// 含 Unicode 替换字符（U+FFFD）的数据单元格数
+    pub replacement_char_cells: usize,
+    /// 按 `skip_title_bands` 跳过的标题行数，未计入 `total_rows`
+    pub title_rows_skipped: usize,
+    /// 启用 `sample` 时未被抽中的数据行数，未计入 `total_rows`（即抽中的行数）
+    pub rows_not_sampled: usize,
+    /// 按 `deduplicate` 丢弃的重复行数，已计入 `total_rows`
+    pub duplicate_rows_removed: usize,
+    /// 实际写出的数据行数，扣除了被过滤、去重及因 `max_output_bytes` 丢弃的行
+    pub rows_written: usize,
+    /// 是否因达到 `max_output_bytes` 而提前停止，此时输出只含前 `rows_written` 行
+    pub output_limit_reached: bool,
+    /// `memory_pressure` 报告内存紧张、写入线程因此提前结束 row group 的次数
+    pub memory_pressure_flushes: usize,
+    /// 写出的输出文件路径：通常只有 `output_path`，启用 `column_split` 时为各部分文件（按 K 排序）。
+    /// 输出不是文件或没有生成文件时为空；不含 `fan_out` 与 `rejects_output` 的文件
+    pub output_files: Vec<PathBuf>,
+    /// `Collect` 模式下违反 `constraints` 的单元格总数，详情的样本在 `errors` 中
+    pub constraint_violations: usize,
+    /// 启用 `rejects_output` 时写入拒绝行文件的行数
+    pub rejected_rows: usize,
+    /// 启用 `units_row` 时，列名到单位的映射，不含单位为空的列
+    pub units: HashMap<String, String>,
+    /// 按 `max_distinct_per_column` 关闭了字典编码的列
+    pub high_cardinality_columns: Vec<String>,
+    /// 各列的统计概况，仅由 [`profile_only`] 填充
+    pub column_profiles: Vec<ColumnProfile>,
+    /// 启用 `collect_stats` 时，读取、构建与写入各阶段的耗时
+    pub phase_timings: Option<PhaseTimings>,
+}
+
+/// 转换各阶段的耗时，用于判断瓶颈在读取、数组构建还是压缩写出
+///
+/// 构建阶段在多个 worker 中并行执行，这里是各 worker 耗时之和，可能超过整个转换的时长。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// 读取线程解析单元格的时间，不含等待 worker 接收批次的时间
+    pub read: Duration,
+    /// 由单元格构建 `RecordBatch` 的时间，所有 worker 累计
+    pub build: Duration,
+    /// 写入线程排序、编码压缩与写出的时间
+    pub write: Duration,
+}
+
+/// 当前系统时间；`wasm32` 目标上 `SystemTime::now` 不可用，返回 `None`
+#[cfg(not(target_arch = "wasm32"))]
+fn current_time() -> Option<std::time::SystemTime> {
+    Some(std::time::SystemTime::now())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn current_time() -> Option<std::time::SystemTime> {
+    None
+}
+
+/// 阶段计时的起点，未启用计时时为 `None`，不读取时钟。
+/// wasm32 上 `Instant::now` 不可用，计时总是关闭，各阶段耗时为零
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn start_timer(enabled: bool) -> Option<Instant> {
+    enabled.then(Instant::now)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn start_timer(_enabled: bool) -> Option<Instant> {
+    None
+}
+
+/// 单个 row group 的大小统计，取自 footer 元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowGroupStats {
+    pub num_rows: usize,
+    /// 未压缩的列数据总字节数
+    pub uncompressed_bytes: u64,
+    /// 压缩后的列数据总字节数
+    pub compressed_bytes: u64,
+}
+
+impl RowGroupStats {
+    /// 压缩比（未压缩 / 压缩后），压缩后大小为 0 时返回 `None`
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.compressed_bytes > 0)
+            .then(|| self.uncompressed_bytes as f64 / self.compressed_bytes as f64)
+    }
+}
+
+/// 转换进度，由 `ConvertExcelToParquetOptions::progress` 回调报告
+///
+/// `dimensions()` 常把只有格式的空白行计算在内，直接以其行数为分母时进度会停在中途。
+/// 因此在发出前几个批次后，按已读取的数据行数与已扫过的 Sheet 行数之比推算剩余部分，
+/// 随读取不断修正分母；转换结束时分母等于实际行数。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// 已读取的数据行数
+    pub rows_read: usize,
+    /// 估计的数据总行数，不小于 `rows_read`
+    pub estimated_total_rows: usize,
+}
+
+impl Progress {
+    /// 完成比例，范围为 0.0 到 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.estimated_total_rows == 0 {
+            return 1.0;
+        }
+        self.rows_read as f64 / self.estimated_total_rows as f64
+    }
+}
+
+/// 进度回调
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// 转换完成回调，参数为输出路径与转换报告
+pub type CompletionCallback = Arc<dyn Fn(&Path, &ConversionReport) + Send + Sync>;
+
+/// 内存压力回调，返回 `true` 表示内存紧张
+pub type MemoryPressureCallback = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// 加密密钥，长度须为 16、24 或 32 字节（AES-128/192/256）
+#[cfg(feature = "encryption")]
+pub type EncryptionKey = Vec<u8>;
+
+/// Parquet 模块化加密配置
+///
+/// `footer_key` 是必需的：它加密文件 footer（schema 与统计信息），
+/// 读取方必须持有该密钥才能打开文件。`encrypt_columns` 中列出的列使用各自的密钥加密，
+/// 未列出的列不加密。
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct ParquetEncryption {
+    pub footer_key: EncryptionKey,
+    pub encrypt_columns: HashMap<String, EncryptionKey>,
+}
+
+/// 重复表头的处理方式，是否重复按 `case_insensitive_dedup` 判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupStrategy {
+    /// 重复出现的表头依次加 `_<base>`、`_<base + 1>`……后缀。默认 `base` 为 2，
+    /// 即 `id`、`id_2`、`id_3`；`base` 为 1 时为 `id`、`id_1`、`id_2`
+    NumericSuffix { base: usize },
+    /// 第 n 次出现的表头加第 n 个小写字母作为后缀，如 `id`、`id_b`、`id_c`，
+    /// 超过 26 次时与列字母一样继续为 `_aa`、`_ab`……
+    LetterSuffix,
+    /// 出现重复表头时报错
+    Error,
+    /// 只保留第一次出现的列，之后同名的列连同其数据一起丢弃
+    KeepFirstDropRest,
+}
+
+impl Default for DedupStrategy {
+    fn default() -> Self {
+        Self::NumericSuffix { base: 2 }
+    }
+}
+
+/// 输出列的顺序
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputColumnOrder {
+    /// 与 Sheet 中的列顺序一致，派生列、常量列等追加在末尾
+    #[default]
+    SheetOrder,
+    /// 按列名的字节序排序，列相同而顺序不同的 Sheet 得到相同的 schema
+    Sorted,
+    /// 列出的列按给定顺序排在最前，其余列保持原有顺序排在其后，找不到的列名报错
+    Custom(Vec<String>),
+}
+
+impl OutputColumnOrder {
+    /// 字段的新顺序（原下标），保持原顺序时为 `None`
+    fn permutation(&self, fields: &[Field]) -> Result<Option<Vec<usize>>> {
+        match self {
+            Self::SheetOrder => Ok(None),
+            Self::Sorted => {
+                let mut order: Vec<usize> = (0..fields.len()).collect();
+                order.sort_by(|&a, &b| fields[a].name().cmp(fields[b].name()));
+                Ok(Some(order))
+            }
+            Self::Custom(names) => {
+                let mut order = Vec::with_capacity(fields.len());
+                for name in names {
+                    let index = fields
+                        .iter()
+                        .position(|field| field.name() == name)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Output order column '{}' not found in headers", name)
+                        })?;
+                    if !order.contains(&index) {
+                        order.push(index);
+                    }
+                }
+                let rest: Vec<usize> = (0..fields.len())
+                    .filter(|index| !order.contains(index))
+                    .collect();
+                order.extend(rest);
+                Ok(Some(order))
+            }
+        }
+    }
+}
+
+/// 空表头列的命名方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderIndexBase {
+    /// `Field_<i>`，`i` 为相对数据区域第一列的偏移（0 起始）
+    #[default]
+    RegionRelative,
+    /// `Field_<列字母>`，使用工作表中的实际列字母，如 `Field_D`
+    ColumnLetter,
+}
+
+/// Sheet 名称中含有文件名非法字符（`/ \ : * ? " < > |` 及控制字符）时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilenameSanitize {
+    /// 将非法字符替换为指定字符
+    Replace(char),
+    /// 使用 Sheet 名称的哈希（16 位十六进制）作为文件名，适用于名称不可读或过长的情况
+    Hash,
+    /// 遇到非法字符时返回错误
+    Error,
+}
+
+impl Default for FilenameSanitize {
+    fn default() -> Self {
+        Self::Replace('_')
+    }
 }
 
+/// 单个 Sheet 的选项覆盖，未设置的字段沿用基础选项
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOverrides {
+    pub skip_rows: Option<usize>,
+    pub batch_size: Option<usize>,
+}
+
+/// 默认写入 footer 的 `created_by`
+pub const DEFAULT_CREATED_BY: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    " version ",
+    env!("CARGO_PKG_VERSION")
+);
+
+/// `units_row` 中单位写入的 Parquet 字段元数据键
+pub const UNIT_METADATA_KEY: &str = "unit";
+
+/// `column_descriptions` 中说明写入的 Parquet 字段元数据键
+pub const DESCRIPTION_METADATA_KEY: &str = "description";
+
+/// `rejects_output` 文件中记录拒绝原因的列名，违反多条约束时原因以 `; ` 分隔
+pub const REJECT_REASON_COLUMN: &str = "reject_reason";
+/// 因 `mask_column` 为假值被拒绝的行的原因
+const MASK_REJECT_REASON: &str = "Mask column is falsy";
+
+/// 自动 batch size 的目标：每个 row group 未压缩约 128MB
+const AUTO_BATCH_TARGET_BYTES: usize = 128 * 1024 * 1024;
+/// 自动模式下用于估算行宽的采样行数
+const AUTO_BATCH_SAMPLE_ROWS: usize = 1000;
+/// 自动 batch size 的上下限。上限偏保守：通道中最多同时有十几个批次，
+/// 单个批次过大时常驻内存会成倍增长
+const AUTO_BATCH_MIN_ROWS: usize = 1000;
+const AUTO_BATCH_MAX_ROWS: usize = 64 * 1024;
+/// `ConvertPreset::FastWrite` 下每个 row group 容纳的批次数
+const FAST_WRITE_BATCHES_PER_ROW_GROUP: usize = 4;
+/// 没有可采样数据时使用的 batch size
+const DEFAULT_BATCH_SIZE: usize = 5000;
+/// `coalesce_small_tail` 下，行数少于 `batch_size / COALESCE_TAIL_DIVISOR` 的末尾批次会被合并
+const COALESCE_TAIL_DIVISOR: usize = 10;
+/// 1904 纪元与 1900 纪元序列号之间相差的天数
+const EXCEL_1904_OFFSET_DAYS: f64 = 1462.0;
+/// 发出这么多批次后才按数据行密度修正进度的总行数估计
+const PROGRESS_SAMPLE_BATCHES: usize = 3;
+
 // 类型定义
-type RawCell = (u32, u32, String);
+type RawCell = (u32, u32, String, CellKind);
 type RawBatch = (usize, Vec<RawCell>);
 type ProcessedBatch = (usize, RecordBatch);
 
-/// 将 Xlsx 文件转换为 Parquet
-pub fn convert_xlsx_to_parquet(options: ConvertExcelToParquetOptions) -> Result<()> {
-    println!(
-        "Starting conversion for (XLSX): {}",
-        options.excel_file.display()
-    );
-    let mut workbook: Xlsx<_> =
-        open_workbook(options.excel_file).context("Failed to open Excel file")?;
+/// 行号连续、单元格所在列与类型都相同的若干空白行。只记录行数，
+/// 确认不在末尾时才展开为单元格，末尾的大片格式填充因此不占内存
+#[derive(Debug, PartialEq)]
+struct BlankRows {
+    first_row: u32,
+    count: u32,
+    cells: Vec<(u32, CellKind)>,
+}
+
+/// 完全在内存中转换：从 `reader` 读取工作簿，返回 Parquet 文件的字节与转换报告
+///
+/// 不访问文件系统，`output_path`、`atomic_output`、`verify_output`、`column_split` 不起作用，
+/// `options.excel_file` 仅用于日志输出。配合 `wasm32` 目标上自动启用的单线程模式，
+/// 可在浏览器等没有文件与线程的环境中使用。
+pub fn convert_reader_to_bytes<RS: Read + Seek>(
+    reader: RS,
+    format: ExcelFormat,
+    options: ConvertExcelToParquetOptions,
+) -> Result<(Vec<u8>, ConversionReport)> {
+    check_options(&options)?;
+    let buffer = SharedBuffer::default();
+    let target = OutputTarget::Memory(buffer.clone());
+    let report = match format {
+        ExcelFormat::Xlsx => {
+            let workbook = Xlsx::new(reader).context("Failed to open Excel file")?;
+            convert_workbook(workbook, None, &options, target)?
+        }
+        ExcelFormat::Xlsb => {
+            let workbook = Xlsb::new(reader).context("Failed to open Excel file")?;
+            convert_workbook(workbook, None, &options, target)?
+        }
+        #[cfg(feature = "html")]
+        ExcelFormat::Html => {
+            let mut reader = reader;
+            let range = html::read_html(&mut reader, None, options.excel_file.display())?;
+            convert_range_to_target(&range, &options, target)?
+        }
+    };
+    Ok((buffer.take(), report))
+}
+
+/// 将 Xlsx 文件转换为 Parquet
+pub fn convert_xlsx_to_parquet(options: ConvertExcelToParquetOptions) -> Result<ConversionReport> {
+    check_options(&options)?;
+    info!(
+        "Starting conversion for (XLSX): {}",
+        options.excel_file.display()
+    );
+    let workbook: Xlsx<_> = {
+        let _span = enter_span!("open", file = %options.excel_file.display());
+        open_workbook(options.excel_file).context("Failed to open Excel file")?
+    };
+    convert_workbook(
+        workbook,
+        Some(options.excel_file),
+        &options,
+        OutputTarget::Parquet,
+    )
+}
+
+/// 将 Xlsb 文件转换为 Parquet
+pub fn convert_xlsb_to_parquet(options: ConvertExcelToParquetOptions) -> Result<ConversionReport> {
+    check_options(&options)?;
+    info!(
+        "Starting conversion for (XLSB): {}",
+        options.excel_file.display()
+    );
+    let workbook: Xlsb<_> = {
+        let _span = enter_span!("open", file = %options.excel_file.display());
+        open_workbook(options.excel_file).context("Failed to open Excel file")?
+    };
+    convert_workbook(workbook, None, &options, OutputTarget::Parquet)
+}
+
+/// 从任意 `Read + Seek` 数据源（如内存缓冲区）读取工作簿并转换为 Parquet
+///
+/// 格式由 `format` 指定，`options.excel_file` 仅用于日志输出。
+pub fn convert_reader_to_parquet<RS: Read + Seek>(
+    reader: RS,
+    format: ExcelFormat,
+    options: ConvertExcelToParquetOptions,
+) -> Result<ConversionReport> {
+    check_options(&options)?;
+    info!(
+        "Starting conversion for ({:?}): {}",
+        format,
+        options.excel_file.display()
+    );
+    match format {
+        ExcelFormat::Xlsx => {
+            let workbook = {
+                let _span = enter_span!("open", file = %options.excel_file.display());
+                Xlsx::new(reader).context("Failed to open Excel file")?
+            };
+            convert_workbook(workbook, None, &options, OutputTarget::Parquet)
+        }
+        ExcelFormat::Xlsb => {
+            let workbook = {
+                let _span = enter_span!("open", file = %options.excel_file.display());
+                Xlsb::new(reader).context("Failed to open Excel file")?
+            };
+            convert_workbook(workbook, None, &options, OutputTarget::Parquet)
+        }
+        #[cfg(feature = "html")]
+        ExcelFormat::Html => {
+            let mut reader = reader;
+            let range = html::read_html(&mut reader, None, options.excel_file.display())?;
+            convert_range_to_target(&range, &options, OutputTarget::Parquet)
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "convert_sheet", skip_all, fields(file = %options.excel_file.display(), sheet))
+)]
+#[cfg_attr(
+    not(any(feature = "hidden", feature = "styles")),
+    allow(unused_variables)
+)]
+fn convert_workbook<W, RS>(
+    mut workbook: W,
+    // 工作簿所在的文件，用于读取 calamine 不解析的隐藏行列；从内存读取时为 `None`
+    source_file: Option<&Path>,
+    options: &ConvertExcelToParquetOptions,
+    target: OutputTarget,
+) -> Result<ConversionReport>
+where
+    W: StreamingReader<RS>,
+    RS: Read + Seek,
+{
+    // Get sheet name using Reader trait
+    let sheet_name = get_sheet_name(&workbook, options)?;
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("sheet", sheet_name.as_str());
+    info!("Processing sheet: {}", sheet_name);
+    let print_area = get_print_area(&workbook, options, &sheet_name);
+    let mut formulas = match options.formula_results {
+        FormulaMode::AsFormula => read_formulas(&mut workbook, &sheet_name)?,
+        FormulaMode::CachedValue => HashMap::new(),
+    };
+
+    let dimensions = workbook.sheet_dimensions(&sheet_name)?;
+    let region = if options.auto_trim_region {
+        // 预扫描一遍，取（打印区域内）非空单元格的最小外接矩形
+        let mut bounds = None;
+        workbook.for_each_cell(&sheet_name, |row, col, value| {
+            if print_area.is_none_or(|area| area.contains(row, col))
+                && (formulas.contains_key(&(row, col)) || !cell_to_string(value).is_empty())
+            {
+                extend_bounds(&mut bounds, row, col);
+            }
+            Ok(true)
+        })?;
+        bounds.or(print_area)
+    } else {
+        print_area
+    };
+    let mut context = ConversionContext::new(options, dimensions, region)?
+        .with_sheet_name(&sheet_name)
+        .with_target(target);
+
+    #[cfg(feature = "hidden")]
+    if options.skip_hidden {
+        if W::FORMAT == ExcelFormat::Xlsb {
+            anyhow::bail!("skip_hidden is not supported for .xlsb files");
+        }
+        let path = source_file.ok_or_else(|| {
+            anyhow::anyhow!(
+                "skip_hidden needs the workbook file and is not supported for workbooks read from memory, a zip archive or a URL"
+            )
+        })?;
+        context.set_hidden(hidden::read_hidden(path, &sheet_name)?);
+    }
+
+    #[cfg(feature = "styles")]
+    if options.header_detection == HeaderDetection::ByStyle {
+        match source_file.filter(|_| W::FORMAT == ExcelFormat::Xlsx) {
+            Some(path) => {
+                let first_row = context.header_row_idx;
+                match styles::find_bold_row(path, &sheet_name, first_row)? {
+                    Some(row) => context.set_header_row(row),
+                    None => warn!(
+                        "No bold header row in sheet '{}', using the first row",
+                        sheet_name
+                    ),
+                }
+            }
+            None => warn!("Cell styles are only read from .xlsx files, using the first row"),
+        }
+    }
+
+    if let Some(rows) = get_print_title_rows(&workbook, options, &sheet_name) {
+        context.set_print_titles(read_print_titles(&mut workbook, &sheet_name, rows)?);
+    }
+
+    if options.skip_empty_columns {
+        mark_non_empty_columns(&mut workbook, &sheet_name, &formulas, &mut context)?;
+    }
+
+    workbook.for_each_cell(&sheet_name, |row, col, value| {
+        if context.output_limit_reached() {
+            return Ok(false);
+        }
+        match formulas.remove(&(row, col)) {
+            Some(formula) => context.process_cell(row, col, formula, CellKind::Formula)?,
+            None => context.read_cell(row, col, value)?,
+        }
+        Ok(true)
+    })?;
+
+    let report = context.finish(sheet_name)?;
+
+    info!(
+        "Successfully converted {} to {} (processed ~{} rows)",
+        options.excel_file.to_string_lossy(),
+        options.output_path.to_string_lossy(),
+        report.total_rows
+    );
+
+    Ok(report)
+}
+
+/// 只统计各列的概况（null 个数、最小/最大值、不同值个数），不写出 Parquet 文件
+///
+/// 读取、表头与列的处理和转换完全一致，格式由 `options.excel_file` 的扩展名决定；
+/// `output_path`、`sort_by`、`verify_output`、`atomic_output`、`column_split` 不起作用。
+/// 比先转换再读取输出文件的统计信息省去了编码与磁盘写入。
+pub fn profile_only(options: ConvertExcelToParquetOptions) -> Result<Vec<ColumnProfile>> {
+    info!("Starting profiling for: {}", options.excel_file.display());
+    let report = convert_file_to_target(&options, OutputTarget::Profile)?;
+    Ok(report.column_profiles)
+}
+
+/// 按扩展名打开 `options.excel_file` 并转换到指定目标
+fn convert_file_to_target(
+    options: &ConvertExcelToParquetOptions,
+    target: OutputTarget,
+) -> Result<ConversionReport> {
+    check_options(options)?;
+    match ExcelFormat::from_path(options.excel_file)? {
+        ExcelFormat::Xlsx => {
+            let workbook: Xlsx<_> = {
+                let _span = enter_span!("open", file = %options.excel_file.display());
+                open_workbook(options.excel_file).context("Failed to open Excel file")?
+            };
+            convert_workbook(workbook, Some(options.excel_file), options, target)
+        }
+        ExcelFormat::Xlsb => {
+            let workbook: Xlsb<_> = {
+                let _span = enter_span!("open", file = %options.excel_file.display());
+                open_workbook(options.excel_file).context("Failed to open Excel file")?
+            };
+            convert_workbook(workbook, Some(options.excel_file), options, target)
+        }
+        #[cfg(feature = "html")]
+        ExcelFormat::Html => {
+            let mut file = File::open(options.excel_file).context("Failed to open HTML file")?;
+            let range = html::read_html(&mut file, None, options.excel_file.display())?;
+            convert_range_to_target(&range, options, target)
+        }
+    }
+}
+
+/// 转换调用方已加载的 `calamine::Range`（如 `worksheet_range` 的结果或自行预处理后的数据）
+///
+/// 区域的起止位置视为 Sheet 范围，表头、`skip_rows`、`batch_size` 等处理与流式转换一致；
+/// 与文件读取相关的 `sheet_name`、`sheet_index`、`respect_print_area`、`formula_results`
+/// 不起作用。`Range` 不保留空单元格，因此仅有格式的空单元格会输出为 null 而不是空字符串。
+pub fn convert_range_to_parquet(
+    range: &Range<Data>,
+    options: ConvertExcelToParquetOptions,
+) -> Result<ConversionReport> {
+    convert_range_to_target(range, &options, OutputTarget::Parquet)
+}
+
+/// 将 `Range` 转换到指定目标
+fn convert_range_to_target(
+    range: &Range<Data>,
+    options: &ConvertExcelToParquetOptions,
+    target: OutputTarget,
+) -> Result<ConversionReport> {
+    let (Some(start), Some(end)) = (range.start(), range.end()) else {
+        return Err(anyhow::anyhow!("Range is empty"));
+    };
+    let mut context = ConversionContext::new(options, calamine::Dimensions::new(start, end), None)?
+        .with_target(target);
+
+    if options.skip_empty_columns {
+        for (row, col, _) in range.used_cells() {
+            context.mark_non_empty(start.0 + row as u32, start.1 + col as u32);
+        }
+        context.drop_empty_columns();
+    }
+
+    for (row, col, value) in range.used_cells() {
+        if context.output_limit_reached() {
+            break;
+        }
+        context.read_cell(
+            start.0 + row as u32,
+            start.1 + col as u32,
+            &data_as_ref(value),
+        )?;
+    }
+
+    context.finish(options.sheet_name.clone().unwrap_or_default())
+}
+
+/// 将工作簿中的所有 Sheet 分别转换为 Parquet
+///
+/// `options.output_path` 作为输出目录，每个 Sheet 写入 `<output_path>/<sheet>.parquet`，
+/// 文件名按 `sheet_filename_sanitize` 处理；处理后重名（不区分大小写）的 Sheet 以 `_<Sheet 下标>` 区分。
+/// `sheet_name` / `sheet_index` 会被忽略。`overrides` 按 Sheet 名称提供选项覆盖，
+/// 不在其中的 Sheet 使用基础选项。设置 `sheet_order` 时只按其顺序转换列出的 Sheet。
+/// 返回按转换顺序排列的输出文件路径（启用 `column_split` 时为各部分文件）。
+pub fn convert_all_sheets_to_parquet(
+    options: ConvertExcelToParquetOptions,
+    overrides: Option<HashMap<String, ConvertOverrides>>,
+) -> Result<Vec<PathBuf>> {
+    let output_dir = options.output_path;
+    let sanitize = options.sheet_filename_sanitize;
+    let mut used_names = HashSet::new();
+
+    convert_sheets(&options, overrides, |sheet_idx, sheet_name| {
+        let base_name = sanitize_sheet_filename(sheet_name, sanitize)?;
+        let mut file_name = base_name.clone();
+        let mut suffix = sheet_idx;
+        while !used_names.insert(file_name.to_lowercase()) {
+            file_name = format!("{}_{}", base_name, suffix);
+            suffix += 1;
+        }
+        std::fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+        Ok(output_dir.join(format!("{}.parquet", file_name)))
+    })
+}
+
+/// 与 [`convert_all_sheets_to_parquet`] 相同，但由 `output_for` 根据 Sheet 名称决定输出路径
+///
+/// `output_for` 在每个 Sheet 转换前调用一次，返回路径的上级目录会自动创建；
+/// `options.output_path` 与 `sheet_filename_sanitize` 不起作用。
+pub fn convert_all_sheets_to_parquet_with<F>(
+    options: ConvertExcelToParquetOptions,
+    overrides: Option<HashMap<String, ConvertOverrides>>,
+    output_for: F,
+) -> Result<Vec<PathBuf>>
+where
+    F: Fn(&str) -> PathBuf,
+{
+    convert_sheets(&options, overrides, |_, sheet_name| {
+        let output_path = output_for(sheet_name);
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create output directory")?;
+        }
+        Ok(output_path)
+    })
+}
+
+/// 将多个命名区域分别转换为 Parquet，`named_ranges` 为（区域名称, 输出路径）
+///
+/// 每个区域按 `named_range` 的方式单独读取一遍工作簿，区域可以位于同一 Sheet 且相互重叠，
+/// 各自独立转换。`options.output_path`、`named_range`、`sheet_name`、`sheet_index` 不起作用。
+/// 返回与 `named_ranges` 顺序一致的转换报告，行数见各报告的 `total_rows`。
+pub fn convert_named_ranges_to_parquet(
+    options: ConvertExcelToParquetOptions,
+    named_ranges: &[(String, PathBuf)],
+) -> Result<Vec<ConversionReport>> {
+    check_options(&options)?;
+    let format = ExcelFormat::from_path(options.excel_file)?;
+    #[cfg(feature = "html")]
+    if format == ExcelFormat::Html {
+        anyhow::bail!("HTML tables have no named ranges");
+    }
+    let mut reports = Vec::with_capacity(named_ranges.len());
+    for (name, output_path) in named_ranges {
+        let range_options = ConvertExcelToParquetOptions {
+            output_path,
+            named_range: Some(name.clone()),
+            sheet_name: None,
+            sheet_index: None,
+            ..options.clone()
+        };
+        let report = match format {
+            ExcelFormat::Xlsx => convert_xlsx_to_parquet(range_options),
+            ExcelFormat::Xlsb => convert_xlsb_to_parquet(range_options),
+            #[cfg(feature = "html")]
+            ExcelFormat::Html => unreachable!(),
+        }
+        .with_context(|| format!("Failed to convert named range '{}'", name))?;
+        info!("Named range '{}': {} rows", name, report.total_rows);
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// 依次转换所有 Sheet，`output_for` 接收 Sheet 下标与名称并返回输出路径
+fn convert_sheets(
+    options: &ConvertExcelToParquetOptions,
+    overrides: Option<HashMap<String, ConvertOverrides>>,
+    mut output_for: impl FnMut(usize, &str) -> Result<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    check_options(options)?;
+    let format = ExcelFormat::from_path(options.excel_file)?;
+    let sheet_names = match format {
+        ExcelFormat::Xlsx => {
+            let workbook: Xlsx<_> =
+                open_workbook(options.excel_file).context("Failed to open Excel file")?;
+            workbook.sheet_names()
+        }
+        ExcelFormat::Xlsb => {
+            let workbook: Xlsb<_> =
+                open_workbook(options.excel_file).context("Failed to open Excel file")?;
+            workbook.sheet_names()
+        }
+        #[cfg(feature = "html")]
+        ExcelFormat::Html => anyhow::bail!("HTML tables have no sheets to convert one by one"),
+    };
+
+    let sheets: Vec<(usize, String)> = match &options.sheet_order {
+        Some(order) => order
+            .iter()
+            .map(|name| {
+                sheet_names
+                    .iter()
+                    .position(|sheet_name| sheet_name == name)
+                    .map(|sheet_idx| (sheet_idx, name.clone()))
+                    .ok_or_else(|| anyhow::anyhow!("Sheet '{}' in sheet_order not found", name))
+            })
+            .collect::<Result<_>>()?,
+        None => sheet_names.into_iter().enumerate().collect(),
+    };
+
+    let overrides = overrides.unwrap_or_default();
+    let mut outputs = Vec::with_capacity(sheets.len());
+    let mut manifest = Vec::with_capacity(sheets.len());
+
+    for (sheet_idx, sheet_name) in sheets {
+        let output_path = output_for(sheet_idx, &sheet_name)?;
+        let sheet_override = overrides.get(&sheet_name).cloned().unwrap_or_default();
+
+        let sheet_options = ConvertExcelToParquetOptions {
+            output_path: &output_path,
+            skip_rows: sheet_override.skip_rows.unwrap_or(options.skip_rows),
+            batch_size: sheet_override.batch_size.or(options.batch_size),
+            sheet_name: Some(sheet_name.clone()),
+            sheet_index: None,
+            ..options.clone()
+        };
+
+        let report = match format {
+            ExcelFormat::Xlsx => convert_xlsx_to_parquet(sheet_options),
+            ExcelFormat::Xlsb => convert_xlsb_to_parquet(sheet_options),
+            #[cfg(feature = "html")]
+            ExcelFormat::Html => unreachable!(),
+        }
+        .with_context(|| format!("Failed to convert sheet '{}'", sheet_name))?;
+
+        if options.write_manifest.is_some() {
+            // 按列拆分时不写出 `output_path` 本身，每个部分文件各占一项，行数相同
+            for path in &report.output_files {
+                let bytes = std::fs::metadata(path)
+                    .context("Failed to read output file size")?
+                    .len();
+                manifest.push(serde_json::json!({
+                    "path": path.to_string_lossy(),
+                    "sheet": sheet_name,
+                    "rows": report.row_group_rows.iter().sum::<usize>(),
+                    "bytes": bytes,
+                }));
+            }
+        }
+        outputs.extend(report.output_files);
+    }
+
+    if let Some(manifest_path) = &options.write_manifest {
+        let manifest = serde_json::to_string_pretty(&serde_json::json!({ "files": manifest }))?;
+        std::fs::write(manifest_path, manifest).context("Failed to write manifest")?;
+    }
+
+    Ok(outputs)
+}
+
+/// 将 Sheet 名称转为可用的文件名（不含扩展名）
+fn sanitize_sheet_filename(sheet_name: &str, mode: FilenameSanitize) -> Result<String> {
+    let is_invalid = |c: char| {
+        matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+    };
+    // `.`、`..` 及空名称不能作为文件名
+    let is_reserved = |name: &str| name.is_empty() || name.chars().all(|c| c == '.');
+
+    match mode {
+        FilenameSanitize::Replace(replacement) => {
+            let name: String = sheet_name
+                .chars()
+                .map(|c| if is_invalid(c) { replacement } else { c })
+                .collect();
+            if is_reserved(&name) {
+                Ok(name.replace('.', &replacement.to_string()) + &replacement.to_string())
+            } else {
+                Ok(name)
+            }
+        }
+        FilenameSanitize::Hash => {
+            // FNV-1a，保证不同版本和平台下结果一致
+            let hash = sheet_name
+                .bytes()
+                .fold(0xcbf29ce484222325u64, |hash, byte| {
+                    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+                });
+            Ok(format!("{:016x}", hash))
+        }
+        FilenameSanitize::Error => {
+            if sheet_name.chars().any(is_invalid) || is_reserved(sheet_name) {
+                Err(anyhow::anyhow!(
+                    "Sheet name '{}' is not a valid file name",
+                    sheet_name
+                ))
+            } else {
+                Ok(sheet_name.to_string())
+            }
+        }
+    }
+}
+
+/// calamine 的 `Xlsx` 与 `Xlsb` 各自提供、但不属于 `Reader` trait 的流式读取，
+/// 让两种格式共用同一套读取代码
+trait StreamingReader<RS: Read + Seek>: Reader<RS> {
+    /// 工作簿的格式
+    #[cfg_attr(not(any(feature = "hidden", feature = "styles")), allow(dead_code))]
+    const FORMAT: ExcelFormat;
+
+    /// Sheet 的范围（来自工作表的 `dimension` 记录，不读取单元格）
+    fn sheet_dimensions(&mut self, sheet_name: &str) -> Result<calamine::Dimensions>;
+
+    /// 依次将 Sheet 中的公式交给 `f`，参数为 (row, col) 与公式文本（不含 `=`，可能为空）
+    fn for_each_formula(&mut self, sheet_name: &str, f: impl FnMut((u32, u32), &str))
+    -> Result<()>;
+
+    /// 依次将 Sheet 中的单元格交给 `f`，`f` 返回 `false` 时停止读取
+    fn for_each_cell(
+        &mut self,
+        sheet_name: &str,
+        f: impl FnMut(u32, u32, &calamine::DataRef) -> Result<bool>,
+    ) -> Result<()>;
+}
+
+macro_rules! impl_streaming_reader {
+    ($($workbook:ident),*) => {$(
+        impl<RS: Read + Seek> StreamingReader<RS> for $workbook<RS> {
+            const FORMAT: ExcelFormat = ExcelFormat::$workbook;
+
+            fn sheet_dimensions(&mut self, sheet_name: &str) -> Result<calamine::Dimensions> {
+                Ok(self
+                    .worksheet_cells_reader(sheet_name)
+                    .context("Failed to get worksheet cells reader")?
+                    .dimensions())
+            }
+
+            fn for_each_formula(
+                &mut self,
+                sheet_name: &str,
+                mut f: impl FnMut((u32, u32), &str),
+            ) -> Result<()> {
+                let mut cells_reader = self
+                    .worksheet_cells_reader(sheet_name)
+                    .context("Failed to get worksheet cells reader")?;
+                while let Some(cell) = cells_reader
+                    .next_formula()
+                    .context("Failed to read formula")?
+                {
+                    f(cell.get_position(), cell.get_value());
+                }
+                Ok(())
+            }
+
+            fn for_each_cell(
+                &mut self,
+                sheet_name: &str,
+                mut f: impl FnMut(u32, u32, &calamine::DataRef) -> Result<bool>,
+            ) -> Result<()> {
+                let mut cells_reader = self
+                    .worksheet_cells_reader(sheet_name)
+                    .context("Failed to get worksheet cells reader")?;
+                while let Some(cell) = cells_reader.next_cell().context("Failed to read cell")? {
+                    let (row, col) = cell.get_position();
+                    if !f(row, col, cell.get_value())? {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_streaming_reader!(Xlsx, Xlsb);
+
+/// 预先读取 Sheet 中所有公式的文本，键为 (row, col)
+///
+/// 逐个流式读取而不是用 `Reader::worksheet_formula`，后者会按 Sheet 范围分配稠密的 `Range`。
+fn read_formulas<W, RS>(workbook: &mut W, sheet_name: &str) -> Result<HashMap<(u32, u32), String>>
+where
+    W: StreamingReader<RS>,
+    RS: Read + Seek,
+{
+    let mut formulas = HashMap::new();
+    workbook.for_each_formula(sheet_name, |position, formula| {
+        if !formula.is_empty() {
+            formulas.insert(position, format!("={}", formula));
+        }
+    })?;
+    Ok(formulas)
+}
+
+/// `skip_empty_columns`：预扫描一遍，标记含有非空值的列，再去掉其余的列
+fn mark_non_empty_columns<W, RS>(
+    workbook: &mut W,
+    sheet_name: &str,
+    formulas: &HashMap<(u32, u32), String>,
+    context: &mut ConversionContext<'_>,
+) -> Result<()>
+where
+    W: StreamingReader<RS>,
+    RS: Read + Seek,
+{
+    workbook.for_each_cell(sheet_name, |row, col, value| {
+        if formulas.contains_key(&(row, col)) || !cell_to_string(value).is_empty() {
+            context.mark_non_empty(row, col);
+        }
+        Ok(true)
+    })?;
+    context.drop_empty_columns();
+    Ok(())
+}
+
+/// 读取打印标题行 `first..=last` 中的非空单元格，返回 (row, col, 文本)
+///
+/// 打印标题行在工作表开头附近，读过最后一个标题行即可停止
+fn read_print_titles<W, RS>(
+    workbook: &mut W,
+    sheet_name: &str,
+    (first, last): (u32, u32),
+) -> Result<Vec<(u32, u32, String)>>
+where
+    W: StreamingReader<RS>,
+    RS: Read + Seek,
+{
+    let mut titles = Vec::new();
+    workbook.for_each_cell(sheet_name, |row, col, value| {
+        if row > last {
+            return Ok(false);
+        }
+        let value = cell_to_string(value);
+        if row >= first && !value.is_empty() {
+            titles.push((row, col, value));
+        }
+        Ok(true)
+    })?;
+    Ok(titles)
+}
+
+/// 支持的 Excel 格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExcelFormat {
+    Xlsx,
+    Xlsb,
+    /// 以电子表格扩展名保存的 HTML 表格（需启用 `html` feature），转换文档中的第一个表格
+    #[cfg(feature = "html")]
+    Html,
+}
+
+impl ExcelFormat {
+    /// 根据文件扩展名判断格式
+    ///
+    /// 扩展名不是 `.xlsx`/`.xlsb` 时（如旧系统导出的 `.xls`），启用 `html` feature 后读取文件开头，
+    /// 跳过 BOM 与空白后以 `<` 开头的按 [`ExcelFormat::Html`] 处理
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let format = Self::from_extension(path);
+        #[cfg(feature = "html")]
+        if format.is_err() && html::sniff_file(path) {
+            return Ok(Self::Html);
+        }
+        format
+    }
+
+    /// 只根据扩展名判断，不读取文件（用于压缩包条目、URL 等不在本地的名称）
+    pub(crate) fn from_extension(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+
+        match extension.as_deref() {
+            Some("xlsx") => Ok(Self::Xlsx),
+            Some("xlsb") => Ok(Self::Xlsb),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported file extension. Please use .xlsx or .xlsb"
+            )),
+        }
+    }
+}
+
+// 辅助函数：获取 Sheet Name
+fn get_sheet_name<R, RS>(workbook: &R, options: &ConvertExcelToParquetOptions) -> Result<String>
+where
+    R: Reader<RS>,
+    RS: Read + Seek,
+{
+    if let Some(name) = &options.named_range {
+        find_named_range(workbook, name).map(|(sheet_name, _)| sheet_name)
+    } else if let Some(sheet_name) = &options.sheet_name {
+        Ok(sheet_name.clone())
+    } else if let Some(index) = options.sheet_index {
+        workbook
+            .sheet_names()
+            .get(index)
+            .context(format!("Sheet index {} out of bounds", index))
+            .cloned()
+    } else {
+        workbook
+            .sheet_names()
+            .first()
+            .context("No worksheets found")
+            .cloned()
+    }
+}
+
+/// 将 (row, col) 并入外接矩形 `bounds`
+fn extend_bounds(bounds: &mut Option<calamine::Dimensions>, row: u32, col: u32) {
+    *bounds = Some(match bounds.take() {
+        Some(b) => calamine::Dimensions::new(
+            (b.start.0.min(row), b.start.1.min(col)),
+            (b.end.0.max(row), b.end.1.max(col)),
+        ),
+        None => calamine::Dimensions::new((row, col), (row, col)),
+    });
+}
+
+// 辅助函数：启用 `respect_print_area` 时读取 Sheet 的打印区域
+//
+// calamine 只暴露工作簿的 defined names，因此这里解析 `_xlnm.Print_Area`；
+// 冻结窗格等视图信息 calamine 并不提供。存在多个打印区域时只取第一个。
+fn get_print_area<R, RS>(
+    workbook: &R,
+    options: &ConvertExcelToParquetOptions,
+    sheet_name: &str,
+) -> Option<calamine::Dimensions>
+where
+    R: Reader<RS>,
+    RS: Read + Seek,
+{
+    if let Some(name) = &options.named_range {
+        return find_named_range(workbook, name).ok().map(|(_, area)| area);
+    }
+    if !options.respect_print_area {
+        return None;
+    }
+
+    let area = workbook
+        .defined_names()
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("_xlnm.Print_Area"))
+        .filter_map(|(_, formula)| formula.split(',').next().and_then(cell_ref::parse_area))
+        .find(|(sheet, _)| sheet.as_deref() == Some(sheet_name))
+        .map(|(_, area)| area);
+
+    if area.is_none() {
+        warn!(
+            "No print area defined for sheet '{}', using sheet dimensions",
+            sheet_name
+        );
+    }
+    area
+}
+
+// 辅助函数：启用 `print_title_columns` 时读取 Sheet 打印标题的行范围（0 起始，含两端）
+//
+// 打印标题同样是 defined name（`_xlnm.Print_Titles`），可能同时包含行与列的引用，只取整行的引用。
+fn get_print_title_rows<R, RS>(
+    workbook: &R,
+    options: &ConvertExcelToParquetOptions,
+    sheet_name: &str,
+) -> Option<(u32, u32)>
+where
+    R: Reader<RS>,
+    RS: Read + Seek,
+{
+    if !options.print_title_columns {
+        return None;
+    }
+
+    let rows = workbook
+        .defined_names()
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("_xlnm.Print_Titles"))
+        .flat_map(|(_, formula)| formula.split(',').filter_map(cell_ref::parse_area))
+        .find(|(sheet, area)| sheet.as_deref() == Some(sheet_name) && cell_ref::is_whole_rows(area))
+        .map(|(_, area)| (area.start.0, area.end.0));
+
+    if rows.is_none() {
+        warn!("No print title rows defined for sheet '{}'", sheet_name);
+    }
+    rows
+}
+
+/// 查找工作簿中的命名区域，返回其所在 Sheet 与区域
+fn find_named_range<R, RS>(workbook: &R, name: &str) -> Result<(String, calamine::Dimensions)>
+where
+    R: Reader<RS>,
+    RS: Read + Seek,
+{
+    let (_, formula) = workbook
+        .defined_names()
+        .iter()
+        .find(|(defined, _)| defined.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow::anyhow!("Named range '{}' not found", name))?;
+    match formula.split(',').next().and_then(cell_ref::parse_area) {
+        Some((Some(sheet), area)) => Ok((sheet, area)),
+        _ => Err(anyhow::anyhow!(
+            "Named range '{}' does not refer to a sheet area: {}",
+            name,
+            formula
+        )),
+    }
+}
+
+/// 在各线程间共享的非致命错误收集器
+#[derive(Clone)]
+struct IssueCollector {
+    mode: ErrorMode,
+    inner: Arc<std::sync::Mutex<(Vec<ConversionIssue>, usize)>>,
+    skipped_rows: Arc<AtomicUsize>, // 被跳过的批次中的数据行数
+}
+
+impl IssueCollector {
+    fn new(mode: ErrorMode) -> Self {
+        Self {
+            mode,
+            inner: Arc::new(std::sync::Mutex::new((Vec::new(), 0))),
+            skipped_rows: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn is_collecting(&self) -> bool {
+        matches!(self.mode, ErrorMode::Collect { .. })
+    }
+
+    /// 记录一条错误，仅在 Collect 模式下生效
+    fn record(&self, issue: ConversionIssue) {
+        if let ErrorMode::Collect { max_errors } = self.mode {
+            let mut inner = self.inner.lock().unwrap();
+            inner.1 += 1;
+            if inner.0.len() < max_errors {
+                inner.0.push(issue);
+            }
+        }
+    }
+
+    fn take(&self) -> (Vec<ConversionIssue>, usize) {
+        std::mem::take(&mut *self.inner.lock().unwrap())
+    }
+
+    fn add_skipped_rows(&self, rows: usize) {
+        self.skipped_rows.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    fn skipped_rows(&self) -> usize {
+        self.skipped_rows.load(Ordering::Relaxed)
+    }
+}
+
+/// 转换上下文，管理状态和线程
+struct ConversionContext<'a> {
+    // Config
+    options: &'a ConvertExcelToParquetOptions<'a>,
+    region: Option<calamine::Dimensions>, // 限定转换区域（如打印区域），区域外的单元格被忽略
+    header_row_idx: u32,
+    last_row: u32, // 区域的最后一行，用于估计进度
+    num_cols: usize,
+    start_col: u32,
+    columns: Vec<u32>,                   // 输出的列（Sheet 中的列号，升序）
+    non_empty_columns: HashSet<u32>,     // skip_empty_columns 预扫描的结果
+    batch_size: Option<usize>,           // 自动模式下在采样完成前为 None
+    thread_budget: Option<ThreadBudget>, // 创建时确定，之后修改默认预算不影响本次转换
+    header_detection: HeaderDetection,
+    preset: Option<ConvertPreset>, // 调用方给出 writer_properties 时不生效
+    single_threaded: bool,
+    // 以下随输出目标调整：不写文件时不做原子写入、校验与额外输出，只统计概况时不排序
+    target: OutputTarget,
+    atomic: bool,
+    verify_output: bool,
+    rejects_output: Option<&'a Path>,
+    fan_out: &'a [FanOutTarget],
+    sort_by: Option<&'a (String, SortOrder)>,
+
+    // State
+    current_row: Option<u32>,
+    last_data_row: Option<u32>, // 最近一个计入输出的非空白行，用于估计进度
+    current_row_cells: HashMap<u32, String>, // Header building
+    header_cells: Option<HashMap<u32, String>>, // 读取单位行时暂存的表头
+    unit_cells: HashMap<u32, String>,
+    units: HashMap<String, String>,
+    raw_cells_buffer: Vec<RawCell>,
+    row_start: usize,                   // 当前行在 raw_cells_buffer 中的起始位置
+    current_row_blank: bool,            // 当前行的单元格是否全为空
+    pending_blank_rows: Vec<BlankRows>, // 尚未确认是否位于末尾的空白行
+    pending_blank_count: usize,
+    trailing_rows: VecDeque<(Vec<RawCell>, usize)>, // 暂存的最后若干行及其行数（含之前的空白行）
+    repeated_header: Option<Vec<String>>,           // 表头启动后解析出的重复表头的非空值
+    repeated_headers_skipped: usize,
+    title_rows_skipped: usize,
+    sampler: Option<RowSampler>,
+    rows_not_sampled: usize,
+    current_batch_rows: usize,
+    batch_counter: usize,
+    workers_started: bool,
+    pub total_rows: usize,
+
+    // Channels & Threads
+    work_tx: Option<mpsc::SyncSender<RawBatch>>, // Option allows dropping to signal EOF
+    result_tx: Option<mpsc::SyncSender<ProcessedBatch>>, // Option allows dropping
+    worker_threads: Vec<thread::JoinHandle<Result<()>>>,
+    writer: Option<WriterHandle>,
+
+    // Shared for init
+    work_rx: Option<Arc<std::sync::Mutex<mpsc::Receiver<RawBatch>>>>,
+    result_rx: Option<mpsc::Receiver<ProcessedBatch>>,
+    atomic_outputs: Vec<AtomicOutput>, // 启动写入时创建，未提交时丢弃会删除临时文件
+    output_files: Vec<PathBuf>,        // 写出的输出文件，按列拆分时为各部分文件
+    header_columns: Vec<(u32, String)>, // 输出的数据列及其列名
+    high_cardinality_columns: Vec<String>,
+    layout: Option<Arc<BatchLayout>>, // 单线程模式下在读取线程中构建批次
+    writer_props: Option<WriterProperties>,
+    output_limit_reached: Arc<AtomicBool>, // 写入端达到字节上限时置位，读取循环据此停止
+    header_candidates: Vec<RawCell>, // 非 FirstRow 模式下暂存的表头行及下一行，判断后作为数据重放
+    has_header: bool,
+    print_titles: Vec<(u32, u32, String)>, // 打印标题行中的非空单元格 (row, col, 值)
+    hidden_rows: HashSet<u32>,             // `skip_hidden` 时跳过的行
+    hidden_columns: Vec<u32>,              // `skip_hidden` 时不输出的列，升序
+    source_file: String,
+    sheet_name: String,
+    rejects_atomic: Option<AtomicOutput>,
+    reject_tx: Option<mpsc::SyncSender<ProcessedBatch>>,
+    reject_rx: Option<mpsc::Receiver<ProcessedBatch>>,
+    rejects_writer: Option<WriterHandle>,
+    fan_out_atomic: Vec<AtomicOutput>,
+    dedup_columns: Option<Vec<usize>>, // 去重键列在 schema 中的下标
+    counters: Arc<BatchCounters>,
+    started: Option<Instant>, // 启用 collect_stats 时的转换起点
+    send_time: Duration,      // 读取线程在 send_batch 中花费的时间，不计入读取阶段
+    forward_fill: Vec<(u32, Option<(String, CellKind)>)>, // (Sheet 列号, 该列上一个非空值)
+    replacement_char_cells: usize,
+    schema: Option<Arc<Schema>>,
+    sort_column: Option<(usize, SortOrder)>,
+    issues: IssueCollector,
+    trace: TraceContext,
+}
+
+impl<'a> ConversionContext<'a> {
+    fn new(
+        options: &'a ConvertExcelToParquetOptions<'a>,
+        dimensions: calamine::Dimensions,
+        region: Option<calamine::Dimensions>,
+    ) -> Result<Self> {
+        let region = region.map(|region| cell_ref::clamp_area(region, dimensions));
+        let dimensions = region.unwrap_or(dimensions);
+        let num_cols = (dimensions.end.1 - dimensions.start.1 + 1) as usize;
+        let start_col = dimensions.start.1;
+        let header_row_idx = dimensions.start.0 + options.skip_rows as u32;
+        check_options(options)?;
+
+        info!(
+            "Sheet dimensions: rows {}-{}, cols {}-{}",
+            dimensions.start.0, dimensions.end.0, dimensions.start.1, dimensions.end.1
+        );
+
+        let num_workers = 8;
+        let (work_tx, work_rx) = mpsc::sync_channel::<RawBatch>(num_workers * 2);
+        let (result_tx, result_rx) = mpsc::sync_channel::<ProcessedBatch>(num_workers * 2);
+        let (reject_tx, reject_rx) = match options.rejects_output {
+            Some(_) => {
+                let (tx, rx) = mpsc::sync_channel::<ProcessedBatch>(num_workers * 2);
+                (Some(tx), Some(rx))
+            }
+            None => (None, None),
+        };
+
+        Ok(Self {
+            options,
+            region,
+            header_row_idx,
+            last_row: dimensions.end.0,
+            num_cols,
+            start_col,
+            columns: (start_col..start_col + num_cols as u32).collect(),
+            non_empty_columns: HashSet::new(),
+            batch_size: options.batch_size,
+            thread_budget: options.thread_budget.clone().or_else(global_thread_budget),
+
+            current_row: None,
+            last_data_row: None,
+            current_row_cells: HashMap::new(),
+            header_cells: None,
+            unit_cells: HashMap::new(),
+            units: HashMap::new(),
+            raw_cells_buffer: Vec::new(),
+            row_start: 0,
+            current_row_blank: true,
+            pending_blank_rows: Vec::new(),
+            trailing_rows: VecDeque::new(),
+            pending_blank_count: 0,
+            repeated_header: None,
+            repeated_headers_skipped: 0,
+            title_rows_skipped: 0,
+            sampler: options
+                .sample
+                .map(|spec| RowSampler::new(spec, options.sample_seed)),
+            rows_not_sampled: 0,
+            current_batch_rows: 0,
+            batch_counter: 0,
+            workers_started: false,
+            total_rows: 0,
+
+            work_tx: Some(work_tx),
+            result_tx: Some(result_tx),
+            worker_threads: Vec::new(),
+            writer: None,
+
+            work_rx: Some(Arc::new(std::sync::Mutex::new(work_rx))),
+            result_rx: Some(result_rx),
+            target: OutputTarget::Parquet,
+            atomic: options.atomic_output,
+            atomic_outputs: Vec::new(),
+            output_files: Vec::new(),
+            verify_output: options.verify_output,
+            header_columns: Vec::new(),
+            high_cardinality_columns: Vec::new(),
+            single_threaded: options.single_threaded || cfg!(target_arch = "wasm32"),
+            layout: None,
+            writer_props: Some(build_writer_properties(options)?),
+            sort_by: options.sort_by.as_ref(),
+            output_limit_reached: Arc::new(AtomicBool::new(false)),
+            // 按样式识别在读取前确定表头所在的行，之后与 `FirstRow` 相同
+            header_detection: if header_is_first_row(options.header_detection) {
+                HeaderDetection::FirstRow
+            } else {
+                options.header_detection
+            },
+            header_candidates: Vec::new(),
+            has_header: options.header_detection != HeaderDetection::None,
+            preset: options
+                .preset
+                .filter(|_| options.writer_properties.is_none()),
+            print_titles: Vec::new(),
+            hidden_rows: HashSet::new(),
+            hidden_columns: Vec::new(),
+            source_file: options
+                .excel_file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            sheet_name: options.sheet_name.clone().unwrap_or_default(),
+            rejects_output: options.rejects_output.as_deref(),
+            rejects_atomic: None,
+            reject_tx,
+            reject_rx,
+            rejects_writer: None,
+            fan_out: &options.fan_out,
+            fan_out_atomic: Vec::new(),
+            dedup_columns: None,
+            counters: Arc::new(BatchCounters::default()),
+            started: start_timer(options.collect_stats),
+            send_time: Duration::ZERO,
+            forward_fill: Vec::new(),
+            replacement_char_cells: 0,
+            schema: None,
+            sort_column: None,
+            issues: IssueCollector::new(options.error_mode),
+            trace: TraceContext::current(),
+        })
+    }
+
+    /// 写入端是否已达到 `max_output_bytes`，之后读到的行都会被丢弃
+    fn output_limit_reached(&self) -> bool {
+        self.output_limit_reached.load(Ordering::Relaxed)
+    }
+
+    /// 设置当前 Sheet 的名称，用于 `include_sheet_column`
+    fn with_sheet_name(mut self, sheet_name: &str) -> Self {
+        self.sheet_name = sheet_name.to_string();
+        self
+    }
+
+    /// 设置隐藏的行列：隐藏列不再输出，表头之后的隐藏行不再读取
+    #[cfg(feature = "hidden")]
+    fn set_hidden(&mut self, hidden: hidden::HiddenCells) {
+        info!(
+            "Skipping {} hidden rows and {} hidden columns",
+            hidden.rows.len(),
+            hidden.columns.len()
+        );
+        self.columns
+            .retain(|col| hidden.columns.binary_search(col).is_err());
+        self.hidden_rows = hidden.rows;
+        self.hidden_columns = hidden.columns;
+    }
+
+    /// 按样式识别出的表头所在行，其上的行不再读取
+    #[cfg(feature = "styles")]
+    fn set_header_row(&mut self, row: u32) {
+        info!("Header detection: row {} is bold", row);
+        self.header_row_idx = row;
+    }
+
+    /// 设置打印标题行中的单元格，位于表头之上的单元格在启动 worker 时成为常量列
+    fn set_print_titles(&mut self, titles: Vec<(u32, u32, String)>) {
+        self.print_titles = titles;
+    }
+
+    /// 设置输出目标；不写文件时也不做原子写入与校验，只统计概况时也不排序
+    fn with_target(mut self, target: OutputTarget) -> Self {
+        if !matches!(target, OutputTarget::Parquet) {
+            self.atomic = false;
+            self.verify_output = false;
+            self.rejects_output = None;
+            self.reject_tx = None;
+            self.reject_rx = None;
+            self.fan_out = &[];
+        }
+        if matches!(target, OutputTarget::Profile) {
+            self.sort_by = None;
+        }
+        self.target = target;
+        self
+    }
+
+    /// 读取一个 calamine 单元格，转为字符串并记录类型后交给 `process_cell`
+    fn read_cell(&mut self, row: u32, col: u32, value: &calamine::DataRef) -> Result<()> {
+        if self.is_hidden_row(row) {
+            return Ok(());
+        }
+        if let calamine::DataRef::Error(e) = value
+            && row >= self.header_row_idx
+            && self.in_region(row, col)
+        {
+            self.issues.record(ConversionIssue {
+                row: Some(row),
+                col: Some(col),
+                message: format!("Cell contains Excel error value {:?}", e),
+            });
+        }
+        let text = match value {
+            calamine::DataRef::DateTime(dt) => {
+                date_serial_1900(dt, self.options.date_system).to_string()
+            }
+            calamine::DataRef::Float(f) if let Some(precision) = self.options.float_precision => {
+                format!("{:.*}", precision, f)
+            }
+            calamine::DataRef::Bool(b)
+                if let Some((true_text, false_text)) = &self.options.bool_string_format =>
+            {
+                if *b {
+                    true_text.clone()
+                } else {
+                    false_text.clone()
+                }
+            }
+            value => cell_to_string(value),
+        };
+        self.process_cell(row, col, text, CellKind::of(value))
+    }
+
+    /// 预扫描时记录一个非空单元格，只统计表头行及之后、区域内的单元格
+    fn mark_non_empty(&mut self, row: u32, col: u32) {
+        if row >= self.header_row_idx && self.in_region(row, col) {
+            self.non_empty_columns.insert(col);
+        }
+    }
+
+    /// 根据预扫描结果移除全空的列；整个 Sheet 都为空时保持不变
+    fn drop_empty_columns(&mut self) {
+        if self.non_empty_columns.is_empty() {
+            return;
+        }
+        let before = self.columns.len();
+        let non_empty = std::mem::take(&mut self.non_empty_columns);
+        self.columns.retain(|col| non_empty.contains(col));
+        info!("Dropped {} empty columns", before - self.columns.len());
+    }
+
+    fn in_region(&self, row: u32, col: u32) -> bool {
+        self.region
+            .as_ref()
+            .is_none_or(|region| region.contains(row, col))
+    }
+
+    /// 表头之后的隐藏行不输出
+    fn is_hidden_row(&self, row: u32) -> bool {
+        row > self.header_row_idx && self.hidden_rows.contains(&row)
+    }
+
+    fn process_cell(&mut self, row: u32, col: u32, value: String, kind: CellKind) -> Result<()> {
+        if !self.in_region(row, col) || self.is_hidden_row(row) {
+            return Ok(());
+        }
+        if !self.workers_started {
+            self.handle_header_phase(row, col, value, kind)
+        } else {
+            self.handle_worker_phase(row, col, value, kind)
+        }
+    }
+
+    fn handle_header_phase(
+        &mut self,
+        row: u32,
+        col: u32,
+        value: String,
+        kind: CellKind,
+    ) -> Result<()> {
+        if self.current_row.is_none() {
+            self.current_row = Some(row);
+        } else if self.current_row != Some(row) {
+            // 换行了，检查上一行是不是 header（或单位行）
+            let prev_row = self.current_row.unwrap();
+
+            if prev_row == self.header_row_idx && self.options.units_row {
+                self.header_cells = Some(std::mem::take(&mut self.current_row_cells));
+            }
+            if prev_row >= self.header_row_idx && row > self.last_header_row() {
+                self.current_row = None;
+                self.end_header()?;
+
+                // 当前单元格属于第一行数据，交给 worker 阶段处理
+                return self.handle_worker_phase(row, col, value, kind);
+            }
+
+            self.current_row_cells.clear();
+            self.current_row = Some(row);
+        }
+        if self.header_detection != HeaderDetection::FirstRow && row >= self.header_row_idx {
+            self.header_candidates.push((row, col, value.clone(), kind));
+        }
+        self.current_row_cells.insert(col, value);
+        Ok(())
+    }
+
+    /// 表头（启用 `units_row` 时含单位行，猜测表头时含用于比较的下一行）的最后一行
+    fn last_header_row(&self) -> u32 {
+        self.header_row_idx
+            + (self.options.units_row || self.header_detection == HeaderDetection::Guess) as u32
+    }
+
+    /// 表头读取完毕：取出暂存的表头与单位行并启动 worker
+    fn end_header(&mut self) -> Result<()> {
+        if self.header_detection != HeaderDetection::FirstRow {
+            return self.end_detected_header();
+        }
+        if let Some(header_cells) = self.header_cells.take() {
+            self.unit_cells = std::mem::replace(&mut self.current_row_cells, header_cells);
+        }
+        self.start_workers()?;
+        self.workers_started = true;
+        Ok(())
+    }
+
+    /// 按 `header_detection` 决定第一行是否为表头，启动 worker 后将暂存的数据行重放到 worker 阶段
+    fn end_detected_header(&mut self) -> Result<()> {
+        let cells = std::mem::take(&mut self.header_candidates);
+        self.has_header = self.header_detection == HeaderDetection::Guess
+            && looks_like_header(&cells, self.header_row_idx);
+        info!(
+            "Header detection: row {} is {}",
+            self.header_row_idx,
+            if self.has_header { "a header" } else { "data" }
+        );
+        self.current_row_cells = if self.has_header {
+            cells
+                .iter()
+                .filter(|(row, _, _, _)| *row == self.header_row_idx)
+                .map(|(_, col, value, _)| (*col, value.clone()))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        self.start_workers()?;
+        self.workers_started = true;
+        for (row, col, value, kind) in cells {
+            if !(self.has_header && row == self.header_row_idx) {
+                self.handle_worker_phase(row, col, value, kind)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_workers(&mut self) -> Result<()> {
+        let _span = enter_span!("header", row = self.header_row_idx);
+        // 溢出列：表头最后一个非空单元格之后的列不再单独输出
+        let overflow = match &self.options.overflow_column {
+            Some(_) => {
+                let last = self
+                    .current_row_cells
+                    .iter()
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(col, _)| *col)
+                    .max();
+                if let Some(last) = last {
+                    self.columns.retain(|col| *col <= last);
+                }
+                Some(last.map_or(self.start_col, |last| last + 1))
+            }
+            None => None,
+        };
+        let (mut headers, mut ignored_columns) = build_headers(
+            &self.current_row_cells,
+            &mut self.columns,
+            self.start_col,
+            self.options.case_insensitive_dedup,
+            self.options.dedup_strategy,
+            self.options.header_index_base,
+        )?;
+        ignored_columns.extend(&self.hidden_columns);
+        ignored_columns.sort_unstable();
+        info!("Found headers: {} columns", headers.len());
+
+        // 列窗口：只保留两个表头名称之间（含两端）的列
+        if let Some((first, last)) = &self.options.column_window {
+            let position = |name: &String| {
+                headers.iter().position(|h| h == name).ok_or_else(|| {
+                    anyhow::anyhow!("Column window bound '{}' not found in headers", name)
+                })
+            };
+            let (start, end) = (position(first)?, position(last)?);
+            if start > end {
+                return Err(anyhow::anyhow!(
+                    "Column window start '{}' comes after end '{}'",
+                    first,
+                    last
+                ));
+            }
+            headers = headers.drain(start..=end).collect();
+            self.columns = self.columns.drain(start..=end).collect();
+        }
+
+        // 向下填充列按表头名称解析为 Sheet 列号
+        self.forward_fill = self
+            .options
+            .forward_fill_columns
+            .iter()
+            .map(|name| {
+                let index = headers.iter().position(|h| h == name).ok_or_else(|| {
+                    anyhow::anyhow!("Forward fill column '{}' not found in headers", name)
+                })?;
+                Ok((self.columns[index], None))
+            })
+            .collect::<Result<_>>()?;
+
+        self.repeated_header = match &self.options.repeated_header {
+            Some(RepeatedHeader::SameAsHeader) => {
+                let mut cells: Vec<_> = self
+                    .current_row_cells
+                    .iter()
+                    .filter(|(_, value)| !value.is_empty())
+                    .collect();
+                cells.sort_unstable_by_key(|(col, _)| **col);
+                Some(cells.into_iter().map(|(_, value)| value.clone()).collect())
+            }
+            Some(RepeatedHeader::Text(values)) => Some(values.clone()),
+            None => None,
+        }
+        .filter(|values| !values.is_empty());
+
+        // 掩码列按表头名称解析，不保留时从输出列中移除
+        let mut columns = self.columns.clone();
+        let mask = match &self.options.mask_column {
+            Some(name) => {
+                let index = headers.iter().position(|h| h == name).ok_or_else(|| {
+                    anyhow::anyhow!("Mask column '{}' not found in headers", name)
+                })?;
+                let col = columns[index];
+                if !self.options.keep_mask_column {
+                    headers.remove(index);
+                    columns.remove(index);
+                }
+                Some(col)
+            }
+            None => None,
+        };
+
+        // 按列的错误值处理方式同样按表头名称解析
+        if let Some(name) = self
+            .options
+            .column_error_overrides
+            .keys()
+            .find(|name| !headers.contains(name))
+        {
+            return Err(anyhow::anyhow!(
+                "Error override column '{}' not found in headers",
+                name
+            ));
+        }
+        let error_policies = headers
+            .iter()
+            .map(|name| {
+                self.options
+                    .column_error_overrides
+                    .get(name)
+                    .unwrap_or(&self.options.error_cell_policy)
+                    .clone()
+            })
+            .collect();
+
+        let constraints = self
+            .options
+            .constraints
+            .iter()
+            .map(|constraint| {
+                let column = headers
+                    .iter()
+                    .position(|h| *h == constraint.column)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Constraint column '{}' not found in headers",
+                            constraint.column
+                        )
+                    })?;
+                let check = match &constraint.rule {
+                    ConstraintRule::NotNull => ConstraintCheck::NotNull,
+                    ConstraintRule::Matches(pattern) => {
+                        ConstraintCheck::Matches(regex::Regex::new(pattern).with_context(|| {
+                            format!(
+                                "Invalid pattern for constraint on column '{}'",
+                                constraint.column
+                            )
+                        })?)
+                    }
+                    ConstraintRule::Range { min, max } => ConstraintCheck::Range {
+                        min: *min,
+                        max: *max,
+                    },
+                };
+                Ok(ResolvedConstraint {
+                    column,
+                    name: constraint.column.clone(),
+                    check,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // 派生列的源列需在启动前解析，与排序列一样尽早报错
+        let mut derived = Vec::with_capacity(self.options.derived_columns.len());
+        for column in &self.options.derived_columns {
+            if headers.contains(&column.name) {
+                return Err(anyhow::anyhow!(
+                    "Derived column '{}' conflicts with an existing header",
+                    column.name
+                ));
+            }
+            let sources = column
+                .source_columns
+                .iter()
+                .map(|source| {
+                    headers.iter().position(|h| h == source).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Source column '{}' of derived column '{}' not found in headers",
+                            source,
+                            column.name
+                        )
+                    })
+                })
+                .collect::<Result<Vec<usize>>>()?;
+            derived.push(ResolvedDerivedColumn {
+                sources,
+                separator: column.separator.clone(),
+                null_handling: column.null_handling,
+            });
+        }
+
+        self.header_columns = columns
+            .iter()
+            .copied()
+            .zip(headers.iter().cloned())
+            .collect();
+        let mut fields: Vec<Field> = Vec::new();
+        for (name, col) in headers.iter().zip(&columns) {
+            let mut field = Field::new(name, DataType::Utf8, true);
+            if let Some(unit) = self.unit_cells.get(col).filter(|unit| !unit.is_empty()) {
+                field = field.with_metadata(HashMap::from([(
+                    UNIT_METADATA_KEY.to_string(),
+                    unit.clone(),
+                )]));
+                self.units.insert(name.clone(), unit.clone());
+            }
+            fields.push(field);
+            if self.options.type_sidecar_columns {
+                fields.push(Field::new(format!("{}__type", name), DataType::Utf8, true));
+            }
+        }
+        if let Some(name) = &self.options.overflow_column {
+            if headers.contains(name) {
+                return Err(anyhow::anyhow!(
+                    "Overflow column '{}' conflicts with an existing header",
+                    name
+                ));
+            }
+            fields.push(Field::new(name, DataType::Utf8, true));
+        }
+        for column in &self.options.derived_columns {
+            fields.push(Field::new(&column.name, DataType::Utf8, true));
+        }
+        let row_hash = match &self.options.row_hash_column {
+            Some((name, algo)) => {
+                if fields.iter().any(|field| field.name() == name) {
+                    return Err(anyhow::anyhow!(
+                        "Row hash column '{}' conflicts with an existing column",
+                        name
+                    ));
+                }
+                fields.push(Field::new(name, DataType::Utf8, false));
+                let mut order: Vec<usize> = (0..headers.len()).collect();
+                order.sort_by(|&a, &b| headers[a].cmp(&headers[b]));
+                Some((order, *algo))
+            }
+            None => None,
+        };
+        let mut constants = Vec::new();
+        if let Some(name) = &self.options.include_sheet_column {
+            constants.push((name.clone(), self.sheet_name.clone()));
+        }
+        let mut constant_columns: Vec<_> = self.options.constant_columns.iter().collect();
+        constant_columns.sort_unstable();
+        let mut now = None;
+        for (name, template) in constant_columns {
+            let mut value = template
+                .replace("{source_file}", &self.source_file)
+                .replace("{sheet}", &self.sheet_name);
+            if value.contains("{now}") {
+                if now.is_none() {
+                    let time = current_time().ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "'{{now}}' in constant column '{}' needs a system clock",
+                            name
+                        )
+                    })?;
+                    now = Some(format_utc_timestamp(time));
+                }
+                value = value.replace("{now}", now.as_deref().unwrap());
+            }
+            constants.push((name.clone(), value));
+        }
+        for (row, col, value) in &self.print_titles {
+            if *row < self.header_row_idx {
+                let name = format!("print_title_{}", cell_ref::cell_address(*row, *col));
+                constants.push((name, value.clone()));
+            }
+        }
+        for (name, _) in &constants {
+            if fields.iter().any(|field| field.name() == name) {
+                return Err(anyhow::anyhow!(
+                    "Constant column '{}' conflicts with an existing column",
+                    name
+                ));
+            }
+            fields.push(Field::new(name, DataType::Utf8, true));
+        }
+        for (name, description) in &self.options.column_descriptions {
+            let field = fields
+                .iter_mut()
+                .find(|field| field.name() == name)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Description column '{}' not found in headers", name)
+                })?;
+            let mut metadata = field.metadata().clone();
+            metadata.insert(DESCRIPTION_METADATA_KEY.to_string(), description.clone());
+            field.set_metadata(metadata);
+        }
+        // 重排放在最后，排序、去重等按列名解析的下标都基于重排后的 schema
+        let column_order = self.options.output_column_order.permutation(&fields)?;
+        if let Some(order) = &column_order {
+            fields = order.iter().map(|&i| fields[i].clone()).collect();
+        }
+        let schema = Arc::new(Schema::new(fields));
+        let rejects = match self.rejects_output {
+            Some(_) if schema.index_of(REJECT_REASON_COLUMN).is_ok() => {
+                return Err(anyhow::anyhow!(
+                    "Column '{}' conflicts with the reject reason column",
+                    REJECT_REASON_COLUMN
+                ));
+            }
+            Some(_) => Some(rejects_schema(&schema)),
+            None => None,
+        };
+
+        // 排序列需在启动前解析，避免处理完整个文件才发现列名错误
+        self.sort_column = match &self.sort_by {
+            Some((name, order)) => Some((
+                schema
+                    .index_of(name)
+                    .map_err(|_| anyhow::anyhow!("Sort column '{}' not found in headers", name))?,
+                *order,
+            )),
+            None => None,
+        };
+        self.dedup_columns = match &self.options.deduplicate {
+            Some(DedupMode::FullRow) => Some((0..schema.fields().len()).collect()),
+            Some(DedupMode::ByColumns(names)) => Some(
+                names
+                    .iter()
+                    .map(|name| {
+                        schema.index_of(name).map_err(|_| {
+                            anyhow::anyhow!("Dedup column '{}' not found in headers", name)
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            None => None,
+        };
+
+        let layout = Arc::new(BatchLayout {
+            columns,
+            type_sidecar: self.options.type_sidecar_columns,
+            overflow,
+            derived,
+            row_hash,
+            constants: constants.into_iter().map(|(_, value)| value).collect(),
+            mask,
+            bool_true_text: self
+                .options
+                .bool_string_format
+                .as_ref()
+                .map_or_else(|| "true".to_string(), |(true_text, _)| true_text.clone()),
+            ignored_columns,
+            error_policies,
+            whitespace_as_null: self.options.whitespace_as_null,
+            timed: self.options.collect_stats,
+            constraints,
+            rejects,
+            strict_validation: self.options.strict_batch_validation,
+            column_order,
+        });
+        if self.single_threaded {
+            self.layout = Some(layout);
+            self.schema = Some(schema);
+            return Ok(());
+        }
+
+        // Start Workers，数量受线程预算限制
+        let num_workers = 8;
+        let permits = budget::acquire_worker_permits(self.thread_budget.as_ref(), num_workers);
+        info!("Starting {} workers", permits.len());
+        let work_rx = self.work_rx.take().unwrap(); // Take the rx to share
+
+        for permit in permits {
+            let work_rx_clone = work_rx.clone();
+            let result_tx_clone = self.result_tx.as_ref().unwrap().clone();
+            let reject_tx_clone = self.reject_tx.clone();
+            let schema_clone = schema.clone();
+            let layout = layout.clone();
+            let issues = self.issues.clone();
+            let counters = self.counters.clone();
+            let trace = self.trace.clone();
+
+            let mut builder = thread::Builder::new();
+            if let Some(size) = self.options.worker_stack_size {
+                builder = builder.stack_size(size);
+            }
+            let handle = builder
+                .spawn(move || -> Result<()> {
+                    let _permit = permit;
+                    let _trace = trace.attach();
+                    loop {
+                        let msg = {
+                            let lock = work_rx_clone.lock().unwrap();
+                            match lock.recv() {
+                                Ok(m) => m,
+                                Err(_) => break,
+                            }
+                        };
+                        let (id, cells) = msg;
+                        let (record_batch, rejects) =
+                            process_batch(id, &cells, &schema_clone, &layout, &issues, &counters)?;
+                        if result_tx_clone.send((id, record_batch)).is_err() {
+                            break;
+                        }
+                        if let (Some(tx), Some(rejects)) = (&reject_tx_clone, rejects)
+                            && tx.send((id, rejects)).is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Ok(())
+                })
+                .context("Failed to spawn worker thread")?;
+            self.worker_threads.push(handle);
+        }
+
+        self.schema = Some(schema);
+        Ok(())
+    }
+
+    /// 单个 Parquet 输出的写入目标；排序输出时推迟到排序完成后再创建写入器
+    fn parquet_sink(
+        &self,
+        file: Box<dyn Write + Send>,
+        schema: Arc<Schema>,
+        props: WriterProperties,
+    ) -> Result<BatchSink> {
+        if self.sort_column.is_some() {
+            return Ok(BatchSink::DeferredParquet(Some(Box::new(
+                DeferredParquet {
+                    file,
+                    schema,
+                    props,
+                },
+            ))));
+        }
+        let writer = ArrowWriter::try_new(file, schema, Some(props))
+            .context("Failed to create parquet writer")?;
+        Ok(BatchSink::Parquet(Box::new(writer)))
+    }
+
+    /// 启动写入线程。row group 大小取决于 batch size，因此在第一个批次发出前才启动
+    fn start_writer(&mut self) -> Result<()> {
+        let batch_size = self.resolve_batch_size();
+        let split = if self.options.coalesce_small_tail {
+            RowGroupSplit::PerBatchCoalescing(batch_size / COALESCE_TAIL_DIVISOR)
+        } else if self.options.one_row_group_per_batch {
+            RowGroupSplit::PerBatch
+        } else {
+            RowGroupSplit::BySize
+        };
+        // 调用方指定的 row group 大小优先，否则按批次大小推算
+        let max_rows = self.options.max_row_group_size.unwrap_or(match split {
+            // 合并尾部批次时需容纳一个完整批次加上不足阈值的尾部
+            RowGroupSplit::PerBatchCoalescing(min_rows) => batch_size + min_rows,
+            RowGroupSplit::BySize if self.preset == Some(ConvertPreset::FastWrite) => {
+                batch_size * FAST_WRITE_BATCHES_PER_ROW_GROUP
+            }
+            _ => batch_size,
+        });
+        let mut props = self
+            .writer_props
+            .take()
+            .unwrap()
+            .into_builder()
+            .set_max_row_group_size(max_rows)
+            .build();
+        let high_cardinality = self.high_cardinality_columns();
+        if let Some(max_distinct) = self.options.max_distinct_per_column
+            && !high_cardinality.is_empty()
+        {
+            if self.options.strict_distinct_check {
+                return Err(anyhow::anyhow!(
+                    "Columns with more than {} distinct values: {}",
+                    max_distinct,
+                    high_cardinality.join(", ")
+                ));
+            }
+            let mut builder = props.into_builder();
+            for name in &high_cardinality {
+                warn!(
+                    "Column '{}' has more than {} distinct values, dictionary encoding disabled",
+                    name, max_distinct
+                );
+                builder =
+                    builder.set_column_dictionary_enabled(ColumnPath::from(name.as_str()), false);
+            }
+            props = builder.build();
+            self.high_cardinality_columns = high_cardinality;
+        }
+        let schema_clone = self.schema.clone().unwrap();
+        let result_rx = self.result_rx.take().unwrap();
+
+        if let Some(path) = self.rejects_output {
+            let output_path = if self.atomic {
+                let output = AtomicOutput::new(path);
+                let temp_path = output.temp_path().to_path_buf();
+                self.rejects_atomic = Some(output);
+                temp_path
+            } else {
+                path.to_path_buf()
+            };
+            let file = File::create(&output_path).context("Failed to create rejects file")?;
+            let file: Box<dyn Write + Send> = Box::new(file);
+            let schema = rejects_schema(&schema_clone);
+            let writer = ArrowWriter::try_new(file, schema.clone(), Some(props.clone()))
+                .context("Failed to create parquet writer for rejects")?;
+            let writer = OrderedWriter::new(
+                BatchSink::Parquet(Box::new(writer)),
+                schema,
+                None,
+                RowGroupSplit::BySize,
+            );
+            self.rejects_writer = Some(if self.single_threaded {
+                WriterHandle::Inline(Box::new(writer))
+            } else {
+                let reject_rx = self.reject_rx.take().unwrap();
+                WriterHandle::Thread(spawn_writer(writer, reject_rx, self.trace.clone()))
+            });
+        }
+
+        // 额外输出在主输出之前创建，与主输出共用 schema 与写入属性
+        let mut fan_out = Vec::with_capacity(self.fan_out.len());
+        for target in self.fan_out {
+            let output_path = if self.atomic {
+                let output = AtomicOutput::new(&target.path);
+                let temp_path = output.temp_path().to_path_buf();
+                self.fan_out_atomic.push(output);
+                temp_path
+            } else {
+                target.path.clone()
+            };
+            let file = File::create(&output_path).with_context(|| {
+                format!("Failed to create output file {}", target.path.display())
+            })?;
+            let file: Box<dyn Write + Send> = match self.options.write_buffer_size {
+                Some(capacity) => Box::new(BufWriter::with_capacity(capacity, file)),
+                None => Box::new(file),
+            };
+            fan_out.push(match target.format {
+                FanOutFormat::Parquet => {
+                    self.parquet_sink(file, schema_clone.clone(), props.clone())?
+                }
+                FanOutFormat::Ndjson => BatchSink::Ndjson(Box::new(
+                    arrow::json::WriterBuilder::new()
+                        .with_explicit_nulls(true)
+                        .build(file),
+                )),
+            });
+        }
+
+        let sink = match &self.target {
+            OutputTarget::Parquet => {
+                let parts = match &self.options.column_split {
+                    Some(split) => split_columns(&schema_clone, split)?,
+                    None => vec![(0..schema_clone.fields().len()).collect()],
+                };
+                let mut writers = Vec::with_capacity(parts.len());
+                for (k, columns) in parts.into_iter().enumerate() {
+                    let target_path = match self.options.column_split {
+                        Some(_) => column_part_path(self.options.output_path, k + 1),
+                        None => self.options.output_path.to_path_buf(),
+                    };
+                    let output_path = if self.atomic {
+                        let output = AtomicOutput::new(&target_path);
+                        let temp_path = output.temp_path().to_path_buf();
+                        self.atomic_outputs.push(output);
+                        temp_path
+                    } else {
+                        target_path.clone()
+                    };
+                    // 在当前线程创建文件，确保放弃转换时临时文件的清理不会与写入线程竞争
+                    let file =
+                        File::create(&output_path).context("Failed to create output file")?;
+                    let file: Box<dyn Write + Send> = match self.options.write_buffer_size {
+                        Some(capacity) => Box::new(BufWriter::with_capacity(capacity, file)),
+                        None => Box::new(file),
+                    };
+                    writers.push((columns, file));
+                    self.output_files.push(target_path);
+                }
+                if self.options.column_split.is_some() {
+                    let mut parts = Vec::with_capacity(writers.len());
+                    for (columns, file) in writers {
+                        let writer = ArrowWriter::try_new(
+                            file,
+                            Arc::new(schema_clone.project(&columns)?),
+                            Some(props.clone()),
+                        )
+                        .context("Failed to create parquet writer")?;
+                        parts.push(ColumnPart { columns, writer });
+                    }
+                    BatchSink::ColumnSplit(parts)
+                } else {
+                    let (_, file) = writers.pop().unwrap();
+                    self.parquet_sink(file, schema_clone.clone(), props)?
+                }
+            }
+            OutputTarget::Memory(buffer) => {
+                let buffer: Box<dyn Write + Send> = Box::new(buffer.clone());
+                self.parquet_sink(buffer, schema_clone.clone(), props)?
+            }
+            OutputTarget::Batches(batches) => {
+                batches.push(RecordBatch::new_empty(schema_clone.clone()));
+                BatchSink::Batches(batches.clone())
+            }
+            OutputTarget::Profile => BatchSink::Profile(
+                schema_clone
+                    .fields()
+                    .iter()
+                    .map(|field| ProfileAccumulator::new(field.name()))
+                    .collect(),
+            ),
+        };
+
+        let mut writer = OrderedWriter::new(sink, schema_clone, self.sort_column, split);
+        if let Some(columns) = self.dedup_columns.clone() {
+            writer = writer.with_dedup(columns);
+        }
+        if self.options.collect_stats {
+            writer = writer.with_timing();
+        }
+        if let Some(max_bytes) = self.options.max_output_bytes {
+            writer = writer.with_byte_limit(max_bytes, self.output_limit_reached.clone());
+        }
+        if self.options.spill_to_disk {
+            writer = writer.with_spill()?;
+        }
+        if let Some(pressure) = self.options.memory_pressure.clone() {
+            writer = writer.with_memory_pressure(pressure);
+        }
+        if !fan_out.is_empty() {
+            writer = writer.with_fan_out(fan_out);
+        }
+        self.writer = Some(if self.single_threaded {
+            WriterHandle::Inline(Box::new(writer))
+        } else {
+            WriterHandle::Thread(spawn_writer(writer, result_rx, self.trace.clone()))
+        });
+
+        Ok(())
+    }
+
+    /// 统计第一个批次中每个数据列的不同值个数，返回超过 `max_distinct_per_column` 的列名
+    fn high_cardinality_columns(&self) -> Vec<String> {
+        let Some(max_distinct) = self.options.max_distinct_per_column else {
+            return Vec::new();
+        };
+        let mut distinct: HashMap<u32, HashSet<&str>> = HashMap::new();
+        for (_, col, value, _) in &self.raw_cells_buffer {
+            distinct.entry(*col).or_default().insert(value);
+        }
+        self.header_columns
+            .iter()
+            .filter(|(col, _)| {
+                distinct
+                    .get(col)
+                    .is_some_and(|values| values.len() > max_distinct)
+            })
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    fn handle_worker_phase(
+        &mut self,
+        row: u32,
+        col: u32,
+        value: String,
+        kind: CellKind,
+    ) -> Result<()> {
+        if self.current_row != Some(row) {
+            if self.current_row.is_some() {
+                self.commit_row();
+            }
+
+            // 新的一行开始前，若当前批次已满则先发送
+            if self.batch_size.is_none() && self.current_batch_rows >= AUTO_BATCH_SAMPLE_ROWS {
+                self.resolve_batch_size();
+            }
+            if self
+                .batch_size
+                .is_some_and(|size| self.current_batch_rows >= size)
+            {
+                self.send_batch()?;
+            }
+
+            self.current_row = Some(row);
+            self.row_start = self.raw_cells_buffer.len();
+            self.current_row_blank = true;
+        }
+        let value = if value.contains(char::REPLACEMENT_CHARACTER) {
+            self.replacement_char_cells += 1;
+            match self.options.on_replacement_char {
+                ReplacementPolicy::Keep => value,
+                ReplacementPolicy::Null => return Ok(()),
+                ReplacementPolicy::Strip => value.replace(char::REPLACEMENT_CHARACTER, ""),
+                ReplacementPolicy::Error if self.issues.is_collecting() => {
+                    self.issues.record(ConversionIssue {
+                        row: Some(row),
+                        col: Some(col),
+                        message: "Cell contains the Unicode replacement character".to_string(),
+                    });
+                    value
+                }
+                ReplacementPolicy::Error => {
+                    return Err(anyhow::anyhow!(
+                        "Cell at row {}, column {} contains the Unicode replacement character",
+                        row,
+                        col
+                    ));
+                }
+            }
+        } else {
+            value
+        };
+        if !value.is_empty() {
+            self.current_row_blank = false;
+        }
+        self.raw_cells_buffer.push((row, col, value, kind));
+        Ok(())
+    }
+
+    /// 结束当前行并计入批次
+    ///
+    /// 需要暂存时（见 `holds_back_blank_rows`），全空的行（例如只有格式的单元格）移出缓冲区，
+    /// 只记下行号与单元格所在的列，等到后面出现非空行时再按原顺序展开放回；位于表格末尾的空白行
+    /// 因此不会输出。与重复表头相同的行直接丢弃。
+    fn commit_row(&mut self) {
+        if let Some(header) = &self.repeated_header
+            && self.raw_cells_buffer[self.row_start..]
+                .iter()
+                .filter(|(_, _, value, _)| !value.is_empty())
+                .map(|(_, _, value, _)| value)
+                .eq(header.iter())
+        {
+            self.raw_cells_buffer.truncate(self.row_start);
+            self.repeated_headers_skipped += 1;
+            return;
+        }
+        if let Some(band) = &self.options.skip_title_bands
+            && self.columns.len() > band.max_cells
+        {
+            let populated = self.raw_cells_buffer[self.row_start..]
+                .iter()
+                .filter(|(_, _, value, _)| !value.is_empty())
+                .map(|(_, col, _, _)| *col);
+            let (count, first) = populated.fold((0, None::<u32>), |(count, first), col| {
+                (count + 1, Some(first.map_or(col, |first| first.min(col))))
+            });
+            if count > 0
+                && count <= band.max_cells
+                && (!band.require_first_column || first == self.columns.first().copied())
+            {
+                self.raw_cells_buffer.truncate(self.row_start);
+                self.title_rows_skipped += 1;
+                return;
+            }
+        }
+        if self.current_row_blank && self.holds_back_blank_rows() {
+            let row = self.current_row.unwrap();
+            let cells: Vec<(u32, CellKind)> = self
+                .raw_cells_buffer
+                .drain(self.row_start..)
+                .map(|(_, col, _, kind)| (col, kind))
+                .collect();
+            match self.pending_blank_rows.last_mut() {
+                Some(run) if run.first_row + run.count == row && run.cells == cells => {
+                    run.count += 1;
+                }
+                _ => self.pending_blank_rows.push(BlankRows {
+                    first_row: row,
+                    count: 1,
+                    cells,
+                }),
+            }
+            self.pending_blank_count += 1;
+            // 末尾的空白行不产生批次，单独按批次大小汇报进度，让估计随填充区收敛
+            if self
+                .pending_blank_count
+                .is_multiple_of(self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE))
+            {
+                self.report_progress(false);
+            }
+            return;
+        }
+        if !self.current_row_blank {
+            self.last_data_row = self.current_row;
+            if !self.forward_fill.is_empty() {
+                self.fill_down();
+            }
+        }
+        if self.pending_blank_count > 0 {
+            let mut pending = Vec::new();
+            for run in std::mem::take(&mut self.pending_blank_rows) {
+                for row in run.first_row..run.first_row + run.count {
+                    pending.extend(
+                        run.cells
+                            .iter()
+                            .map(|&(col, kind)| (row, col, String::new(), kind)),
+                    );
+                }
+            }
+            self.raw_cells_buffer
+                .splice(self.row_start..self.row_start, pending);
+        }
+        let mut rows = self.pending_blank_count + 1;
+        self.pending_blank_count = 0;
+        if self.options.skip_trailing_rows > 0 {
+            // 当前行进入暂存队列，挤出的最早一行才真正计入批次
+            let cells = self.raw_cells_buffer.drain(self.row_start..).collect();
+            self.trailing_rows.push_back((cells, rows));
+            if self.trailing_rows.len() <= self.options.skip_trailing_rows {
+                return;
+            }
+            let (cells, released) = self.trailing_rows.pop_front().unwrap();
+            self.raw_cells_buffer.extend(cells);
+            rows = released;
+        }
+        if let Some(sampler) = &mut self.sampler {
+            // 待计入的若干行中只有最后一行有数据，之前的空白行一律丢弃
+            let data_row = self.raw_cells_buffer[self.row_start..]
+                .iter()
+                .map(|(row, _, _, _)| *row)
+                .max();
+            if !sampler.keep() {
+                self.raw_cells_buffer.truncate(self.row_start);
+                self.rows_not_sampled += 1;
+                return;
+            }
+            if rows > 1 {
+                let cells: Vec<RawCell> = self
+                    .raw_cells_buffer
+                    .drain(self.row_start..)
+                    .filter(|(row, _, _, _)| Some(*row) == data_row)
+                    .collect();
+                self.raw_cells_buffer.extend(cells);
+                rows = 1;
+            }
+        }
+        self.current_batch_rows += rows;
+        self.total_rows += rows;
+    }
+
+    /// 空白行是否暂存到后面出现非空行为止
+    ///
+    /// 第一个数据行之前始终暂存，表头之后只有空白行时因此输出 0 行；之后只在需要丢弃末尾空白行时暂存，
+    /// `skip_trailing_rows` 与抽样同样依赖暂存，把空白行归入其后的数据行
+    fn holds_back_blank_rows(&self) -> bool {
+        self.last_data_row.is_none()
+            || self.options.drop_trailing_blank_rows
+            || self.options.skip_trailing_rows > 0
+            || self.sampler.is_some()
+    }
+
+    /// 按 `forward_fill_columns` 填充当前行的空单元格，并记下各列新的非空值
+    fn fill_down(&mut self) {
+        let row = self.current_row.unwrap();
+        for (col, last) in &mut self.forward_fill {
+            let cell = self.raw_cells_buffer[self.row_start..]
+                .iter_mut()
+                .find(|(_, c, _, _)| c == col);
+            match (cell, last.as_ref()) {
+                (Some((_, _, value, kind)), _) if !value.is_empty() => {
+                    *last = Some((value.clone(), *kind));
+                }
+                (Some((_, _, value, kind)), Some((last_value, last_kind))) => {
+                    *value = last_value.clone();
+                    *kind = *last_kind;
+                }
+                (None, Some((last_value, last_kind))) => {
+                    self.raw_cells_buffer
+                        .push((row, *col, last_value.clone(), *last_kind));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// 返回 batch size；自动模式下首次调用时根据已缓存的行估算行宽并确定
+    fn resolve_batch_size(&mut self) -> usize {
+        if let Some(size) = self.batch_size {
+            return size;
+        }
+        // 每个单元格按缓存中的 `RawCell` 本身、字符串内容与输出的 4 字节偏移量估算，
+        // 只按文本长度估算会严重低估列多而值短的 Sheet
+        let value_bytes: usize = self
+            .raw_cells_buffer
+            .iter()
+            .map(|(_, _, value, _)| std::mem::size_of::<RawCell>() + value.len())
+            .sum();
+        let size = match value_bytes.checked_div(self.current_batch_rows) {
+            Some(avg_bytes) => {
+                let row_bytes = (avg_bytes + self.num_cols * 4).max(1);
+                (AUTO_BATCH_TARGET_BYTES / row_bytes)
+                    .clamp(AUTO_BATCH_MIN_ROWS, AUTO_BATCH_MAX_ROWS)
+            }
+            None => DEFAULT_BATCH_SIZE,
+        };
+        info!("Auto batch size: {} rows", size);
+        self.batch_size = Some(size);
+        size
+    }
+
+    fn send_batch(&mut self) -> Result<()> {
+        let started = start_timer(self.options.collect_stats);
+        if self.writer.is_none() {
+            self.start_writer()?;
+        }
+        if let Some(WriterHandle::Inline(writer)) = &mut self.writer {
+            let cells = std::mem::take(&mut self.raw_cells_buffer);
+            let (batch, rejects) = process_batch(
+                self.batch_counter,
+                &cells,
+                self.schema.as_ref().unwrap(),
+                self.layout.as_ref().unwrap(),
+                &self.issues,
+                &self.counters,
+            )?;
+            writer.push(self.batch_counter, batch)?;
+            if let (Some(WriterHandle::Inline(rejects_writer)), Some(rejects)) =
+                (&mut self.rejects_writer, rejects)
+            {
+                rejects_writer.push(self.batch_counter, rejects)?;
+            }
+            // 复用单元格缓冲区的内存
+            self.raw_cells_buffer = cells;
+            self.raw_cells_buffer.clear();
+            self.batch_counter += 1;
+            self.current_batch_rows = 0;
+        } else if let Some(tx) = &self.work_tx {
+            // 按上一批次的实际单元格数预分配，避免自动模式下按列数预估过大
+            let capacity = self.raw_cells_buffer.len();
+            let buffer =
+                std::mem::replace(&mut self.raw_cells_buffer, Vec::with_capacity(capacity));
+            if tx.send((self.batch_counter, buffer)).is_err() {
+                return Err(anyhow::anyhow!("Worker channel closed"));
+            }
+            self.batch_counter += 1;
+            self.current_batch_rows = 0;
+        }
+        if let Some(started) = started {
+            self.send_time += started.elapsed();
+        }
+        self.report_progress(false);
+        Ok(())
+    }
+
+    /// 调用进度回调。总行数 = 已读取行数 + 剩余区域行数 × 数据行密度；
+    /// 密度只统计到最近一个非空白行，前几个批次假定剩余每行都有数据
+    fn report_progress(&self, done: bool) {
+        let Some(progress) = &self.options.progress else {
+            return;
+        };
+        let rows_read = self.total_rows;
+        let estimated_total_rows = if done {
+            rows_read
+        } else {
+            let position = self.current_row.unwrap_or(self.header_row_idx);
+            let remaining = self.last_row.saturating_sub(position) as usize;
+            let span_data = self
+                .last_data_row
+                .map_or(0, |row| row.saturating_sub(self.header_row_idx))
+                as usize;
+            if self.batch_counter < PROGRESS_SAMPLE_BATCHES || span_data == 0 {
+                rows_read + remaining
+            } else {
+                rows_read + (rows_read * remaining).div_ceil(span_data)
+            }
+        };
+        progress(Progress {
+            rows_read,
+            estimated_total_rows,
+        });
+    }
+
+    fn finish(&mut self, sheet_name: String) -> Result<ConversionReport> {
+        let read_time = self.started.map_or(Duration::ZERO, |started| {
+            started.elapsed().saturating_sub(self.send_time)
+        });
+        if !self.workers_started
+            && self
+                .current_row
+                .is_some_and(|row| (self.header_row_idx..=self.last_header_row()).contains(&row))
+        {
+            // 表头是最后一行，没有后续行触发 worker 启动
+            if self.current_row == Some(self.header_row_idx) && self.options.units_row {
+                self.header_cells = Some(std::mem::take(&mut self.current_row_cells));
+            }
+            self.current_row = None;
+            self.end_header()?;
+        }
+        if self.workers_started && self.current_row.is_some() {
+            self.commit_row();
+        }
+        if self.pending_blank_count > 0 {
+            info!("Dropped {} trailing blank rows", self.pending_blank_count);
+        }
+        if !self.trailing_rows.is_empty() {
+            info!("Dropped {} footer rows", self.trailing_rows.len());
+            self.trailing_rows.clear();
+        }
+        if self.title_rows_skipped > 0 {
+            info!("Skipped {} title rows", self.title_rows_skipped);
+        }
+        if self.sampler.is_some() {
+            info!(
+                "Sampled {} rows, {} rows not sampled",
+                self.total_rows, self.rows_not_sampled
+            );
+        }
+        if self.repeated_headers_skipped > 0 {
+            info!(
+                "Skipped {} repeated header rows",
+                self.repeated_headers_skipped
+            );
+        }
+
+        // Send remaining
+        if !self.raw_cells_buffer.is_empty() {
+            self.send_batch()?;
+        } else if self.workers_started && self.writer.is_none() {
+            // 只有表头没有数据时也要写出仅含 schema 的文件
+            self.start_writer()?;
+        }
+
+        // Drop work_tx to signal workers to stop
+        self.work_tx = None;
+
+        // Wait for workers, keeping the first error
+        let mut worker_result = Ok(());
+        for handle in self.worker_threads.drain(..) {
+            let result = handle
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Worker thread panicked")));
+            if worker_result.is_ok() {
+                worker_result = result;
+            }
+        }
+
+        // Drop our result_tx copy so the writer knows when all workers are done
+        self.result_tx = None;
+        self.reject_tx = None;
+
+        let writer_started = self.writer.is_some();
+        let WriterOutput {
+            row_group_stats,
+            column_profiles,
+            write_time,
+            duplicate_rows,
+            rows_written,
+            byte_limit_reached,
+            memory_pressure_flushes,
+            ..
+        } = match self.writer.take() {
+            Some(WriterHandle::Thread(handle)) => handle.join().unwrap()?,
+            Some(WriterHandle::Inline(writer)) => writer.finish()?,
+            None => WriterOutput::default(),
+        };
+        let rejected_rows = match self.rejects_writer.take() {
+            Some(WriterHandle::Thread(handle)) => handle.join().unwrap()?.rows_written,
+            Some(WriterHandle::Inline(writer)) => writer.finish()?.rows_written,
+            None => 0,
+        };
+        worker_result?;
+        if byte_limit_reached {
+            info!(
+                "Output size limit reached, stopped after {} rows",
+                rows_written
+            );
+        }
+
+        let verified_rows = if self.verify_output && writer_started {
+            let paths: Vec<PathBuf> = if self.atomic_outputs.is_empty() {
+                self.output_files.clone()
+            } else {
+                self.atomic_outputs
+                    .iter()
+                    .map(|output| output.temp_path().to_path_buf())
+                    .collect()
+            };
+            // 与写入线程报告的写出行数比较；按列拆分时每个文件都应有全部行
+            let mut verified = None;
+            for path in &paths {
+                verified = Some(verify_parquet_rows(
+                    path,
+                    rows_written,
+                    self.verification_reader_options()?,
+                )?);
+            }
+            verified
+        } else {
+            None
+        };
+
+        if writer_started {
+            for output in self.atomic_outputs.drain(..) {
+                output.commit()?;
+            }
+            if let Some(output) = self.rejects_atomic.take() {
+                output.commit()?;
+            }
+            for output in self.fan_out_atomic.drain(..) {
+                output.commit()?;
+            }
+        }
+
+        let phase_timings = self.options.collect_stats.then(|| PhaseTimings {
+            read: read_time,
+            build: Duration::from_nanos(self.counters.build_nanos.load(Ordering::Relaxed)),
+            write: write_time,
+        });
+        if let Some(timings) = &phase_timings {
+            for (i, stats) in row_group_stats.iter().enumerate() {
+                info!(
+                    "Row group {}: {} rows, {} -> {} bytes (ratio {:.2})",
+                    i,
+                    stats.num_rows,
+                    stats.uncompressed_bytes,
+                    stats.compressed_bytes,
+                    stats.compression_ratio().unwrap_or(0.0)
+                );
+            }
+            info!(
+                "Phase timings: read {:?}, build {:?}, write {:?}",
+                timings.read, timings.build, timings.write
+            );
+        }
+
+        self.report_progress(true);
+
+        let (errors, error_count) = self.issues.take();
+        let report = ConversionReport {
+            sheet_name,
+            total_rows: self.total_rows - self.issues.skipped_rows(),
+            errors,
+            error_count,
+            batch_size: self.resolve_batch_size(),
+            row_group_rows: row_group_stats.iter().map(|stats| stats.num_rows).collect(),
+            verified_rows,
+            row_group_stats: if self.options.collect_stats {
+                row_group_stats
+            } else {
+                Vec::new()
+            },
+            masked_rows: self.counters.masked_rows.load(Ordering::Relaxed),
+            repeated_headers_skipped: self.repeated_headers_skipped,
+            title_rows_skipped: self.title_rows_skipped,
+            rows_not_sampled: self.rows_not_sampled,
+            replacement_char_cells: self.replacement_char_cells,
+            has_header: self.has_header,
+            duplicate_rows_removed: duplicate_rows,
+            rows_written,
+            output_limit_reached: byte_limit_reached,
+            memory_pressure_flushes,
+            output_files: std::mem::take(&mut self.output_files),
+            constraint_violations: self.counters.constraint_violations.load(Ordering::Relaxed),
+            rejected_rows,
+            units: std::mem::take(&mut self.units),
+            high_cardinality_columns: std::mem::take(&mut self.high_cardinality_columns),
+            column_profiles,
+            phase_timings,
+        };
+        if let Some(on_complete) = &self.options.on_complete {
+            for path in &report.output_files {
+                on_complete(path, &report);
+            }
+        }
+        Ok(report)
+    }
+
+    /// 校验输出时使用的读取选项，启用加密时附带解密密钥
+    fn verification_reader_options(&self) -> Result<ArrowReaderOptions> {
+        #[allow(unused_mut)]
+        let mut reader_options = ArrowReaderOptions::new();
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = &self.options.encryption {
+            use parquet::encryption::decrypt::FileDecryptionProperties;
+
+            let mut builder = FileDecryptionProperties::builder(encryption.footer_key.clone());
+            for (column, key) in &encryption.encrypt_columns {
+                builder = builder.with_column_key(column, key.clone());
+            }
+            let decryption = builder
+                .build()
+                .context("Invalid encryption configuration")?;
+            reader_options = reader_options.with_file_decryption_properties(decryption);
+        }
+        Ok(reader_options)
+    }
+}
+
+/// 写入端：独立的写入线程，或单线程模式下直接在读取线程中写出
+enum WriterHandle {
+    Thread(thread::JoinHandle<Result<WriterOutput>>),
+    Inline(Box<OrderedWriter>),
+}
+
+/// 转换结果的去向
+#[derive(Clone)]
+enum OutputTarget {
+    /// 写出 Parquet 文件
+    Parquet,
+    /// 写入内存缓冲区
+    Memory(SharedBuffer),
+    /// 只统计各列概况，不写文件
+    Profile,
+    /// 在内存中收集全部批次，第一个批次为携带 schema 的空批次
+    Batches(SharedBatches),
+}
+
+/// 重新读取 Parquet 文件并统计行数，与 `expected` 不一致时返回错误
+fn verify_parquet_rows(
+    path: &Path,
+    expected: usize,
+    reader_options: ArrowReaderOptions,
+) -> Result<usize> {
+    let file = File::open(path).context("Failed to reopen output for verification")?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new_with_options(file, reader_options)
+        .context("Output verification failed: cannot read Parquet footer")?
+        .build()
+        .context("Output verification failed: cannot read Parquet footer")?;
+
+    let mut rows = 0;
+    for batch in reader {
+        rows += batch
+            .context("Output verification failed: cannot decode row group")?
+            .num_rows();
+    }
+    if rows != expected {
+        return Err(anyhow::anyhow!(
+            "Output verification failed: expected {} rows, found {}",
+            expected,
+            rows
+        ));
+    }
+    info!("Verified {} rows in {}", rows, path.display());
+    Ok(rows)
+}
+
+/// 按指定列排序整个批次，空值排在最后
+///
+/// 若该列所有非空值都能解析为数字，则按数值排序，否则按字符串排序；返回值的第二项表示是否按数值排序。
+fn sort_record_batch(
+    batch: &RecordBatch,
+    column: usize,
+    order: SortOrder,
+) -> Result<(RecordBatch, bool)> {
+    let values = batch
+        .column(column)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .context("Sort column is not a string column")?;
+
+    let numeric: Option<Float64Array> = values
+        .iter()
+        .map(|v| match v {
+            Some(s) => s.trim().parse::<f64>().ok().map(Some),
+            None => Some(None),
+        })
+        .collect();
+
+    let sort_options = SortOptions {
+        descending: order == SortOrder::Descending,
+        nulls_first: false,
+    };
+    let indices = match &numeric {
+        Some(numbers) => sort_to_indices(numbers, Some(sort_options), None),
+        None => sort_to_indices(values, Some(sort_options), None),
+    }
+    .context("Failed to sort rows")?;
+
+    let sorted = take_record_batch(batch, &indices).context("Failed to reorder rows")?;
+    Ok((sorted, numeric.is_some()))
+}
+
+/// 默认写入属性：ZSTD 压缩（未启用 `zstd` feature 时为 Snappy），`created_by` 为本 crate 的名称和版本
+fn default_writer_properties() -> WriterPropertiesBuilder {
+    #[cfg(feature = "zstd")]
+    let compression = Compression::ZSTD(ZstdLevel::default());
+    #[cfg(not(feature = "zstd"))]
+    let compression = Compression::SNAPPY;
+    WriterProperties::builder()
+        .set_compression(compression)
+        .set_created_by(DEFAULT_CREATED_BY.to_string())
+}
+
+/// 根据选项构建 Parquet 写入属性
+fn build_writer_properties(options: &ConvertExcelToParquetOptions) -> Result<WriterProperties> {
+    if let Some(props) = &options.writer_properties {
+        return Ok(props.clone());
+    }
+
+    let mut builder = default_writer_properties();
+    builder = match options.preset {
+        Some(ConvertPreset::FastWrite) => builder
+            .set_compression(Compression::SNAPPY)
+            .set_dictionary_enabled(false),
+        #[cfg(feature = "zstd")]
+        Some(ConvertPreset::SmallFile) => builder
+            .set_compression(Compression::ZSTD(ZstdLevel::try_new(19)?))
+            .set_dictionary_enabled(true),
+        #[cfg(not(feature = "zstd"))]
+        Some(ConvertPreset::SmallFile) => {
+            return Err(anyhow::anyhow!(
+                "The small-file preset needs the zstd feature"
+            ));
+        }
+        Some(ConvertPreset::Compatibility) => builder
+            .set_compression(Compression::SNAPPY)
+            .set_writer_version(WriterVersion::PARQUET_1_0),
+        None => builder,
+    };
+    if let Some(created_by) = &options.created_by {
+        builder = builder.set_created_by(created_by.clone());
+    }
+    if options.statistics_truncate_length.is_some() {
+        builder = builder.set_statistics_truncate_length(options.statistics_truncate_length);
+    }
+
+    #[cfg(feature = "encryption")]
+    if let Some(encryption) = &options.encryption {
+        use parquet::encryption::encrypt::FileEncryptionProperties;
+
+        let mut encryption_builder =
+            FileEncryptionProperties::builder(encryption.footer_key.clone());
+        for (column, key) in &encryption.encrypt_columns {
+            encryption_builder = encryption_builder.with_column_key(column, key.clone());
+        }
+        let encryption_props = encryption_builder
+            .build()
+            .context("Invalid encryption configuration")?;
+        builder = builder.with_file_encryption_properties(encryption_props);
+    }
+
+    Ok(builder.build())
+}
+
+/// 将日期单元格的序列号换算到 1900 纪元；时长与纪元无关，保持原值
+fn date_serial_1900(dt: &ExcelDateTime, date_system: Option<DateSystem>) -> f64 {
+    if !dt.is_datetime() {
+        return dt.as_f64();
+    }
+    // calamine 不公开单元格的纪元标记，通过与同值的 1904 纪元日期比较得到
+    let is_1904 = match date_system {
+        Some(system) => system == DateSystem::Excel1904,
+        None => *dt == ExcelDateTime::new(dt.as_f64(), ExcelDateTimeType::DateTime, true),
+    };
+    if is_1904 {
+        dt.as_f64() + EXCEL_1904_OFFSET_DAYS
+    } else {
+        dt.as_f64()
+    }
+}
+
+/// 将单元格值转为字符串
+///
+/// 富文本单元格由 calamine 在解析共享字符串时将各段文本拼接为纯文本（忽略格式与注音），
+/// 到这里已是普通字符串，不会带出格式标记。
+fn cell_to_string(cell: &calamine::DataRef) -> String {
+    match cell {
+        calamine::DataRef::Int(i) => i.to_string(),
+        calamine::DataRef::Float(f) => f.to_string(),
+        calamine::DataRef::String(s) => s.clone(),
+        calamine::DataRef::SharedString(s) => s.to_string(),
+        calamine::DataRef::Bool(b) => b.to_string(),
+        calamine::DataRef::DateTime(dt) => dt.to_string(),
+        calamine::DataRef::DurationIso(d) => d.to_string(),
+        calamine::DataRef::DateTimeIso(dt) => dt.to_string(),
+        calamine::DataRef::Error(e) => format!("{:?}", e),
+        calamine::DataRef::Empty => String::new(),
+    }
+}
+
+/// 将 `Data` 借用为 `DataRef`，以复用流式读取的单元格处理
+fn data_as_ref(data: &Data) -> calamine::DataRef<'_> {
+    use calamine::DataRef;
+
+    match data {
+        Data::Int(i) => DataRef::Int(*i),
+        Data::Float(f) => DataRef::Float(*f),
+        Data::String(s) => DataRef::SharedString(s),
+        Data::Bool(b) => DataRef::Bool(*b),
+        Data::DateTime(dt) => DataRef::DateTime(*dt),
+        Data::DateTimeIso(s) => DataRef::DateTimeIso(s.clone()),
+        Data::DurationIso(s) => DataRef::DurationIso(s.clone()),
+        Data::Error(e) => DataRef::Error(e.clone()),
+        Data::Empty => DataRef::Empty,
+    }
+}
+
+// 新的 Worker 函数：从 RawCell 构建 RecordBatch
+fn create_record_batch_from_cells(
+    id: usize,
+    schema: &Arc<Schema>,
+    layout: &BatchLayout,
+    cells: &[RawCell],
+    issues: &IssueCollector,
+    counters: &BatchCounters,
+) -> Result<(RecordBatch, Option<RecordBatch>)> {
+    let mut row_map: HashMap<u32, HashMap<u32, (&str, CellKind)>> = HashMap::new();
+    let mut row_indices: Vec<u32> = Vec::new();
+
+    for (r, c, v, kind) in cells {
+        if layout.columns.binary_search(c).is_err()
+            && layout.mask != Some(*c)
+            && layout.ignored_columns.binary_search(c).is_err()
+            && layout.overflow.is_none_or(|start| *c < start)
+            && !v.is_empty()
+        {
+            issues.record(ConversionIssue {
+                row: Some(*r),
+                col: Some(*c),
+                message: "Cell is outside the header columns and was dropped".to_string(),
+            });
+        }
+        let row_entry = row_map.entry(*r).or_insert_with(|| {
+            row_indices.push(*r);
+            HashMap::new()
+        });
+        row_entry.insert(*c, (v.as_str(), *kind));
+    }
+
+    row_indices.sort_unstable();
+
+    // 启用拒绝行输出时被拒绝的行先照常构建并记下原因，最后再从批次中分出
+    let mut reject_reasons: Vec<Option<String>> = Vec::new();
+    if let Some(mask) = layout.mask {
+        let truthy = |row_idx: &u32| {
+            is_truthy(
+                row_map
+                    .get(row_idx)
+                    .and_then(|cols| cols.get(&mask))
+                    .copied(),
+                &layout.bool_true_text,
+            )
+        };
+        let before = row_indices.len();
+        if layout.rejects.is_some() {
+            reject_reasons = row_indices
+                .iter()
+                .map(|row_idx| (!truthy(row_idx)).then(|| MASK_REJECT_REASON.to_string()))
+                .collect();
+            let masked = reject_reasons.iter().flatten().count();
+            counters.masked_rows.fetch_add(masked, Ordering::Relaxed);
+        } else {
+            row_indices.retain(truthy);
+            counters
+                .masked_rows
+                .fetch_add(before - row_indices.len(), Ordering::Relaxed);
+        }
+    }
+    if layout.rejects.is_some() {
+        reject_reasons.resize(row_indices.len(), None);
+    }
+
+    let cell = |row_idx: &u32, col: u32| row_map.get(row_idx).and_then(|cols| cols.get(&col));
+    let mut data_arrays: Vec<ArrayRef> = Vec::with_capacity(layout.columns.len());
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len());
+
+    for (&target_col_idx, error_policy) in layout.columns.iter().zip(&layout.error_policies) {
+        let values: StringArray = row_indices
+            .iter()
+            .map(|row_idx| match cell(row_idx, target_col_idx)? {
+                (value, CellKind::Error) => match error_policy {
+                    ErrorCellPolicy::ErrorName => Some(*value),
+                    ErrorCellPolicy::Null => None,
+                    ErrorCellPolicy::Replace(replacement) => Some(replacement.as_str()),
+                },
+                (value, _) if layout.whitespace_as_null && value.trim().is_empty() => None,
+                (value, _) => Some(*value),
+            })
+            .collect();
+        let values: ArrayRef = Arc::new(values);
+        data_arrays.push(values.clone());
+        arrays.push(values);
+
+        if layout.type_sidecar {
+            let kinds: StringArray = row_indices
+                .iter()
+                .map(|row_idx| cell(row_idx, target_col_idx).map(|(_, kind)| kind.as_str()))
+                .collect();
+            arrays.push(Arc::new(kinds));
+        }
+    }
+
+    if let Some(start) = layout.overflow {
+        let values: StringArray = row_indices
+            .iter()
+            .map(|row_idx| {
+                let mut cells: Vec<_> = row_map
+                    .get(row_idx)?
+                    .iter()
+                    .filter(|(col, (value, _))| **col >= start && !value.is_empty())
+                    .collect();
+                if cells.is_empty() {
+                    return None;
+                }
+                cells.sort_unstable_by_key(|(col, _)| **col);
+                let values: Vec<&str> = cells.into_iter().map(|(_, (value, _))| *value).collect();
+                serde_json::to_string(&values).ok()
+            })
+            .collect();
+        arrays.push(Arc::new(values));
+    }
+
+    for constraint in &layout.constraints {
+        let values = data_arrays[constraint.column]
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        for (i, row_idx) in row_indices.iter().enumerate() {
+            // 被掩码过滤的行与不输出拒绝行时一样不做校验
+            if reject_reasons
+                .get(i)
+                .is_some_and(|reason| reason.as_deref() == Some(MASK_REJECT_REASON))
+            {
+                continue;
+            }
+            let value = values.is_valid(i).then(|| values.value(i));
+            let Some(message) = constraint.violation(value) else {
+                continue;
+            };
+            if !issues.is_collecting() {
+                return Err(anyhow::anyhow!("Row {}: {}", row_idx, message));
+            }
+            counters
+                .constraint_violations
+                .fetch_add(1, Ordering::Relaxed);
+            if let Some(reason) = reject_reasons.get_mut(i) {
+                match reason {
+                    Some(reason) => {
+                        reason.push_str("; ");
+                        reason.push_str(&message);
+                    }
+                    None => {
+                        counters
+                            .constraint_rejected_rows
+                            .fetch_add(1, Ordering::Relaxed);
+                        *reason = Some(message.clone());
+                    }
+                }
+            }
+            issues.record(ConversionIssue {
+                row: Some(*row_idx),
+                col: Some(layout.columns[constraint.column]),
+                message,
+            });
+        }
+    }
+
+    for column in &layout.derived {
+        arrays.push(concat_columns(&data_arrays, column, row_indices.len()));
+    }
+    if let Some((columns, algo)) = &layout.row_hash {
+        let sources: Vec<&StringArray> = columns
+            .iter()
+            .map(|&i| {
+                data_arrays[i]
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap()
+            })
+            .collect();
+        let hashes: StringArray = (0..row_indices.len())
+            .map(|row| Some(row_hash(&sources, row, *algo)))
+            .collect();
+        arrays.push(Arc::new(hashes));
+    }
+    for value in &layout.constants {
+        let values: StringArray =
+            std::iter::repeat_n(Some(value.as_str()), row_indices.len()).collect();
+        arrays.push(Arc::new(values));
+    }
+
+    if let Some(order) = &layout.column_order {
+        arrays = order.iter().map(|&i| arrays[i].clone()).collect();
+    }
+    if layout.strict_validation {
+        validate_batch_arrays(id, schema, &arrays, row_indices.len())?;
+    }
+    let batch =
+        RecordBatch::try_new(schema.clone(), arrays).context("Failed to create record batch")?;
+    match &layout.rejects {
+        Some(rejects) => split_rejects(batch, rejects, reject_reasons),
+        None => Ok((batch, None)),
+    }
+}
+
+/// 检查批次的列数与 schema 一致，且每列的长度都等于预期行数
+fn validate_batch_arrays(
+    id: usize,
+    schema: &Schema,
+    arrays: &[ArrayRef],
+    expected_rows: usize,
+) -> Result<()> {
+    if arrays.len() != schema.fields().len() {
+        return Err(anyhow::anyhow!(
+            "Batch {}: built {} columns, schema has {}",
+            id,
+            arrays.len(),
+            schema.fields().len()
+        ));
+    }
+    for (field, array) in schema.fields().iter().zip(arrays) {
+        if array.len() != expected_rows {
+            return Err(anyhow::anyhow!(
+                "Batch {}: column '{}' has {} values, expected {} rows",
+                id,
+                field.name(),
+                array.len(),
+                expected_rows
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 按拒绝原因将批次分为输出行与拒绝行，拒绝行末尾追加原因列
+fn split_rejects(
+    batch: RecordBatch,
+    rejects_schema: &Arc<Schema>,
+    reasons: Vec<Option<String>>,
+) -> Result<(RecordBatch, Option<RecordBatch>)> {
+    let keep: BooleanArray = reasons
+        .iter()
+        .map(|reason| Some(reason.is_none()))
+        .collect();
+    let rejected = filter_record_batch(&batch, &not(&keep)?)?;
+    let mut columns = rejected.columns().to_vec();
+    columns.push(Arc::new(
+        reasons
+            .into_iter()
+            .flatten()
+            .map(Some)
+            .collect::<StringArray>(),
+    ));
+    let rejected = RecordBatch::try_new(rejects_schema.clone(), columns)
+        .context("Failed to create rejects batch")?;
+    Ok((filter_record_batch(&batch, &keep)?, Some(rejected)))
+}
+
+/// 拒绝行文件的 schema：输出列加上非空的 [`REJECT_REASON_COLUMN`]
+fn rejects_schema(schema: &Schema) -> Arc<Schema> {
+    let mut fields = schema.fields().to_vec();
+    fields.push(Arc::new(Field::new(
+        REJECT_REASON_COLUMN,
+        DataType::Utf8,
+        false,
+    )));
+    Arc::new(Schema::new(fields))
+}
+
+/// 按 `split` 将 schema 的列分组：每组为全部键列加上其余列中按顺序的一段
+fn split_columns(schema: &Schema, split: &ColumnSplit) -> Result<Vec<Vec<usize>>> {
+    let keys = split
+        .key_columns
+        .iter()
+        .map(|name| {
+            schema
+                .index_of(name)
+                .map_err(|_| anyhow::anyhow!("Split key column '{}' not found in headers", name))
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    let rest: Vec<usize> = (0..schema.fields().len())
+        .filter(|i| !keys.contains(i))
+        .collect();
+    if rest.is_empty() {
+        return Ok(vec![keys]);
+    }
+    Ok(rest
+        .chunks(split.max_columns - keys.len())
+        .map(|chunk| keys.iter().chain(chunk).copied().collect())
+        .collect())
+}
+
+/// 按列拆分时第 `k` 个文件的路径：`data.parquet` -> `data.part-cols-k.parquet`
+fn column_part_path(output_path: &Path, k: usize) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match output_path.extension() {
+        Some(extension) => format!("{}.part-cols-{}.{}", stem, k, extension.to_string_lossy()),
+        None => format!("{}.part-cols-{}", stem, k),
+    };
+    output_path.with_file_name(file_name)
+}
+
+/// 将时间格式化为 RFC 3339 的 UTC 时间（精确到秒），如 `2024-01-31T08:00:00Z`
+fn format_utc_timestamp(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // 由 1970-01-01 起的天数换算公历日期（Howard Hinnant 的 civil_from_days）
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// 掩码列的取值是否为真：缺失、空字符串、`false` 与数值 0 为假。
+/// 布尔单元格按原值判断，`true_text` 为 TRUE 的输出文本，不受 `bool_string_format` 的影响
+fn is_truthy(value: Option<(&str, CellKind)>, true_text: &str) -> bool {
+    if let Some((value, CellKind::Bool)) = value {
+        return value == true_text;
+    }
+    let Some(value) = value
+        .map(|(value, _)| value.trim())
+        .filter(|v| !v.is_empty())
+    else {
+        return false;
+    };
+    if value.eq_ignore_ascii_case("false") {
+        return false;
+    }
+    value.parse::<f64>() != Ok(0.0)
+}
+
+/// 构建一个批次及其拒绝行（启用 `rejects_output` 时）；Collect 模式下构建失败的批次
+/// 记录错误后以空批次代替，以保持写入顺序
+fn process_batch(
+    id: usize,
+    cells: &[RawCell],
+    schema: &Arc<Schema>,
+    layout: &BatchLayout,
+    issues: &IssueCollector,
+    counters: &BatchCounters,
+) -> Result<(RecordBatch, Option<RecordBatch>)> {
+    let _span = enter_span!("batch", batch_id = id, cells = cells.len());
+    let started = start_timer(layout.timed);
+    let result = create_record_batch_from_cells(id, schema, layout, cells, issues, counters);
+    if let Some(started) = started {
+        counters
+            .build_nanos
+            .fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+    match result {
+        Ok(batch) => Ok(batch),
+        Err(e) if issues.is_collecting() && !layout.strict_validation => {
+            issues.record(ConversionIssue {
+                row: cells.first().map(|(r, _, _, _)| *r),
+                col: None,
+                message: format!("Skipped batch {}: {:#}", id, e),
+            });
+            issues.add_skipped_rows(cells.chunk_by(|a, b| a.0 == b.0).count());
+            let rejects = layout.rejects.clone().map(RecordBatch::new_empty);
+            Ok((RecordBatch::new_empty(schema.clone()), rejects))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 计算一行的哈希：每个值依次编码为 null 标记（0/1）、小端 u64 字节长度与 UTF-8 字节
+fn row_hash(sources: &[&StringArray], row: usize, algo: ChecksumAlgo) -> String {
+    use sha2::Digest;
+    use std::hash::Hasher;
+
+    let mut xxhash = twox_hash::XxHash64::with_seed(0);
+    let mut sha256 = sha2::Sha256::new();
+    let mut update = |bytes: &[u8]| match algo {
+        ChecksumAlgo::XxHash64 => xxhash.write(bytes),
+        ChecksumAlgo::Sha256 => sha256.update(bytes),
+    };
+    for source in sources {
+        if source.is_null(row) {
+            update(&[0]);
+        } else {
+            let value = source.value(row).as_bytes();
+            update(&[1]);
+            update(&(value.len() as u64).to_le_bytes());
+            update(value);
+        }
+    }
+    match algo {
+        ChecksumAlgo::XxHash64 => format!("{:016x}", xxhash.finish()),
+        ChecksumAlgo::Sha256 => sha256
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    }
+}
+
+/// 表头是否按 `FirstRow` 读取：按样式识别只是事先确定表头所在的行
+fn header_is_first_row(detection: HeaderDetection) -> bool {
+    match detection {
+        HeaderDetection::FirstRow => true,
+        #[cfg(feature = "styles")]
+        HeaderDetection::ByStyle => true,
+        HeaderDetection::None | HeaderDetection::Guess => false,
+    }
+}
+
+/// 猜测表头行：`cells` 为表头候选行及其下一行的单元格
+fn looks_like_header(cells: &[RawCell], header_row: u32) -> bool {
+    let mut header_cols = HashSet::new();
+    for (row, col, value, kind) in cells {
+        if *row != header_row || value.is_empty() {
+            continue;
+        }
+        if !matches!(kind, CellKind::String | CellKind::Empty) {
+            return false;
+        }
+        header_cols.insert(*col);
+    }
+    !header_cols.is_empty()
+        && cells
+            .iter()
+            .filter(|(row, _, value, _)| *row != header_row && !value.is_empty())
+            .all(|(_, col, _, _)| header_cols.contains(col))
+}
+
+/// 按派生列的定义逐行拼接源列
+fn concat_columns(
+    arrays: &[ArrayRef],
+    column: &ResolvedDerivedColumn,
+    num_rows: usize,
+) -> ArrayRef {
+    let sources: Vec<&StringArray> = column
+        .sources
+        .iter()
+        .map(|&i| arrays[i].as_any().downcast_ref::<StringArray>().unwrap())
+        .collect();
+
+    let values = (0..num_rows).map(|row| {
+        let parts: Vec<&str> = match column.null_handling {
+            NullHandling::Skip => sources
+                .iter()
+                .filter(|source| !source.is_null(row))
+                .map(|source| source.value(row))
+                .collect(),
+            NullHandling::AsEmpty => sources
+                .iter()
+                .map(|source| {
+                    if source.is_null(row) {
+                        ""
+                    } else {
+                        source.value(row)
+                    }
+                })
+                .collect(),
+        };
+        (!parts.is_empty()).then(|| parts.join(&column.separator))
+    });
+    Arc::new(StringArray::from_iter(values))
+}
+
+/// 空表头命名为 `Field_<i>`，`i` 为相对 `start_col` 的列偏移（或按 `index_base` 使用列字母），不受被丢弃的列影响；
+/// 重名的表头按 `strategy` 处理，`case_insensitive` 时按小写形式判断重名。
+/// `KeepFirstDropRest` 丢弃的列从 `columns` 中移除，连同表头一起返回其列号
+fn build_headers(
+    cells: &HashMap<u32, String>,
+    columns: &mut Vec<u32>,
+    start_col: u32,
+    case_insensitive: bool,
+    strategy: DedupStrategy,
+    index_base: HeaderIndexBase,
+) -> Result<(Vec<String>, Vec<u32>)> {
+    let mut headers: Vec<String> = columns
+        .iter()
+        .map(|col| cells.get(col).cloned().unwrap_or_default())
+        .collect();
+
+    for (header, col) in headers.iter_mut().zip(columns.iter()) {
+        if header.is_empty() {
+            *header = match index_base {
+                HeaderIndexBase::RegionRelative => format!("Field_{}", col - start_col),
+                HeaderIndexBase::ColumnLetter => {
+                    format!("Field_{}", cell_ref::column_letters(*col))
+                }
+            };
+        }
+    }
+
+    // 每个表头第一次出现的列号与出现次数
+    let mut seen: HashMap<String, (u32, usize)> = HashMap::new();
+    let mut keep = Vec::with_capacity(headers.len());
+    for (header, col) in headers.iter_mut().zip(columns.iter()) {
+        let key = if case_insensitive {
+            header.to_lowercase()
+        } else {
+            header.clone()
+        };
+        let (first_col, count) = seen.entry(key).or_insert((*col, 0));
+        *count += 1;
+        keep.push(*count == 1 || strategy != DedupStrategy::KeepFirstDropRest);
+        if *count > 1 {
+            match strategy {
+                DedupStrategy::NumericSuffix { base } => {
+                    *header = format!("{}_{}", header, base + *count - 2);
+                }
+                DedupStrategy::LetterSuffix => {
+                    let letters = cell_ref::column_letters(*count as u32 - 1).to_lowercase();
+                    *header = format!("{}_{}", header, letters);
+                }
+                DedupStrategy::Error => {
+                    return Err(anyhow::anyhow!(
+                        "Duplicate header '{}' in columns {} and {}",
+                        header,
+                        cell_ref::column_letters(*first_col),
+                        cell_ref::column_letters(*col)
+                    ));
+                }
+                DedupStrategy::KeepFirstDropRest => {}
+            }
+        }
+    }
+    let mut dropped = Vec::new();
+    if keep.contains(&false) {
+        let mut keep_iter = keep.iter();
+        columns.retain(|col| {
+            let kept = *keep_iter.next().unwrap();
+            if !kept {
+                dropped.push(*col);
+            }
+            kept
+        });
+        let mut keep_iter = keep.iter();
+        headers.retain(|_| *keep_iter.next().unwrap());
+    }
+    Ok((headers, dropped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Array;
+    use rust_xlsxwriter::Workbook;
+
+    /// 写入测试用工作簿，每个 Sheet 为若干行字符串
+    fn write_workbook(path: &Path, sheets: &[(&str, Vec<Vec<&str>>)]) {
+        let mut workbook = Workbook::new();
+        for (name, rows) in sheets {
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(*name).unwrap();
+            for (r, row) in rows.iter().enumerate() {
+                for (c, value) in row.iter().enumerate() {
+                    worksheet.write_string(r as u32, c as u16, *value).unwrap();
+                }
+            }
+        }
+        workbook.save(path).unwrap();
+    }
+
+    /// 读取 Parquet，返回列名和按行排列的字符串值
+    fn read_parquet(path: &Path) -> (Vec<String>, Vec<Vec<Option<String>>>) {
+        let file = File::open(path).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let columns = builder
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+
+        let mut rows = Vec::new();
+        for batch in builder.build().unwrap() {
+            let batch = batch.unwrap();
+            for r in 0..batch.num_rows() {
+                let row = batch
+                    .columns()
+                    .iter()
+                    .map(|col| {
+                        let col = col.as_any().downcast_ref::<StringArray>().unwrap();
+                        (!col.is_null(r)).then(|| col.value(r).to_string())
+                    })
+                    .collect();
+                rows.push(row);
+            }
+        }
+        (columns, rows)
+    }
+
+    #[test]
+    fn test_all_sheets_with_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("book.xlsx");
+        write_workbook(
+            &input,
+            &[
+                (
+                    "Report",
+                    vec![
+                        vec!["Quarterly report"],
+                        vec!["generated"],
+                        vec!["id", "name"],
+                        vec!["1", "a"],
+                        vec!["2", "b"],
+                    ],
+                ),
+                ("Plain", vec![vec!["code", "qty"], vec!["x", "3"]]),
+            ],
+        );
+
+        let output_dir = dir.path().join("out");
+        let options = ConvertExcelToParquetOptions::new(&input, &output_dir);
+        let overrides = HashMap::from([(
+            "Report".to_string(),
+            ConvertOverrides {
+                skip_rows: Some(2),
+                ..Default::default()
+            },
+        )]);
+
+        let outputs = convert_all_sheets_to_parquet(options, Some(overrides)).unwrap();
+        assert_eq!(
+            outputs,
+            vec![
+                output_dir.join("Report.parquet"),
+                output_dir.join("Plain.parquet")
+            ]
+        );
+
+        let (columns, rows) = read_parquet(&outputs[0]);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows.len(), 2);
+
+        let (columns, rows) = read_parquet(&outputs[1]);
+        assert_eq!(columns, vec!["code", "qty"]);
+        assert_eq!(rows, vec![vec![Some("x".into()), Some("3".into())]]);
+    }
+
+    #[test]
+    fn test_all_sheets_sanitized_filenames() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("names.xlsx");
+        write_workbook(
+            &input,
+            &[
+                ("Q1<Q2", vec![vec!["id"], vec!["1"]]),
+                ("Q1>Q2", vec![vec!["id"], vec!["2"]]),
+            ],
+        );
+
+        let output_dir = dir.path().join("out");
+        let options = ConvertExcelToParquetOptions::new(&input, &output_dir);
+        let outputs = convert_all_sheets_to_parquet(options, None).unwrap();
+        assert_eq!(
+            outputs,
+            vec![
+                output_dir.join("Q1_Q2.parquet"),
+                output_dir.join("Q1_Q2_1.parquet")
+            ]
+        );
+        assert_eq!(read_parquet(&outputs[1]).1, vec![vec![Some("2".into())]]);
+
+        let options = ConvertExcelToParquetOptions {
+            sheet_filename_sanitize: FilenameSanitize::Error,
+            ..ConvertExcelToParquetOptions::new(&input, &output_dir)
+        };
+        assert!(convert_all_sheets_to_parquet(options, None).is_err());
+
+        assert_eq!(
+            sanitize_sheet_filename("Q1/Q2", FilenameSanitize::Hash)
+                .unwrap()
+                .len(),
+            16
+        );
+        assert_eq!(
+            sanitize_sheet_filename("..", FilenameSanitize::Replace('-')).unwrap(),
+            "---"
+        );
+    }
+
+    #[test]
+    fn test_all_sheets_with_output_fn() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("routed.xlsx");
+        let names = ["North", "South", "East"];
+        let sheets: Vec<_> = names
+            .iter()
+            .map(|name| (*name, vec![vec!["id"], vec!["1"]]))
+            .collect();
+        write_workbook(&input, &sheets);
+
+        let root = dir.path().to_path_buf();
+        let options = ConvertExcelToParquetOptions::new(&input, Path::new("unused"));
+        let outputs = convert_all_sheets_to_parquet_with(options, None, |sheet| {
+            let idx = names.iter().position(|name| *name == sheet).unwrap();
+            let group = if idx % 2 == 0 { "even" } else { "odd" };
+            root.join(group).join(format!("{}.parquet", sheet))
+        })
+        .unwrap();
+
+        assert_eq!(
+            outputs,
+            vec![
+                root.join("even/North.parquet"),
+                root.join("odd/South.parquet"),
+                root.join("even/East.parquet")
+            ]
+        );
+        assert!(outputs.iter().all(|path| path.exists()));
+    }
+
+    #[test]
+    fn test_include_sheet_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("tabs.xlsx");
+        write_workbook(
+            &input,
+            &[
+                ("A", vec![vec!["id"], vec!["1"], vec!["2"]]),
+                ("B", vec![vec!["id"], vec!["3"]]),
+            ],
+        );
+
+        let output_dir = dir.path().join("out");
+        let options = ConvertExcelToParquetOptions {
+            include_sheet_column: Some("sheet".to_string()),
+            ..ConvertExcelToParquetOptions::new(&input, &output_dir)
+        };
+        let outputs = convert_all_sheets_to_parquet(options, None).unwrap();
+        let (columns, rows) = read_parquet(&outputs[0]);
+        assert_eq!(columns, vec!["id", "sheet"]);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("1".into()), Some("A".into())],
+                vec![Some("2".into()), Some("A".into())]
+            ]
+        );
+        assert_eq!(
+            read_parquet(&outputs[1]).1,
+            vec![vec![Some("3".into()), Some("B".into())]]
+        );
+
+        let output = dir.path().join("conflict.parquet");
+        let options = ConvertExcelToParquetOptions {
+            include_sheet_column: Some("id".to_string()),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+    }
+
+    #[test]
+    fn test_constant_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("loads.xlsx");
+        let output = dir.path().join("loads.parquet");
+        write_workbook(
+            &input,
+            &[("Orders", vec![vec!["id"], vec!["1"], vec!["2"]])],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            include_sheet_column: Some("sheet".to_string()),
+            constant_columns: HashMap::from([
+                ("load_date".to_string(), "{now}".to_string()),
+                ("batch".to_string(), "b-7 {source_file}/{sheet}".to_string()),
+            ]),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "sheet", "batch", "load_date"]);
+        assert!(
+            rows.iter()
+                .all(|row| row[2].as_deref() == Some("b-7 loads.xlsx/Orders"))
+        );
+        let load_date = rows[0][3].clone().unwrap();
+        assert_eq!(rows[1][3].as_deref(), Some(load_date.as_str()));
+        assert_eq!(load_date.len(), 20);
+        assert!(load_date.ends_with('Z'));
+
+        assert_eq!(
+            format_utc_timestamp(std::time::UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+            "2024-02-29T12:34:56Z"
+        );
+        assert_eq!(
+            format_utc_timestamp(std::time::UNIX_EPOCH),
+            "1970-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_print_title_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("gov.xlsx");
+        let output = dir.path().join("gov.parquet");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "Region: North").unwrap();
+        worksheet.write_string(1, 1, "FY2024").unwrap();
+        for (c, value) in ["id", "amount"].iter().enumerate() {
+            worksheet.write_string(2, c as u16, *value).unwrap();
+        }
+        worksheet.write_string(3, 0, "1").unwrap();
+        worksheet.write_string(3, 1, "10").unwrap();
+        // 标题行包含表头行，表头不应重复成为常量列
+        worksheet.set_repeat_rows(0, 2).unwrap();
+        workbook.save(&input).unwrap();
+
+        let options = ConvertExcelToParquetOptions {
+            skip_rows: 2,
+            print_title_columns: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(
+            columns,
+            vec!["id", "amount", "print_title_A1", "print_title_B2"]
+        );
+        assert_eq!(
+            rows,
+            vec![vec![
+                Some("1".into()),
+                Some("10".into()),
+                Some("Region: North".into()),
+                Some("FY2024".into())
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_all_sheets_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("ordered.xlsx");
+        write_workbook(
+            &input,
+            &[
+                ("North", vec![vec!["id"], vec!["1"]]),
+                ("South", vec![vec!["id"], vec!["2"]]),
+                ("East", vec![vec!["id"], vec!["3"]]),
+            ],
+        );
+
+        let output_dir = dir.path().join("out");
+        let options = ConvertExcelToParquetOptions {
+            sheet_order: Some(vec!["East".to_string(), "North".to_string()]),
+            ..ConvertExcelToParquetOptions::new(&input, &output_dir)
+        };
+        let outputs = convert_all_sheets_to_parquet(options, None).unwrap();
+        assert_eq!(
+            outputs,
+            vec![
+                output_dir.join("East.parquet"),
+                output_dir.join("North.parquet")
+            ]
+        );
+        let rows: Vec<_> = outputs
+            .iter()
+            .flat_map(|path| read_parquet(path).1)
+            .collect();
+        assert_eq!(rows, vec![vec![Some("3".into())], vec![Some("1".into())]]);
+        assert!(!output_dir.join("South.parquet").exists());
+
+        let options = ConvertExcelToParquetOptions {
+            sheet_order: Some(vec!["West".to_string()]),
+            ..ConvertExcelToParquetOptions::new(&input, &output_dir)
+        };
+        assert!(convert_all_sheets_to_parquet(options, None).is_err());
+    }
+
+    #[test]
+    fn test_all_sheets_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("manifest.xlsx");
+        write_workbook(
+            &input,
+            &[
+                ("North", vec![vec!["id"], vec!["1"], vec!["2"]]),
+                ("South", vec![vec!["id"], vec!["3"]]),
+                ("Empty", vec![vec!["id"]]),
+            ],
+        );
+
+        let output_dir = dir.path().join("out");
+        let manifest_path = dir.path().join("manifest.json");
+        let options = ConvertExcelToParquetOptions {
+            write_manifest: Some(manifest_path.clone()),
+            ..ConvertExcelToParquetOptions::new(&input, &output_dir)
+        };
+        let outputs = convert_all_sheets_to_parquet(options, None).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        for (file, path) in files.iter().zip(&outputs) {
+            assert_eq!(file["path"], path.to_string_lossy().as_ref());
+            assert_eq!(file["bytes"], std::fs::metadata(path).unwrap().len());
+            assert_eq!(file["rows"], read_parquet(path).1.len());
+        }
+        assert_eq!(files[0]["sheet"], "North");
+        let total: u64 = files
+            .iter()
+            .map(|file| file["rows"].as_u64().unwrap())
+            .sum();
+        assert_eq!(total, 3);
+
+        // 按列拆分时清单列出每个部分文件
+        let input = dir.path().join("wide.xlsx");
+        write_workbook(
+            &input,
+            &[(
+                "Wide",
+                vec![
+                    vec!["id", "a", "b"],
+                    vec!["1", "x", "y"],
+                    vec!["2", "z", "w"],
+                ],
+            )],
+        );
+        let options = ConvertExcelToParquetOptions {
+            write_manifest: Some(manifest_path.clone()),
+            column_split: Some(ColumnSplit {
+                max_columns: 2,
+                key_columns: vec!["id".to_string()],
+            }),
+            ..ConvertExcelToParquetOptions::new(&input, &output_dir)
+        };
+        convert_all_sheets_to_parquet(options, None).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        for (k, file) in files.iter().enumerate() {
+            let path = output_dir.join(format!("Wide.part-cols-{}.parquet", k + 1));
+            assert_eq!(file["path"], path.to_string_lossy().as_ref());
+            assert_eq!(file["sheet"], "Wide");
+            assert_eq!(file["bytes"], std::fs::metadata(&path).unwrap().len());
+            assert_eq!(file["rows"], 2);
+        }
+    }
+
+    #[test]
+    fn test_sort_by_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("scores.xlsx");
+        write_workbook(
+            &input,
+            &[(
+                "Scores",
+                vec![
+                    vec!["name", "score"],
+                    vec!["a", "10"],
+                    vec!["b", "9"],
+                    vec!["c", "100"],
+                    vec!["d", ""],
+                ],
+            )],
+        );
+
+        for (order, expected) in [
+            (SortOrder::Ascending, vec!["b", "a", "c", "d"]),
+            (SortOrder::Descending, vec!["c", "a", "b", "d"]),
+        ] {
+            let output = dir.path().join(format!("{:?}.parquet", order));
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(2),
+                sort_by: Some(("score".to_string(), order)),
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+
+            let (_, rows) = read_parquet(&output);
+            let names: Vec<_> = rows.iter().map(|r| r[0].clone().unwrap()).collect();
+            assert_eq!(names, expected);
+        }
+    }
+
+    #[test]
+    fn test_sort_by_sorting_columns_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("people.xlsx");
+        write_workbook(
+            &input,
+            &[(
+                "People",
+                vec![
+                    vec!["name", "age"],
+                    vec!["carol", "41"],
+                    vec!["alice", "9"],
+                    vec!["bob", "30"],
+                ],
+            )],
+        );
+
+        let read_sorting_columns = |column: &str| {
+            let output = dir.path().join(format!("{}.parquet", column));
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(2),
+                sort_by: Some((column.to_string(), SortOrder::Descending)),
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            let file = File::open(&output).unwrap();
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+            builder.metadata().row_group(0).sorting_columns().cloned()
+        };
+
+        assert_eq!(
+            read_sorting_columns("name"),
+            Some(vec![parquet::file::metadata::SortingColumn {
+                column_idx: 0,
+                descending: true,
+                nulls_first: false,
+            }])
+        );
+        // 数值顺序与字符串的字节序不同，不能声明为有序
+        assert_eq!(read_sorting_columns("age"), None);
+    }
+
+    #[test]
+    fn test_created_by() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("book.xlsx");
+        write_workbook(&input, &[("Sheet1", vec![vec!["a"], vec!["1"]])]);
+
+        let read_created_by = |path: &Path| {
+            let file = File::open(path).unwrap();
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+            builder
+                .metadata()
+                .file_metadata()
+                .created_by()
+                .map(String::from)
+        };
+
+        let default_output = dir.path().join("default.parquet");
+        convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &default_output))
+            .unwrap();
+        assert_eq!(
+            read_created_by(&default_output).as_deref(),
+            Some(DEFAULT_CREATED_BY)
+        );
+
+        let custom_output = dir.path().join("custom.parquet");
+        let options = ConvertExcelToParquetOptions {
+            created_by: Some("etl-pipeline 2.3".to_string()),
+            ..ConvertExcelToParquetOptions::new(&input, &custom_output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(
+            read_created_by(&custom_output).as_deref(),
+            Some("etl-pipeline 2.3")
+        );
+    }
+
+    #[test]
+    fn test_statistics_truncate_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("notes.xlsx");
+        let output = dir.path().join("notes.parquet");
+        let (low, high) = ("a".repeat(200), "m".repeat(200));
+        write_workbook(
+            &input,
+            &[("Sheet1", vec![vec!["note"], vec![&high], vec![&low]])],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            statistics_truncate_length: Some(16),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+
+        let file = File::open(&output).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let statistics = builder
+            .metadata()
+            .row_group(0)
+            .column(0)
+            .statistics()
+            .unwrap();
+        assert_eq!(statistics.min_bytes_opt().unwrap(), &low.as_bytes()[..16]);
+        assert_eq!(statistics.max_bytes_opt().unwrap().len(), 16);
+        assert!(!statistics.max_is_exact());
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows[0][0].as_deref(), Some(high.as_str()));
+    }
+
+    #[test]
+    fn test_respect_print_area() {
+        use rust_xlsxwriter::Format;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("report.xlsx");
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (r, row) in [["id", "name"], ["1", "a"], ["2", "b"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                worksheet.write_string(r as u32, c as u16, *value).unwrap();
+            }
+        }
+        // 远离数据的格式单元格会撑大 dimensions
+        worksheet
+            .write_blank(200, 20, &Format::new().set_bold())
+            .unwrap();
+        worksheet.set_print_area(0, 0, 2, 1).unwrap();
+        workbook.save(&input).unwrap();
+
+        let output = dir.path().join("report.parquet");
+        let options = ConvertExcelToParquetOptions {
+            respect_print_area: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn test_convert_named_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("template.xlsx");
+        let mut workbook = Workbook::new();
+        // 第二个 Sheet 才有数据，命名区域决定读取哪个 Sheet
+        workbook.add_worksheet().set_name("Cover").unwrap();
+        let worksheet = workbook.add_worksheet().set_name("Data").unwrap();
+        for (r, row) in [
+            ["id", "name", "qty", ""],
+            ["1", "a", "5", ""],
+            ["2", "b", "7", ""],
+            ["", "", "", ""],
+            ["code", "", "", ""],
+            ["x1", "", "", ""],
+        ]
+        .iter()
+        .enumerate()
+        {
+            for (c, value) in row.iter().enumerate() {
+                if !value.is_empty() {
+                    worksheet.write_string(r as u32, c as u16, *value).unwrap();
+                }
+            }
+        }
+        workbook.define_name("Items", "=Data!$A$1:$B$3").unwrap();
+        workbook
+            .define_name("Quantities", "=Data!$B$1:$C$3")
+            .unwrap();
+        workbook.define_name("Codes", "=Data!$A$5:$A$6").unwrap();
+        workbook.define_name("Header", "=Data!$1:$3").unwrap();
+        workbook.define_name("Keys", "=Data!$A:$B").unwrap();
+        workbook.save(&input).unwrap();
+
+        let outputs: Vec<(String, PathBuf)> = ["Items", "Quantities", "Codes", "Header", "Keys"]
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    dir.path().join(format!("{}.parquet", name)),
+                )
+            })
+            .collect();
+        let options = ConvertExcelToParquetOptions::new(&input, dir.path());
+        let reports = convert_named_ranges_to_parquet(options, &outputs).unwrap();
+        let rows: Vec<_> = reports.iter().map(|report| report.total_rows).collect();
+        assert_eq!(rows, vec![2, 2, 1, 2, 4]);
+
+        // 重叠的区域各自独立转换
+        let (columns, _) = read_parquet(&outputs[0].1);
+        assert_eq!(columns, vec!["id", "name"]);
+        let (columns, rows) = read_parquet(&outputs[1].1);
+        assert_eq!(columns, vec!["name", "qty"]);
+        assert_eq!(rows[1], vec![Some("b".into()), Some("7".into())]);
+        let (columns, rows) = read_parquet(&outputs[2].1);
+        assert_eq!(columns, vec!["code"]);
+        assert_eq!(rows, vec![vec![Some("x1".into())]]);
+
+        // 整行/整列引用限制在 Sheet 的范围内，而不是展开到 XFD 列或第 1048576 行
+        let (columns, _) = read_parquet(&outputs[3].1);
+        assert_eq!(columns, vec!["id", "name", "qty"]);
+        let (columns, rows) = read_parquet(&outputs[4].1);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows.len(), 4);
+
+        let missing = vec![("Nope".to_string(), dir.path().join("nope.parquet"))];
+        let options = ConvertExcelToParquetOptions::new(&input, dir.path());
+        let err = convert_named_ranges_to_parquet(options, &missing).unwrap_err();
+        assert!(format!("{:#}", err).contains("Named range 'Nope' not found"));
+    }
+
+    #[test]
+    fn test_auto_trim_region() {
+        use rust_xlsxwriter::Format;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("stray.xlsx");
+
+        // 数据位于 C3:D5，远处的格式单元格使 dimensions 延伸到 AE5001
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (r, row) in [["id", "name"], ["1", "a"], ["2", "b"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                worksheet
+                    .write_string(r as u32 + 2, c as u16 + 2, *value)
+                    .unwrap();
+            }
+        }
+        worksheet
+            .write_blank(5000, 30, &Format::new().set_bold())
+            .unwrap();
+        workbook.save(&input).unwrap();
+
+        let output = dir.path().join("stray.parquet");
+        let convert = |auto_trim_region: bool| {
+            let options = ConvertExcelToParquetOptions {
+                auto_trim_region,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            let (columns, rows) = read_parquet(&output);
+            (report.total_rows, columns, rows)
+        };
+        let (_, columns, _) = convert(false);
+        assert_eq!(columns.len(), 29);
+
+        let (total_rows, columns, rows) = convert(true);
+        assert_eq!(total_rows, 2);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("1".to_string()), Some("a".to_string())],
+                vec![Some("2".to_string()), Some("b".to_string())]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_mode_records_error_cells() {
+        use calamine::{CellErrorType, DataRef, Dimensions};
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("collected.parquet");
+        let options = ConvertExcelToParquetOptions {
+            error_mode: ErrorMode::Collect { max_errors: 2 },
+            ..ConvertExcelToParquetOptions::new(Path::new("input.xlsx"), &output)
+        };
+
+        let cells = [
+            (0, 0, DataRef::String("id".into())),
+            (0, 1, DataRef::String("value".into())),
+            (1, 0, DataRef::Int(1)),
+            (1, 1, DataRef::Error(CellErrorType::Div0)),
+            (2, 0, DataRef::Int(2)),
+            (2, 1, DataRef::Error(CellErrorType::NA)),
+            (3, 0, DataRef::Int(3)),
+            (3, 1, DataRef::Error(CellErrorType::Ref)),
+        ];
+        let mut context =
+            ConversionContext::new(&options, Dimensions::new((0, 0), (3, 1)), None).unwrap();
+        for (row, col, value) in &cells {
+            context.read_cell(*row, *col, value).unwrap();
+        }
+        let report = context.finish("Sheet1".to_string()).unwrap();
+
+        assert_eq!(report.total_rows, 3);
+        assert_eq!(report.error_count, 3);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(
+            (report.errors[0].row, report.errors[0].col),
+            (Some(1), Some(1))
+        );
+
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows.len(), 3);
+
+        // 构建失败的批次被跳过，其中的行不计入 total_rows
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(2),
+            single_threaded: true,
+            error_mode: ErrorMode::Collect { max_errors: 10 },
+            ..options
+        };
+        let mut context =
+            ConversionContext::new(&options, Dimensions::new((0, 0), (3, 1)), None).unwrap();
+        for (row, col, value) in &cells[..3] {
+            context.read_cell(*row, *col, value).unwrap();
+        }
+        // 表头已处理：让 schema 多出一列，之后的每个批次都无法通过列数校验
+        let mut fields = context.schema.as_ref().unwrap().fields().to_vec();
+        fields.push(Arc::new(Field::new("extra", DataType::Utf8, true)));
+        context.schema = Some(Arc::new(Schema::new(fields)));
+        for (row, col, value) in &cells[3..] {
+            context.read_cell(*row, *col, value).unwrap();
+        }
+        let report = context.finish("Sheet1".to_string()).unwrap();
+        assert!(
+            report
+                .errors
+                .iter()
+                .any(|e| e.message.starts_with("Skipped batch"))
+        );
+        assert_eq!(report.total_rows, 0);
+        assert_eq!(report.rows_written, 0);
+        assert!(read_parquet(&output).1.is_empty());
+    }
+
+    #[test]
+    fn test_column_error_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("errors.parquet");
+        let mut range = Range::new((0, 0), (2, 2));
+        for (col, name) in ["price", "name", "note"].into_iter().enumerate() {
+            range.set_value((0, col as u32), Data::String(name.to_string()));
+        }
+        range.set_value((1, 0), Data::Error(calamine::CellErrorType::NA));
+        range.set_value((1, 1), Data::Error(calamine::CellErrorType::NA));
+        range.set_value((1, 2), Data::Error(calamine::CellErrorType::Div0));
+        range.set_value((2, 0), Data::Float(9.5));
+        range.set_value((2, 1), Data::String("widget".to_string()));
+
+        let options = ConvertExcelToParquetOptions {
+            column_error_overrides: HashMap::from([
+                (
+                    "price".to_string(),
+                    ErrorCellPolicy::Replace("0".to_string()),
+                ),
+                ("name".to_string(), ErrorCellPolicy::Null),
+            ]),
+            ..ConvertExcelToParquetOptions::new(&output, &output)
+        };
+        convert_range_to_parquet(&range, options.clone()).unwrap();
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("0".to_string()), None, Some("Div0".to_string())],
+                vec![Some("9.5".to_string()), Some("widget".to_string()), None],
+            ]
+        );
+
+        // 覆盖优先于全局策略
+        let options = ConvertExcelToParquetOptions {
+            error_cell_policy: ErrorCellPolicy::Null,
+            ..options
+        };
+        convert_range_to_parquet(&range, options.clone()).unwrap();
+        assert_eq!(read_parquet(&output).1[0][0].as_deref(), Some("0"));
+        assert_eq!(read_parquet(&output).1[0][2], None);
+
+        let options = ConvertExcelToParquetOptions {
+            column_error_overrides: HashMap::from([("missing".to_string(), ErrorCellPolicy::Null)]),
+            ..options
+        };
+        assert!(convert_range_to_parquet(&range, options).is_err());
+    }
+
+    #[test]
+    fn test_rich_text_cells() {
+        use rust_xlsxwriter::Format;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("rich.xlsx");
+        let output = dir.path().join("rich.parquet");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "note").unwrap();
+        let (bold, italic) = (Format::new().set_bold(), Format::new().set_italic());
+        worksheet
+            .write_rich_string(
+                1,
+                0,
+                &[
+                    (&bold, "Total "),
+                    (&italic, "due"),
+                    (&Format::default(), ": 5"),
+                ],
+            )
+            .unwrap();
+        workbook.save(&input).unwrap();
+
+        convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows, vec![vec![Some("Total due: 5".into())]]);
+    }
+
+    #[test]
+    fn test_formula_modes() {
+        use rust_xlsxwriter::Formula;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("formulas.xlsx");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "amount").unwrap();
+        worksheet.write_string(1, 0, "1").unwrap();
+        worksheet.write_string(2, 0, "2").unwrap();
+        worksheet
+            .write_formula(3, 0, Formula::new("=SUM(A2:A3)").set_result("3"))
+            .unwrap();
+        workbook.save(&input).unwrap();
+
+        for (mode, expected) in [
+            (FormulaMode::CachedValue, "3"),
+            (FormulaMode::AsFormula, "=SUM(A2:A3)"),
+        ] {
+            let output = dir.path().join(format!("{:?}.parquet", mode));
+            let options = ConvertExcelToParquetOptions {
+                formula_results: mode,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+
+            let (_, rows) = read_parquet(&output);
+            assert_eq!(rows[0][0].as_deref(), Some("1"));
+            assert_eq!(rows[2][0].as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_auto_batch_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let cell = "x".repeat(100);
+        let headers: Vec<String> = (0..100).map(|i| format!("c{}", i)).collect();
+
+        let narrow = dir.path().join("narrow.xlsx");
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..20).map(|_| vec!["1"]));
+        write_workbook(&narrow, &[("Data", rows)]);
+
+        let wide = dir.path().join("wide.xlsx");
+        let mut rows = vec![headers.iter().map(String::as_str).collect::<Vec<_>>()];
+        rows.extend((0..20).map(|_| vec![cell.as_str(); 100]));
+        write_workbook(&wide, &[("Data", rows)]);
+
+        let output = dir.path().join("out.parquet");
+        let narrow_report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&narrow, &output)).unwrap();
+        let wide_report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&wide, &output)).unwrap();
+        assert!(wide_report.batch_size < narrow_report.batch_size);
+        assert_eq!(wide_report.total_rows, 20);
+        assert_eq!(narrow_report.batch_size, AUTO_BATCH_MAX_ROWS);
+
+        // 值很短的宽表同样计入每个单元格的开销，不会得到上限大小的批次
+        let short = dir.path().join("short.xlsx");
+        let short_headers: Vec<String> = (0..200).map(|i| format!("c{}", i)).collect();
+        let mut rows = vec![short_headers.iter().map(String::as_str).collect::<Vec<_>>()];
+        rows.extend((0..20).map(|_| vec!["1"; 200]));
+        write_workbook(&short, &[("Data", rows)]);
+        let short_report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&short, &output)).unwrap();
+        assert!(short_report.batch_size < AUTO_BATCH_MAX_ROWS / 2);
+
+        // 显式指定时原样使用
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(7),
+            ..ConvertExcelToParquetOptions::new(&wide, &output)
+        };
+        assert_eq!(convert_xlsx_to_parquet(options).unwrap().batch_size, 7);
+    }
+
+    #[test]
+    fn test_row_group_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("groups.xlsx");
+        let output = dir.path().join("groups.parquet");
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..10).map(|_| vec!["1"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(4),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.row_group_rows, vec![4, 4, 2]);
+        assert!(report.row_group_stats.is_empty());
+        assert_eq!(
+            report.row_group_rows.iter().sum::<usize>(),
+            report.total_rows
+        );
+    }
+
+    #[test]
+    fn test_one_row_group_per_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("aligned.xlsx");
+        let output = dir.path().join("aligned.parquet");
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..10).map(|_| vec!["1"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        // row group 上限大于批次时，批次会被合并到同一个 row group
+        let convert = |one_row_group_per_batch: bool| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(4),
+                max_row_group_size: Some(100),
+                one_row_group_per_batch,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap().row_group_rows
+        };
+        assert_eq!(convert(false), vec![10]);
+        assert_eq!(convert(true), vec![4, 4, 2]);
+    }
+
+    #[test]
+    fn test_coalesce_small_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("tail.xlsx");
+        let output = dir.path().join("tail.parquet");
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..10_050).map(|_| vec!["1"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |coalesce_small_tail: bool| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(10_000),
+                coalesce_small_tail,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap().row_group_rows
+        };
+        assert_eq!(convert(false), vec![10_000, 50]);
+        assert_eq!(convert(true), vec![10_050]);
+
+        // 掩码使中间的批次只剩 1 行：只有最后一个批次会被合并，中间的批次仍单独成为 row group
+        let mut rows = vec![vec!["id", "keep"]];
+        rows.extend((0..100).map(|_| vec!["1", "1"]));
+        rows.push(vec!["1", "1"]);
+        rows.extend((0..99).map(|_| vec!["1", "0"]));
+        rows.extend((0..105).map(|_| vec!["1", "1"]));
+        write_workbook(&input, &[("Data", rows)]);
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(100),
+            coalesce_small_tail: true,
+            mask_column: Some("keep".to_string()),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.row_group_rows, vec![100, 1, 105]);
+    }
+
+    #[test]
+    fn test_max_output_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("limit.xlsx");
+        let output = dir.path().join("limit.parquet");
+        let values: Vec<String> = (0..200)
+            .map(|i| format!("item-{:05}", i * 7919 % 100_000))
+            .collect();
+        let mut rows = vec![vec!["value"]];
+        rows.extend(values.iter().map(|value| vec![value.as_str()]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |max_output_bytes: Option<usize>| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(20),
+                collect_stats: true,
+                verify_output: true,
+                max_output_bytes,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap()
+        };
+        let full = convert(None);
+        assert!(!full.output_limit_reached);
+        assert_eq!(full.rows_written, 200);
+
+        // 上限容得下前三个 row group（加上文件头的 4 字节），放不下第四个
+        let sizes: Vec<usize> = full
+            .row_group_stats
+            .iter()
+            .map(|stats| stats.compressed_bytes as usize)
+            .collect();
+        let limit = 4 + sizes[..3].iter().sum::<usize>() + sizes[3] / 2;
+        let report = convert(Some(limit));
+        assert!(report.output_limit_reached);
+        assert_eq!(report.rows_written, 60);
+        assert_eq!(report.row_group_rows, vec![20, 20, 20]);
+        assert_eq!(report.verified_rows, Some(60));
+
+        let written: u64 = report
+            .row_group_stats
+            .iter()
+            .map(|stats| stats.compressed_bytes)
+            .sum();
+        assert!(4 + written as usize <= limit);
+
+        let options = ConvertExcelToParquetOptions {
+            max_output_bytes: Some(limit),
+            sort_by: Some(("value".to_string(), SortOrder::Ascending)),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+    }
+
+    #[test]
+    fn test_write_buffer_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("buffered.xlsx");
+        let values: Vec<String> = (0..500).map(|i| format!("row {}", i)).collect();
+        let mut rows = vec![vec!["value"]];
+        rows.extend(values.iter().map(|value| vec![value.as_str()]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |write_buffer_size: Option<usize>| {
+            let output = dir.path().join("buffered.parquet");
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(100),
+                write_buffer_size,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            std::fs::read(&output).unwrap()
+        };
+        let direct = convert(None);
+        assert_eq!(convert(Some(64)), direct);
+        assert_eq!(convert(Some(1 << 20)), direct);
+    }
+
+    #[test]
+    fn test_column_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("wide.xlsx");
+        let output = dir.path().join("wide.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Sheet1",
+                vec![
+                    vec!["id", "q1", "q2", "q3", "q4", "note"],
+                    vec!["1", "10", "20", "30", "40", "x"],
+                ],
+            )],
+        );
+
+        let window = |first: &str, last: &str| ConvertExcelToParquetOptions {
+            column_window: Some((first.to_string(), last.to_string())),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(window("q1", "q4")).unwrap();
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["q1", "q2", "q3", "q4"]);
+        assert_eq!(
+            rows,
+            vec![vec![
+                Some("10".into()),
+                Some("20".into()),
+                Some("30".into()),
+                Some("40".into())
+            ]]
+        );
+
+        let err = convert_xlsx_to_parquet(window("q3", "q1")).unwrap_err();
+        assert!(err.to_string().contains("comes after"));
+        let err = convert_xlsx_to_parquet(window("q1", "q9")).unwrap_err();
+        assert!(err.to_string().contains("'q9' not found"));
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let input = Path::new("missing.xlsx");
+        let output = Path::new("out.parquet");
+        let messages = |options: ConvertExcelToParquetOptions| -> Vec<(Vec<&str>, String)> {
+            validate_options(&options)
+                .unwrap_err()
+                .into_iter()
+                .map(|conflict| (conflict.options, conflict.message))
+                .collect()
+        };
+
+        // 不需要打开文件
+        assert_eq!(
+            validate_options(&ConvertExcelToParquetOptions::new(input, output)),
+            Ok(())
+        );
+
+        let conflicts = messages(ConvertExcelToParquetOptions {
+            sheet_name: Some("Data".to_string()),
+            sheet_index: Some(1),
+            sort_by: Some(("id".to_string(), SortOrder::Ascending)),
+            max_output_bytes: Some(1024),
+            ..ConvertExcelToParquetOptions::new(input, output)
+        });
+        assert_eq!(
+            conflicts,
+            vec![
+                (
+                    vec!["sheet_name", "sheet_index"],
+                    "sheet_name takes precedence over sheet_index, which has no effect".to_string()
+                ),
+                (
+                    vec!["max_output_bytes", "sort_by"],
+                    "max_output_bytes cannot be combined with sort_by".to_string()
+                ),
+            ]
+        );
+
+        let conflicts = messages(ConvertExcelToParquetOptions {
+            named_range: Some("Sales".to_string()),
+            sheet_index: Some(1),
+            units_row: true,
+            header_detection: HeaderDetection::Guess,
+            keep_mask_column: true,
+            sample_seed: Some(7),
+            ..ConvertExcelToParquetOptions::new(input, output)
+        });
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(|(_, m)| m.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "named_range overrides sheet_index, which has no effect",
+                "units_row requires header_detection to be FirstRow",
+                "keep_mask_column has no effect without mask_column",
+                "sample_seed only applies to a RandomFraction sample",
+            ]
+        );
+
+        let conflicts = messages(ConvertExcelToParquetOptions {
+            batch_size: Some(0),
+            sample: Some(SampleSpec::RandomFraction(1.5)),
+            column_split: Some(ColumnSplit {
+                max_columns: 1,
+                key_columns: vec!["id".to_string()],
+            }),
+            rejects_output: Some(output.to_path_buf()),
+            writer_properties: Some(WriterProperties::builder().build()),
+            preset: Some(ConvertPreset::FastWrite),
+            ..ConvertExcelToParquetOptions::new(input, output)
+        });
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(|(_, m)| m.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "batch_size must be at least 1",
+                "Sample fraction 1.5 is not between 0 and 1",
+                "Column split max_columns (1) must be greater than the number of key columns (1)",
+                "rejects_output must differ from output_path",
+                "writer_properties replaces preset, which has no effect",
+            ]
+        );
+
+        let conflicts = messages(ConvertExcelToParquetOptions {
+            column_window: Some(("a".to_string(), "b".to_string())),
+            overflow_column: Some("extra".to_string()),
+            ..ConvertExcelToParquetOptions::new(input, output)
+        });
+        assert_eq!(
+            conflicts,
+            vec![(
+                vec!["column_window", "overflow_column"],
+                "column_window cannot be combined with overflow_column".to_string()
+            )]
+        );
+
+        let conflicts = messages(ConvertExcelToParquetOptions {
+            sort_by: Some(("id".to_string(), SortOrder::Ascending)),
+            one_row_group_per_batch: true,
+            coalesce_small_tail: true,
+            spill_to_disk: true,
+            ..ConvertExcelToParquetOptions::new(input, output)
+        });
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(|(options, _)| options[1])
+                .collect::<Vec<_>>(),
+            vec![
+                "one_row_group_per_batch",
+                "coalesce_small_tail",
+                "spill_to_disk"
+            ]
+        );
+
+        // 转换入口在读取前同样检查，不会在写入时 panic
+        let mut range = Range::new((0, 0), (1, 0));
+        range.set_value((0, 0), Data::String("id".to_string()));
+        range.set_value((1, 0), Data::String("1".to_string()));
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(0),
+            ..ConvertExcelToParquetOptions::new(input, output)
+        };
+        let err = convert_range_to_parquet(&range, options.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid options: batch_size must be at least 1"
+        );
+        assert!(convert_xlsx_to_parquet(options).is_err());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_skip_empty_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("trailing.xlsx");
+        let output = dir.path().join("trailing.parquet");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (row, values) in [["id", "", "name"], ["1", "", "a"], ["2", "", "b"]]
+            .iter()
+            .enumerate()
+        {
+            for (col, value) in values.iter().enumerate() {
+                worksheet
+                    .write_string(row as u32, col as u16, *value)
+                    .unwrap();
+            }
+        }
+        // 两列尾部空列：仅有格式，没有值
+        let format = rust_xlsxwriter::Format::new().set_bold();
+        worksheet.write_blank(2, 3, &format).unwrap();
+        worksheet.write_blank(2, 4, &format).unwrap();
+        workbook.save(&input).unwrap();
+
+        let options = ConvertExcelToParquetOptions::new(&input, &output);
+        convert_xlsx_to_parquet(options).unwrap();
+        let (columns, _) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "Field_1", "name", "Field_3", "Field_4"]);
+
+        let options = ConvertExcelToParquetOptions {
+            skip_empty_columns: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("1".into()), Some("a".into())],
+                vec![Some("2".into()), Some("b".into())]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_range_matches_streaming() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("range.xlsx");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["title", ""],
+                    vec!["id", "name"],
+                    vec!["1", "alice"],
+                    vec!["2", ""],
+                    vec!["3", "carol"],
+                ],
+            )],
+        );
+
+        let streamed = dir.path().join("streamed.parquet");
+        convert_xlsx_to_parquet(ConvertExcelToParquetOptions {
+            skip_rows: 1,
+            batch_size: Some(2),
+            ..ConvertExcelToParquetOptions::new(&input, &streamed)
+        })
+        .unwrap();
+
+        let mut workbook: Xlsx<_> = open_workbook(&input).unwrap();
+        let range = workbook.worksheet_range("Data").unwrap();
+        let from_range = dir.path().join("range.parquet");
+        let report = convert_range_to_parquet(
+            &range,
+            ConvertExcelToParquetOptions {
+                skip_rows: 1,
+                batch_size: Some(2),
+                ..ConvertExcelToParquetOptions::new(&input, &from_range)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.total_rows, 3);
+        assert_eq!(read_parquet(&from_range), read_parquet(&streamed));
+    }
+
+    #[test]
+    fn test_thread_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..200).map(|_| vec!["1"]));
+        let inputs: Vec<PathBuf> = (0..2)
+            .map(|i| {
+                let input = dir.path().join(format!("budget{}.xlsx", i));
+                write_workbook(&input, &[("Data", rows.clone())]);
+                input
+            })
+            .collect();
+
+        // 预算随选项传入，不影响同时运行的其他测试
+        let budget = ThreadBudget::new(4);
+        let handles: Vec<_> = inputs
+            .into_iter()
+            .map(|input| {
+                let budget = budget.clone();
+                thread::spawn(move || {
+                    let output = input.with_extension("parquet");
+                    let options = ConvertExcelToParquetOptions {
+                        batch_size: Some(5),
+                        thread_budget: Some(budget),
+                        ..ConvertExcelToParquetOptions::new(&input, &output)
+                    };
+                    convert_xlsx_to_parquet(options).unwrap().total_rows
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 200);
+        }
+        let peak = budget.peak();
+        assert!((1..=4).contains(&peak), "peak workers {}", peak);
+    }
+
+    #[test]
+    fn test_verify_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("verify.xlsx");
+        let output = dir.path().join("verify.parquet");
+        write_workbook(
+            &input,
+            &[("Data", vec![vec!["id"], vec!["1"], vec!["2"], vec!["3"]])],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(2),
+            verify_output: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.verified_rows, Some(3));
+
+        // 行数不符或文件被截断都应报错
+        assert!(verify_parquet_rows(&output, 4, ArrowReaderOptions::new()).is_err());
+        let bytes = std::fs::read(&output).unwrap();
+        let truncated = dir.path().join("truncated.parquet");
+        std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        let err = verify_parquet_rows(&truncated, 3, ArrowReaderOptions::new()).unwrap_err();
+        assert!(err.to_string().contains("verification failed"), "{}", err);
+    }
+
+    #[test]
+    fn test_strict_batch_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("strict.xlsx");
+        let output = dir.path().join("strict.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![vec!["id", "name"], vec!["1", "a"], vec!["2", ""]],
+            )],
+        );
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(1),
+            strict_batch_validation: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert_eq!(convert_xlsx_to_parquet(options).unwrap().total_rows, 2);
+
+        // 人为构造长度不一致的列
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Utf8, true),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec!["1", "2"])),
+            Arc::new(StringArray::from(vec!["a"])),
+        ];
+        let err = validate_batch_arrays(3, &schema, &arrays, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Batch 3: column 'name' has 1 values, expected 2 rows"
+        );
+        assert!(validate_batch_arrays(3, &schema, &arrays[..1], 2).is_err());
+    }
+
+    #[test]
+    fn test_derived_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("keys.xlsx");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["region", "id"],
+                    vec!["eu", "1"],
+                    vec!["", "2"],
+                    vec!["us", "3"],
+                ],
+            )],
+        );
+
+        for (null_handling, expected) in [
+            (NullHandling::Skip, ["eu-1", "2", "us-3"]),
+            (NullHandling::AsEmpty, ["eu-1", "-2", "us-3"]),
+        ] {
+            let output = dir.path().join(format!("{:?}.parquet", null_handling));
+            let options = ConvertExcelToParquetOptions {
+                derived_columns: vec![DerivedColumn {
+                    name: "full_key".to_string(),
+                    source_columns: vec!["region".to_string(), "id".to_string()],
+                    separator: "-".to_string(),
+                    null_handling,
+                }],
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+
+            let (columns, rows) = read_parquet(&output);
+            assert_eq!(columns, vec!["region", "id", "full_key"]);
+            let keys: Vec<_> = rows.iter().map(|r| r[2].clone().unwrap()).collect();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn test_mask_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("mask.xlsx");
+        let output = dir.path().join("mask.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "include"],
+                    vec!["1", "1"],
+                    vec!["2", "0"],
+                    vec!["3"],
+                    vec!["4", "1"],
+                ],
+            )],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            mask_column: Some("include".to_string()),
+            verify_output: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.total_rows, 4);
+        assert_eq!(report.masked_rows, 2);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id"]);
+        assert_eq!(
+            rows,
+            vec![vec![Some("1".to_string())], vec![Some("4".to_string())]]
+        );
+
+        // 布尔单元格的 TRUE/FALSE，保留掩码列
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "keep").unwrap();
+        worksheet.write_string(0, 1, "id").unwrap();
+        for (row, keep) in [true, false, true].into_iter().enumerate() {
+            worksheet.write_boolean(row as u32 + 1, 0, keep).unwrap();
+            worksheet
+                .write_string(row as u32 + 1, 1, (row + 1).to_string())
+                .unwrap();
+        }
+        workbook.save(&input).unwrap();
+
+        let options = ConvertExcelToParquetOptions {
+            mask_column: Some("keep".to_string()),
+            keep_mask_column: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["keep", "id"]);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("true".to_string()), Some("1".to_string())],
+                vec![Some("true".to_string()), Some("3".to_string())],
+            ]
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            mask_column: Some("missing".to_string()),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+    }
+
+    #[test]
+    fn test_type_sidecar_columns() {
+        use rust_xlsxwriter::Format;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("mixed.xlsx");
+        let output = dir.path().join("mixed.parquet");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "value").unwrap();
+        worksheet.write_string(0, 1, "note").unwrap();
+        worksheet.write_number(1, 0, 1.5).unwrap();
+        worksheet.write_string(2, 0, "text").unwrap();
+        worksheet.write_boolean(3, 0, true).unwrap();
+        let date = Format::new().set_num_format("yyyy-mm-dd");
+        worksheet
+            .write_number_with_format(4, 0, 45000.0, &date)
+            .unwrap();
+        worksheet.write_blank(5, 0, &date).unwrap();
+        worksheet.write_string(6, 1, "only note").unwrap();
+        workbook.save(&input).unwrap();
+
+        let options = ConvertExcelToParquetOptions {
+            type_sidecar_columns: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["value", "value__type", "note", "note__type"]);
+        let kinds: Vec<_> = rows.iter().map(|r| r[1].as_deref()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Some("Float"),
+                Some("String"),
+                Some("Bool"),
+                Some("DateTime"),
+                Some("Empty"),
+                None
+            ]
+        );
+        assert_eq!(rows[5][3].as_deref(), Some("String"));
+    }
+
+    #[test]
+    fn test_row_group_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("repetitive.xlsx");
+        let output = dir.path().join("repetitive.parquet");
+        let value = "the same value over and over ".repeat(4);
+        let mut rows = vec![vec!["text"]];
+        rows.extend((0..500).map(|_| vec![value.as_str()]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(250),
+            collect_stats: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.row_group_stats.len(), 2);
+        for stats in &report.row_group_stats {
+            assert_eq!(stats.num_rows, 250);
+            assert!(stats.compressed_bytes < stats.uncompressed_bytes);
+            assert!(stats.compression_ratio().unwrap() > 1.0);
+        }
+    }
+
+    #[test]
+    fn test_phase_timings() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("timed.xlsx");
+        let output = dir.path().join("timed.parquet");
+        let mut rows = vec![vec!["id", "name", "note"]];
+        rows.extend((0..3000).map(|_| vec!["42", "some name", "a longer note value"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let started = Instant::now();
+        let report = convert_xlsx_to_parquet(ConvertExcelToParquetOptions {
+            batch_size: Some(500),
+            collect_stats: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        })
+        .unwrap();
+        let elapsed = started.elapsed();
+
+        let timings = report.phase_timings.unwrap();
+        assert!(timings.read > Duration::ZERO);
+        assert!(timings.build > Duration::ZERO);
+        assert!(timings.write > Duration::ZERO);
+        // 读取与写入各自在单个线程中进行，不会超过总时长
+        assert!(timings.read <= elapsed);
+        assert!(timings.write <= elapsed);
+
+        let report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert!(report.phase_timings.is_none());
+    }
+
+    #[test]
+    fn test_header_only_sheet() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("header_only.xlsx");
+        let output = dir.path().join("header_only.parquet");
+        write_workbook(&input, &[("Data", vec![vec!["id", "name"]])]);
+
+        let report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert_eq!(report.total_rows, 0);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert!(rows.is_empty());
+
+        // 表头后只有带格式的空白行，同样输出 0 行
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "id").unwrap();
+        worksheet.write_string(0, 1, "name").unwrap();
+        let format = rust_xlsxwriter::Format::new().set_bold();
+        for row in 1..4 {
+            worksheet.write_blank(row, 0, &format).unwrap();
+        }
+        workbook.save(&input).unwrap();
+        let report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert_eq!(report.total_rows, 0);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert!(rows.is_empty());
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (row, values) in [["id", "name"], ["1", "a"], ["", ""], ["3", "c"]]
+            .iter()
+            .enumerate()
+        {
+            for (col, value) in values.iter().enumerate() {
+                if !value.is_empty() {
+                    worksheet
+                        .write_string(row as u32, col as u16, *value)
+                        .unwrap();
+                }
+            }
+        }
+        worksheet.write_blank(2, 0, &format).unwrap();
+        worksheet.write_blank(5, 0, &format).unwrap();
+        workbook.save(&input).unwrap();
+        // 数据之间与之后的空白行照常输出
+        let report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert_eq!(report.total_rows, 4);
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows[1], vec![Some(String::new()), None]);
+        assert_eq!(rows[3], vec![Some(String::new()), None]);
+        // drop_trailing_blank_rows 只丢弃最后一个数据行之后的空白行，中间的空白行保留
+        let report = convert_xlsx_to_parquet(ConvertExcelToParquetOptions {
+            drop_trailing_blank_rows: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        })
+        .unwrap();
+        assert_eq!(report.total_rows, 3);
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows[1], vec![Some(String::new()), None]);
+        assert_eq!(rows[2], vec![Some("3".into()), Some("c".into())]);
+
+        // 暂存的空白行只记录行数：一大片相同格式的空白行合并为一段，后面出现数据时再展开
+        let options = ConvertExcelToParquetOptions::new(&input, &output);
+        let dimensions = calamine::Dimensions::new((0, 0), (10_002, 1));
+        let mut context = ConversionContext::new(&options, dimensions, None).unwrap();
+        context
+            .process_cell(0, 0, "id".into(), CellKind::String)
+            .unwrap();
+        context
+            .process_cell(0, 1, "name".into(), CellKind::String)
+            .unwrap();
+        for row in 1..=10_000 {
+            context
+                .process_cell(row, 0, String::new(), CellKind::Empty)
+                .unwrap();
+        }
+        context
+            .process_cell(10_001, 1, String::new(), CellKind::Empty)
+            .unwrap();
+        context
+            .process_cell(10_002, 0, "9".into(), CellKind::String)
+            .unwrap();
+        assert_eq!(
+            context.pending_blank_rows,
+            vec![
+                BlankRows {
+                    first_row: 1,
+                    count: 10_000,
+                    cells: vec![(0, CellKind::Empty)],
+                },
+                BlankRows {
+                    first_row: 10_001,
+                    count: 1,
+                    cells: vec![(1, CellKind::Empty)],
+                },
+            ]
+        );
+        let report = context.finish("Sheet1".into()).unwrap();
+        assert_eq!(report.total_rows, 10_002);
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows[0], vec![Some(String::new()), None]);
+        assert_eq!(rows[10_000], vec![None, Some(String::new())]);
+        assert_eq!(rows[10_001], vec![Some("9".into()), None]);
+    }
+
+    #[test]
+    fn test_custom_writer_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("props.xlsx");
+        let output = dir.path().join("props.parquet");
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..6).map(|_| vec!["1"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let props = WriterProperties::builder()
+            .set_compression(Compression::UNCOMPRESSED)
+            .set_write_batch_size(2)
+            .set_data_page_row_count_limit(2)
+            .build();
+        let options = ConvertExcelToParquetOptions {
+            writer_properties: Some(props),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.row_group_rows, vec![6]);
+
+        let file = File::open(&output).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new_with_options(
+            file,
+            ArrowReaderOptions::new().with_page_index(true),
+        )
+        .unwrap();
+        let metadata = builder.metadata();
+        let column = metadata.row_group(0).column(0);
+        assert_eq!(column.compression(), Compression::UNCOMPRESSED);
+        let offset_index = metadata.offset_index().unwrap();
+        assert_eq!(offset_index[0][0].page_locations().len(), 3);
+        assert_ne!(
+            metadata.file_metadata().created_by(),
+            Some(DEFAULT_CREATED_BY)
+        );
+
+        // writer_properties 中的 row group 大小不被采用，由 max_row_group_size 决定
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(4),
+            writer_properties: Some(
+                WriterProperties::builder()
+                    .set_max_row_group_size(100)
+                    .build(),
+            ),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert_eq!(
+            convert_xlsx_to_parquet(options).unwrap().row_group_rows,
+            vec![4, 2]
+        );
+        // 明确指定的大小即使与 parquet 的默认值相同也会被采用
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(4),
+            max_row_group_size: Some(parquet::file::properties::DEFAULT_MAX_ROW_GROUP_SIZE),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert_eq!(
+            convert_xlsx_to_parquet(options).unwrap().row_group_rows,
+            vec![6]
+        );
+    }
+
+    #[test]
+    fn test_convert_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("preset.xlsx");
+        let output = dir.path().join("preset.parquet");
+        let mut rows = vec![vec!["id"]];
+        rows.extend((0..6).map(|_| vec!["1"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |preset: ConvertPreset| {
+            let options = ConvertExcelToParquetOptions {
+                preset: Some(preset),
+                batch_size: Some(2),
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            let file = File::open(&output).unwrap();
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+            (report.row_group_rows, builder.metadata().clone())
+        };
+
+        let (row_group_rows, metadata) = convert(ConvertPreset::FastWrite);
+        assert_eq!(row_group_rows, vec![6]);
+        let column = metadata.row_group(0).column(0);
+        assert_eq!(column.compression(), Compression::SNAPPY);
+        assert_eq!(column.dictionary_page_offset(), None);
+
+        let (row_group_rows, metadata) = convert(ConvertPreset::SmallFile);
+        assert_eq!(row_group_rows, vec![2, 2, 2]);
+        let column = metadata.row_group(0).column(0);
+        // footer 中只记录编码方式，不记录压缩级别
+        assert!(matches!(column.compression(), Compression::ZSTD(_)));
+        assert!(column.dictionary_page_offset().is_some());
+
+        let (_, metadata) = convert(ConvertPreset::Compatibility);
+        assert_eq!(
+            metadata.row_group(0).column(0).compression(),
+            Compression::SNAPPY
+        );
+        assert_eq!(metadata.file_metadata().version(), 1);
+    }
+
+    #[test]
+    fn test_profile_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("profile.xlsx");
+        let output = dir.path().join("profile.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["amount", "name"],
+                    vec!["9", "b"],
+                    vec!["10", "a"],
+                    vec!["-2.5", ""],
+                    vec!["10"],
+                ],
+            )],
+        );
+
+        let profiles = profile_only(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert!(!output.exists());
+        assert_eq!(
+            profiles[0],
+            ColumnProfile {
+                name: "amount".to_string(),
+                non_null_count: 4,
+                null_count: 0,
+                min: Some("-2.5".to_string()),
+                max: Some("10".to_string()),
+                distinct_count: 3,
+            }
+        );
+        assert_eq!(profiles[1].name, "name");
+        assert_eq!(profiles[1].null_count, 2);
+        assert_eq!(profiles[1].min.as_deref(), Some("a"));
+        assert_eq!(profiles[1].max.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_date_system() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("dates.xlsx");
+        let output = dir.path().join("dates.parquet");
+        // 2023-03-15 在 1900 纪元为 45000，在 1904 纪元为 43538
+        let sheet = |is_1904: bool, serial: f64| {
+            let mut range = Range::new((0, 0), (1, 1));
+            range.set_value((0, 0), Data::String("date".to_string()));
+            range.set_value((0, 1), Data::String("elapsed".to_string()));
+            range.set_value(
+                (1, 0),
+                Data::DateTime(ExcelDateTime::new(
+                    serial,
+                    ExcelDateTimeType::DateTime,
+                    is_1904,
+                )),
+            );
+            range.set_value(
+                (1, 1),
+                Data::DateTime(ExcelDateTime::new(
+                    1.5,
+                    ExcelDateTimeType::TimeDelta,
+                    is_1904,
+                )),
+            );
+            range
+        };
+        let convert = |range: &Range<Data>, date_system: Option<DateSystem>| {
+            let options = ConvertExcelToParquetOptions {
+                date_system,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_range_to_parquet(range, options).unwrap();
+            read_parquet(&output).1
+        };
+        let expected = vec![vec![Some("45000".to_string()), Some("1.5".to_string())]];
+
+        assert_eq!(convert(&sheet(false, 45000.0), None), expected);
+        assert_eq!(convert(&sheet(true, 43538.0), None), expected);
+        assert_eq!(
+            convert(&sheet(false, 43538.0), Some(DateSystem::Excel1904)),
+            expected
+        );
+        assert_eq!(
+            convert(&sheet(true, 43538.0), Some(DateSystem::Excel1900))[0][0].as_deref(),
+            Some("43538")
+        );
+
+        // 用 Mac 版 Excel 保存的 1904 纪元工作簿：与写入相同日期的 1900 纪元工作簿输出一致
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/date1904.xlsx");
+        let convert_file = |input: &Path| {
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(input, &output)).unwrap();
+            read_parquet(&output)
+        };
+        let (columns, rows_1904) = convert_file(&fixture);
+        assert_eq!(columns, vec!["date", "updated_at"]);
+        // 2023-03-15 与 2023-03-15 12:00；1904-01-01 与 1904-01-02 06:00
+        assert_eq!(
+            rows_1904,
+            vec![
+                vec![Some("45000".to_string()), Some("45000.5".to_string())],
+                vec![Some("1462".to_string()), Some("1463.25".to_string())],
+            ]
+        );
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet().set_name("Dates").unwrap();
+        let date = rust_xlsxwriter::Format::new().set_num_format("yyyy-mm-dd");
+        let datetime = rust_xlsxwriter::Format::new().set_num_format("yyyy-mm-dd hh:mm");
+        worksheet.write_string(0, 0, "date").unwrap();
+        worksheet.write_string(0, 1, "updated_at").unwrap();
+        for (row, (day, moment)) in [(45000.0, 45000.5), (1462.0, 1463.25)]
+            .into_iter()
+            .enumerate()
+        {
+            let row = row as u32 + 1;
+            worksheet
+                .write_number_with_format(row, 0, day, &date)
+                .unwrap();
+            worksheet
+                .write_number_with_format(row, 1, moment, &datetime)
+                .unwrap();
+        }
+        workbook.save(&input).unwrap();
+        assert_eq!(convert_file(&input).1, rows_1904);
+    }
+
+    #[test]
+    fn test_parquet_batch_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("pushed.parquet");
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Utf8, true)]));
+        let batch = |values: &[&str]| {
+            let column: ArrayRef = Arc::new(StringArray::from(values.to_vec()));
+            RecordBatch::try_new(schema.clone(), vec![column]).unwrap()
+        };
+
+        let mut writer = ParquetBatchWriter::try_new(&output, schema.clone(), None).unwrap();
+        writer.push(2, batch(&["5"])).unwrap();
+        writer.push(0, batch(&["1", "2"])).unwrap();
+        // 重复推送尚在等待的批次与已写出的批次都立即报错，不影响已推送的数据
+        let err = writer.push(2, batch(&["x"])).unwrap_err();
+        assert!(err.to_string().contains("Batch 2 was already pushed"));
+        let err = writer.push(0, batch(&["x"])).unwrap_err();
+        assert!(err.to_string().contains("Batch 0 was already pushed"));
+        writer.push(1, batch(&["3", "4"])).unwrap();
+        let stats = writer.finish().unwrap();
+        assert_eq!(stats.iter().map(|s| s.num_rows).sum::<usize>(), 5);
+
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id"]);
+        let ids: Vec<_> = rows
+            .into_iter()
+            .map(|row| row[0].clone().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["1", "2", "3", "4", "5"]);
+
+        // 缺少批次 0 时报错且不生成文件
+        let missing = dir.path().join("missing.parquet");
+        let mut writer = ParquetBatchWriter::try_new(&missing, schema.clone(), None).unwrap();
+        writer.push(1, batch(&["3"])).unwrap();
+        let err = writer.finish().unwrap_err();
+        assert!(err.to_string().contains("Batch 0 was never pushed"));
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    fn test_single_threaded_matches_threaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("single.xlsx");
+        let mut rows = vec![vec!["id", "name"]];
+        rows.extend((0..25).map(|i| vec![if i % 2 == 0 { "even" } else { "odd" }, "x"]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |output: &Path, single_threaded: bool| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(4),
+                single_threaded,
+                ..ConvertExcelToParquetOptions::new(&input, output)
+            };
+            convert_xlsx_to_parquet(options).unwrap()
+        };
+        let threaded = dir.path().join("threaded.parquet");
+        let single = dir.path().join("single.parquet");
+        let threaded_report = convert(&threaded, false);
+        let single_report = convert(&single, true);
+
+        assert_eq!(single_report.total_rows, 25);
+        assert_eq!(single_report.row_group_rows, threaded_report.row_group_rows);
+        assert_eq!(
+            std::fs::read(&single).unwrap(),
+            std::fs::read(&threaded).unwrap()
+        );
+
+        let custom_stack = dir.path().join("stack.parquet");
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(4),
+            worker_stack_size: Some(256 * 1024),
+            ..ConvertExcelToParquetOptions::new(&input, &custom_stack)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(
+            std::fs::read(&custom_stack).unwrap(),
+            std::fs::read(&threaded).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_convert_reader_to_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (row, values) in [["id", "name"], ["1", "a"], ["2", "b"]].iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                worksheet
+                    .write_string(row as u32, col as u16, *value)
+                    .unwrap();
+            }
+        }
+        let input = workbook.save_to_buffer().unwrap();
+
+        // output_path 不会被创建
+        let unused = dir.path().join("unused.parquet");
+        let (bytes, report) = convert_reader_to_bytes(
+            std::io::Cursor::new(input),
+            ExcelFormat::Xlsx,
+            ConvertExcelToParquetOptions {
+                single_threaded: true,
+                ..ConvertExcelToParquetOptions::new(Path::new("memory.xlsx"), &unused)
+            },
+        )
+        .unwrap();
+        assert_eq!(report.total_rows, 2);
+        assert!(!unused.exists());
+
+        let output = dir.path().join("bytes.parquet");
+        std::fs::write(&output, bytes).unwrap();
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows[1], vec![Some("2".to_string()), Some("b".to_string())]);
+    }
+
+    #[test]
+    fn test_float_precision() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("floats.parquet");
+        let mut range = Range::new((0, 0), (2, 0));
+        range.set_value((0, 0), Data::String("value".to_string()));
+        range.set_value((1, 0), Data::Float(0.1 + 0.2));
+        range.set_value((2, 0), Data::Int(7));
+
+        let convert = |float_precision: Option<usize>| {
+            let options = ConvertExcelToParquetOptions {
+                float_precision,
+                ..ConvertExcelToParquetOptions::new(&output, &output)
+            };
+            convert_range_to_parquet(&range, options).unwrap();
+            read_parquet(&output).1
+        };
+        assert_eq!(convert(None)[0][0].as_deref(), Some("0.30000000000000004"));
+        assert_eq!(
+            convert(Some(2)),
+            vec![vec![Some("0.30".to_string())], vec![Some("7".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_bool_string_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("bools.parquet");
+        let mut range = Range::new((0, 0), (3, 0));
+        range.set_value((0, 0), Data::String("flag".to_string()));
+        range.set_value((1, 0), Data::Bool(true));
+        range.set_value((2, 0), Data::Bool(false));
+        range.set_value((3, 0), Data::String("true".to_string()));
+
+        let convert = |bool_string_format: Option<(String, String)>| {
+            let options = ConvertExcelToParquetOptions {
+                bool_string_format,
+                ..ConvertExcelToParquetOptions::new(&output, &output)
+            };
+            convert_range_to_parquet(&range, options).unwrap();
+            read_parquet(&output)
+                .1
+                .into_iter()
+                .map(|row| row[0].clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(convert(None), vec!["true", "false", "true"]);
+        // 只影响布尔单元格，文本 "true" 原样输出
+        assert_eq!(
+            convert(Some(("1".to_string(), "0".to_string()))),
+            vec!["1", "0", "true"]
+        );
+
+        // 掩码按布尔原值判断，FALSE 输出为 "N" 时同样被过滤
+        let mut range = Range::new((0, 0), (2, 1));
+        range.set_value((0, 0), Data::String("id".to_string()));
+        range.set_value((0, 1), Data::String("keep".to_string()));
+        range.set_value((1, 0), Data::String("a".to_string()));
+        range.set_value((1, 1), Data::Bool(true));
+        range.set_value((2, 0), Data::String("b".to_string()));
+        range.set_value((2, 1), Data::Bool(false));
+        let options = ConvertExcelToParquetOptions {
+            bool_string_format: Some(("Y".to_string(), "N".to_string())),
+            mask_column: Some("keep".to_string()),
+            keep_mask_column: true,
+            ..ConvertExcelToParquetOptions::new(&output, &output)
+        };
+        convert_range_to_parquet(&range, options).unwrap();
+        assert_eq!(
+            read_parquet(&output).1,
+            vec![vec![Some("a".to_string()), Some("Y".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_units_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("units.xlsx");
+        let output = dir.path().join("units.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["Temp", "Site", "Depth"],
+                    vec!["°C", "", "m"],
+                    vec!["21.5", "A", "3"],
+                    vec!["19.0", "B", "4"],
+                ],
+            )],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            units_row: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.total_rows, 2);
+        assert_eq!(
+            report.units,
+            HashMap::from([
+                ("Temp".to_string(), "°C".to_string()),
+                ("Depth".to_string(), "m".to_string())
+            ])
+        );
+
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["Temp", "Site", "Depth"]);
+        assert_eq!(rows[0][0].as_deref(), Some("21.5"));
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap()).unwrap();
+        let schema = builder.schema();
+        assert_eq!(
+            schema.field(0).metadata().get(UNIT_METADATA_KEY),
+            Some(&"°C".to_string())
+        );
+        assert!(schema.field(1).metadata().is_empty());
+
+        // 只有表头和单位行
+        write_workbook(&input, &[("Data", vec![vec!["Temp"], vec!["°C"]])]);
+        let options = ConvertExcelToParquetOptions {
+            units_row: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.total_rows, 0);
+        assert_eq!(report.units["Temp"], "°C");
+    }
+
+    #[test]
+    fn test_column_descriptions() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("described.xlsx");
+        let output = dir.path().join("described.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![vec!["Temp", "Site"], vec!["°C", ""], vec!["21.5", "A"]],
+            )],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            units_row: true,
+            column_descriptions: HashMap::from([
+                ("Temp".to_string(), "Air temperature at 2m".to_string()),
+                ("batch".to_string(), "Load batch".to_string()),
+            ]),
+            constant_columns: HashMap::from([("batch".to_string(), "7".to_string())]),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options.clone()).unwrap();
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap()).unwrap();
+        let schema = builder.schema();
+        assert_eq!(
+            schema.field(0).metadata(),
+            &HashMap::from([
+                (UNIT_METADATA_KEY.to_string(), "°C".to_string()),
+                (
+                    DESCRIPTION_METADATA_KEY.to_string(),
+                    "Air temperature at 2m".to_string()
+                ),
+            ])
+        );
+        assert!(schema.field(1).metadata().is_empty());
+        assert_eq!(
+            schema.field(2).metadata().get(DESCRIPTION_METADATA_KEY),
+            Some(&"Load batch".to_string())
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            column_descriptions: HashMap::from([("Missing".to_string(), "?".to_string())]),
+            ..options
+        };
+        let err = convert_xlsx_to_parquet(options).unwrap_err();
+        assert!(err.to_string().contains("'Missing'"), "{}", err);
+    }
+
+    #[test]
+    fn test_constraints() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("constraints.xlsx");
+        let output = dir.path().join("constraints.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "name", "score"],
+                    vec!["1", "a", "50"],
+                    vec!["x2", "b", "100"],
+                    vec!["3", "", "150"],
+                    vec!["4", "d", "abc"],
+                ],
+            )],
+        );
+        let constraints = vec![
+            ColumnConstraint {
+                column: "name".to_string(),
+                rule: ConstraintRule::NotNull,
+            },
+            ColumnConstraint {
+                column: "id".to_string(),
+                rule: ConstraintRule::Matches(r"^\d+$".to_string()),
+            },
+            ColumnConstraint {
+                column: "score".to_string(),
+                rule: ConstraintRule::Range {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            },
+        ];
+
+        let options = ConvertExcelToParquetOptions {
+            constraints: constraints.clone(),
+            error_mode: ErrorMode::Collect { max_errors: 3 },
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.total_rows, 4);
+        assert_eq!(report.constraint_violations, 4);
+        assert_eq!(report.error_count, 4);
+        assert_eq!(
+            report
+                .errors
+                .iter()
+                .map(|issue| (issue.row, issue.col, issue.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(3), Some(1), "Column 'name' is null"),
+                (
+                    Some(2),
+                    Some(0),
+                    "Column 'id' value 'x2' does not match '^\\d+$'"
+                ),
+                (
+                    Some(3),
+                    Some(2),
+                    "Column 'score' value '150' is outside [0, 100]"
+                ),
+            ]
+        );
+        // 违规的行照常输出
+        assert_eq!(read_parquet(&output).1.len(), 4);
+
+        let options = ConvertExcelToParquetOptions {
+            constraints: constraints[2..].to_vec(),
+            error_mode: ErrorMode::Collect { max_errors: 10 },
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(
+            report.errors.last().unwrap().message,
+            "Column 'score' value 'abc' is not a number"
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            constraints: constraints[..1].to_vec(),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let err = convert_xlsx_to_parquet(options).unwrap_err();
+        assert!(format!("{:#}", err).contains("Row 3: Column 'name' is null"));
+
+        let options = ConvertExcelToParquetOptions {
+            constraints: vec![ColumnConstraint {
+                column: "missing".to_string(),
+                rule: ConstraintRule::NotNull,
+            }],
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+    }
+
+    #[test]
+    fn test_issue_cell_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("scores.xlsx");
+        let output = dir.path().join("scores.parquet");
+        // 表头在第 1 行，数据在第 2-42 行，最后一行的 C 列越界
+        let mut rows = vec![vec![
+            "id".to_string(),
+            "name".to_string(),
+            "score".to_string(),
+        ]];
+        for i in 1..=41 {
+            let score = if i == 41 { "150" } else { "50" };
+            rows.push(vec![i.to_string(), format!("n{}", i), score.to_string()]);
+        }
+        let rows: Vec<Vec<&str>> = rows
+            .iter()
+            .map(|row| row.iter().map(String::as_str).collect())
+            .collect();
+        write_workbook(&input, &[("Data", rows)]);
+
+        let options = ConvertExcelToParquetOptions {
+            constraints: vec![ColumnConstraint {
+                column: "score".to_string(),
+                rule: ConstraintRule::Range {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            }],
+            error_mode: ErrorMode::Collect { max_errors: 10 },
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].cell_address().as_deref(), Some("C42"));
+
+        let issue = ConversionIssue {
+            row: Some(41),
+            col: None,
+            message: String::new(),
+        };
+        assert_eq!(issue.cell_address(), None);
+    }
+
+    #[test]
+    fn test_rejects_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("rejects.xlsx");
+        let output = dir.path().join("rejects.parquet");
+        let rejects = dir.path().join("rejects.rejected.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "score", "active"],
+                    vec!["1", "50", "1"],
+                    vec!["2", "150", "1"],
+                    vec!["3", "60", "0"],
+                    vec!["4", "abc", "1"],
+                    vec!["5", "70", "1"],
+                    vec!["6", "200", "false"],
+                ],
+            )],
+        );
+
+        for single_threaded in [false, true] {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(2),
+                mask_column: Some("active".to_string()),
+                constraints: vec![ColumnConstraint {
+                    column: "score".to_string(),
+                    rule: ConstraintRule::Range {
+                        min: 0.0,
+                        max: 100.0,
+                    },
+                }],
+                error_mode: ErrorMode::Collect { max_errors: 10 },
+                rejects_output: Some(rejects.clone()),
+                verify_output: true,
+                single_threaded,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            assert_eq!(report.masked_rows, 2);
+            // 被掩码过滤的行不做约束校验
+            assert_eq!(report.constraint_violations, 2);
+            assert_eq!(report.rejected_rows, 4);
+            assert_eq!(report.verified_rows, Some(2));
+
+            let (_, rows) = read_parquet(&output);
+            let ids: Vec<_> = rows.iter().map(|row| row[0].as_deref().unwrap()).collect();
+            assert_eq!(ids, vec!["1", "5"]);
+
+            let (columns, rows) = read_parquet(&rejects);
+            assert_eq!(columns, vec!["id", "score", REJECT_REASON_COLUMN]);
+            let rejected: Vec<_> = rows
+                .iter()
+                .map(|row| (row[0].as_deref().unwrap(), row[2].as_deref().unwrap()))
+                .collect();
+            assert_eq!(
+                rejected,
+                vec![
+                    ("2", "Column 'score' value '150' is outside [0, 100]"),
+                    ("3", MASK_REJECT_REASON),
+                    ("4", "Column 'score' value 'abc' is not a number"),
+                    ("6", MASK_REJECT_REASON),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_fan_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("fan_out.xlsx");
+        let output = dir.path().join("fan_out.parquet");
+        let copy = dir.path().join("copy.parquet");
+        let ndjson = dir.path().join("debug.ndjson");
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "id").unwrap();
+        worksheet.write_string(0, 1, "name").unwrap();
+        for (row, id, name) in [
+            (1, "3", Some("carol")),
+            (2, "1", None),
+            (3, "2", Some("bob")),
+        ] {
+            worksheet.write_string(row, 0, id).unwrap();
+            if let Some(name) = name {
+                worksheet.write_string(row, 1, name).unwrap();
+            }
+        }
+        workbook.save(&input).unwrap();
+
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(1),
+            sort_by: Some(("id".to_string(), SortOrder::Ascending)),
+            fan_out: vec![
+                FanOutTarget {
+                    format: FanOutFormat::Parquet,
+                    path: copy.clone(),
+                },
+                FanOutTarget {
+                    format: FanOutFormat::Ndjson,
+                    path: ndjson.clone(),
+                },
+            ],
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options.clone()).unwrap();
+        assert_eq!(report.rows_written, 3);
+
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows[0], vec![Some("1".into()), None]);
+        assert_eq!(read_parquet(&copy), (columns, rows));
+        assert_eq!(
+            std::fs::read_to_string(&ndjson).unwrap(),
+            "{\"id\":\"1\",\"name\":null}\n\
+             {\"id\":\"2\",\"name\":\"bob\"}\n\
+             {\"id\":\"3\",\"name\":\"carol\"}\n"
+        );
+
+        // 任一输出失败时整个转换失败，已有的输出保持不变
+        let stale = std::fs::read(&output).unwrap();
+        let options = ConvertExcelToParquetOptions {
+            fan_out: vec![FanOutTarget {
+                format: FanOutFormat::Ndjson,
+                path: dir.path().join("missing").join("debug.ndjson"),
+            }],
+            ..options
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+        assert_eq!(std::fs::read(&output).unwrap(), stale);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_row_hash_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("hash.xlsx");
+        let swapped = dir.path().join("swapped.xlsx");
+        let output = dir.path().join("hash.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "name"],
+                    vec!["1", "a"],
+                    vec!["1", "a"],
+                    vec!["1", "b"],
+                    vec!["1a"],
+                ],
+            )],
+        );
+        // 列顺序不同、内容相同的 Sheet
+        write_workbook(
+            &swapped,
+            &[("Data", vec![vec!["name", "id"], vec!["a", "1"]])],
+        );
+
+        let hashes = |input: &Path, algo: ChecksumAlgo| {
+            let options = ConvertExcelToParquetOptions {
+                row_hash_column: Some(("row_hash".to_string(), algo)),
+                ..ConvertExcelToParquetOptions::new(input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            let (columns, rows) = read_parquet(&output);
+            assert_eq!(columns.last().unwrap(), "row_hash");
+            rows.into_iter()
+                .map(|row| row.last().unwrap().clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        for algo in [ChecksumAlgo::XxHash64, ChecksumAlgo::Sha256] {
+            let rows = hashes(&input, algo);
+            assert_eq!(rows[0], rows[1]);
+            assert_ne!(rows[0], rows[2]);
+            // 长度前缀使 ("1", "a") 与 ("1a", null) 不会相同
+            assert_ne!(rows[0], rows[3]);
+            assert_eq!(hashes(&swapped, algo)[0], rows[0]);
+        }
+        assert_eq!(hashes(&input, ChecksumAlgo::XxHash64)[0].len(), 16);
+        // 固定的编码保证跨版本稳定
+        assert_eq!(
+            hashes(&input, ChecksumAlgo::Sha256)[0],
+            "8323f33a2a175ec425008ae254a9c58d9d55a75b12c7eec6b903b33d72923c8f"
+        );
+    }
+
+    #[test]
+    fn test_skip_trailing_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("footer.xlsx");
+        let output = dir.path().join("footer.parquet");
+        let mut rows = vec![vec!["region", "amount"]];
+        rows.extend((1..=5).map(|_| vec!["north", "10"]));
+        rows.push(vec![]);
+        rows.push(vec!["Total", "50"]);
+        rows.push(vec!["Average", "10"]);
+        write_workbook(&input, &[("Data", rows)]);
+
+        let options = ConvertExcelToParquetOptions {
+            skip_trailing_rows: 2,
+            // 批次小于暂存的行数时，批次边界处的行也要正确释放
+            batch_size: Some(2),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.total_rows, 5);
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|row| row[0].as_deref() == Some("north")));
+
+        let options = ConvertExcelToParquetOptions {
+            skip_trailing_rows: 10,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.total_rows, 0);
+    }
+
+    #[test]
+    fn test_overflow_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("overflow.xlsx");
+        let output = dir.path().join("overflow.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "name"],
+                    vec!["1", "a"],
+                    vec!["2", "b", "x", "say \"hi\""],
+                    vec!["3", "c", "", "z"],
+                ],
+            )],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            overflow_column: Some("extra".to_string()),
+            error_mode: ErrorMode::Collect { max_errors: 10 },
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert_eq!(report.error_count, 0);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name", "extra"]);
+        assert_eq!(
+            rows.into_iter()
+                .map(|row| row[2].clone())
+                .collect::<Vec<_>>(),
+            vec![
+                None,
+                Some(r#"["x","say \"hi\""]"#.to_string()),
+                Some(r#"["z"]"#.to_string())
+            ]
+        );
+
+        // 不启用时多出的单元格各自成列
+        convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert_eq!(
+            read_parquet(&output).0,
+            vec!["id", "name", "Field_2", "Field_3"]
+        );
+    }
+
+    #[test]
+    fn test_column_split() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("wide.xlsx");
+        let output = dir.path().join("wide.parquet");
+        let header = ["id", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "c8", "c9"];
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    header.to_vec(),
+                    vec!["1", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "a8", "a9"],
+                    vec!["2", "b1", "b2", "b3", "b4", "b5", "b6", "b7", "b8", "b9"],
+                ],
+            )],
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            column_split: Some(ColumnSplit {
+                max_columns: 4,
+                key_columns: vec!["id".to_string()],
+            }),
+            verify_output: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options).unwrap();
+        assert!(!output.exists());
+        assert_eq!(
+            report.output_files,
+            (1..=3)
+                .map(|k| dir.path().join(format!("wide.part-cols-{}.parquet", k)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(report.verified_rows, Some(2));
+
+        for (k, path) in report.output_files.iter().enumerate() {
+            let (columns, rows) = read_parquet(path);
+            let expected: Vec<&str> = std::iter::once("id")
+                .chain(header[1 + 3 * k..4 + 3 * k].iter().copied())
+                .collect();
+            assert_eq!(columns, expected);
+            assert_eq!(rows[1][0], Some("2".to_string()));
+            assert_eq!(rows[1][1], Some(format!("b{}", 1 + 3 * k)));
+        }
+
+        let options = ConvertExcelToParquetOptions {
+            column_split: Some(ColumnSplit {
+                max_columns: 1,
+                key_columns: vec!["id".to_string()],
+            }),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+
+        // 不拆分时只列出 `output_path`
+        let report =
+            convert_xlsx_to_parquet(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert_eq!(report.output_files, vec![output.clone()]);
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("dupes.xlsx");
+        let output = dir.path().join("dupes.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "name"],
+                    vec!["1", "a"],
+                    vec!["1", "a"],
+                    vec!["2", "b"],
+                    vec!["1", "c"],
+                    vec!["2", "b"],
+                ],
+            )],
+        );
+
+        let convert = |deduplicate: DedupMode| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(2),
+                deduplicate: Some(deduplicate),
+                verify_output: true,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options)?;
+            Ok::<_, anyhow::Error>((report.duplicate_rows_removed, read_parquet(&output).1))
+        };
+        let row = |id: &str, name: &str| vec![Some(id.to_string()), Some(name.to_string())];
+
+        let (removed, rows) = convert(DedupMode::FullRow).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(rows, vec![row("1", "a"), row("2", "b"), row("1", "c")]);
+
+        let (removed, rows) = convert(DedupMode::ByColumns(vec!["id".to_string()])).unwrap();
+        assert_eq!(removed, 3);
+        assert_eq!(rows, vec![row("1", "a"), row("2", "b")]);
+
+        assert!(convert(DedupMode::ByColumns(vec!["missing".to_string()])).is_err());
+    }
+
+    #[test]
+    fn test_repeated_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("report.xlsx");
+        let output = dir.path().join("report.parquet");
+        let ids: Vec<String> = (0..150).map(|i| i.to_string()).collect();
+        // 每 50 行重复一次表头
+        let rows: Vec<Vec<&str>> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                if i % 50 == 0 {
+                    vec!["id", "name"]
+                } else {
+                    vec![id.as_str(), "x"]
+                }
+            })
+            .collect();
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |repeated_header: Option<RepeatedHeader>| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(40),
+                repeated_header,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            (report, read_parquet(&output).1)
+        };
+
+        let (report, rows) = convert(None);
+        assert_eq!(report.total_rows, 149);
+        assert_eq!(rows[49][0].as_deref(), Some("id"));
+
+        let (report, rows) = convert(Some(RepeatedHeader::SameAsHeader));
+        assert_eq!(report.total_rows, 147);
+        assert_eq!(report.repeated_headers_skipped, 2);
+        assert_eq!(rows.len(), 147);
+        assert!(rows.iter().all(|row| row[0].as_deref() != Some("id")));
+        assert_eq!(rows[49][0].as_deref(), Some("51"));
+
+        let text = RepeatedHeader::Text(vec!["id".to_string(), "name".to_string()]);
+        assert_eq!(convert(Some(text)).0.repeated_headers_skipped, 2);
+    }
+
+    #[test]
+    fn test_skip_title_bands() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("regions.xlsx");
+        let output = dir.path().join("regions.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "name", "amount"],
+                    vec!["North Region"],
+                    vec!["1", "a", "10"],
+                    vec!["2", "b", "20"],
+                    vec!["South Region"],
+                    vec!["3", "c", "30"],
+                    vec!["", "", "Subtotal"],
+                    vec!["4", "d", ""],
+                ],
+            )],
+        );
+
+        let convert = |skip_title_bands: Option<TitleBand>| {
+            let options = ConvertExcelToParquetOptions {
+                skip_title_bands,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            let ids: Vec<_> = read_parquet(&output)
+                .1
+                .into_iter()
+                .map(|row| row[0].clone().unwrap_or_default())
+                .collect();
+            (report, ids)
+        };
+
+        let (report, ids) = convert(None);
+        assert_eq!(report.total_rows, 7);
+        assert_eq!(ids[0], "North Region");
+
+        let (report, ids) = convert(Some(TitleBand::default()));
+        assert_eq!(report.title_rows_skipped, 2);
+        assert_eq!(report.total_rows, 5);
+        assert_eq!(ids, vec!["1", "2", "3", "", "4"]);
+
+        // 不限位置时单独一格的小计行也被跳过，两格有值的数据行保留
+        let (report, ids) = convert(Some(TitleBand {
+            max_cells: 1,
+            require_first_column: false,
+        }));
+        assert_eq!(report.title_rows_skipped, 3);
+        assert_eq!(ids, vec!["1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("sample.xlsx");
+        let output = dir.path().join("sample.parquet");
+        let ids: Vec<String> = (1..=100).map(|i| i.to_string()).collect();
+        let mut rows = vec![vec!["id"]];
+        rows.extend(ids.iter().map(|id| vec![id.as_str()]));
+        write_workbook(&input, &[("Data", rows)]);
+
+        let convert = |sample: SampleSpec, sample_seed: Option<u64>| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(7),
+                sample: Some(sample),
+                sample_seed,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            assert_eq!(report.total_rows + report.rows_not_sampled, 100);
+            let (_, rows) = read_parquet(&output);
+            let ids: Vec<usize> = rows
+                .into_iter()
+                .map(|row| row[0].as_deref().unwrap().parse().unwrap())
+                .collect();
+            assert_eq!(ids.len(), report.total_rows);
+            ids
+        };
+
+        let every = convert(SampleSpec::SystematicEvery(10), None);
+        assert_eq!(every, (1..=100).step_by(10).collect::<Vec<_>>());
+
+        // 相同种子抽中相同的行，且比例大致符合
+        let random = convert(SampleSpec::RandomFraction(0.3), Some(42));
+        assert_eq!(convert(SampleSpec::RandomFraction(0.3), Some(42)), random);
+        assert!((10..=50).contains(&random.len()), "{}", random.len());
+        assert!(convert(SampleSpec::RandomFraction(0.0), Some(42)).is_empty());
+        assert_eq!(
+            convert(SampleSpec::RandomFraction(1.0), Some(42)).len(),
+            100
+        );
+
+        let options = ConvertExcelToParquetOptions {
+            sample: Some(SampleSpec::SystematicEvery(0)),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+    }
+
+    #[test]
+    fn test_on_replacement_char() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("corrupt.xlsx");
+        let output = dir.path().join("corrupt.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["id", "name"],
+                    vec!["1", "M\u{FFFD}ller"],
+                    vec!["2", "ok"],
+                ],
+            )],
+        );
+
+        let convert = |on_replacement_char: ReplacementPolicy, error_mode: ErrorMode| {
+            let options = ConvertExcelToParquetOptions {
+                on_replacement_char,
+                error_mode,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options)
+                .map(|report| (report, read_parquet(&output).1[0][1].clone()))
+        };
+
+        let (report, name) = convert(ReplacementPolicy::Keep, ErrorMode::FailFast).unwrap();
+        assert_eq!(report.replacement_char_cells, 1);
+        assert_eq!(name.as_deref(), Some("M\u{FFFD}ller"));
+        let (_, name) = convert(ReplacementPolicy::Strip, ErrorMode::FailFast).unwrap();
+        assert_eq!(name.as_deref(), Some("Mller"));
+        let (_, name) = convert(ReplacementPolicy::Null, ErrorMode::FailFast).unwrap();
+        assert_eq!(name, None);
+
+        let err = convert(ReplacementPolicy::Error, ErrorMode::FailFast).unwrap_err();
+        assert!(err.to_string().contains("row 1, column 1"), "{}", err);
+        let (report, name) = convert(
+            ReplacementPolicy::Error,
+            ErrorMode::Collect { max_errors: 10 },
+        )
+        .unwrap();
+        assert_eq!(report.error_count, 1);
+        assert_eq!(report.errors[0].row, Some(1));
+        assert_eq!(name.as_deref(), Some("M\u{FFFD}ller"));
+    }
+
+    #[test]
+    fn test_whitespace_as_null() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("whitespace.xlsx");
+        let output = dir.path().join("whitespace.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![vec!["value"], vec!["   "], vec!["\t"], vec!["x "]],
+            )],
+        );
+
+        let convert = |whitespace_as_null: bool| {
+            let options = ConvertExcelToParquetOptions {
+                whitespace_as_null,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            read_parquet(&output).1
+        };
+        assert_eq!(
+            convert(false),
+            vec![
+                vec![Some("   ".to_string())],
+                vec![Some("\t".to_string())],
+                vec![Some("x ".to_string())]
+            ]
+        );
+        assert_eq!(
+            convert(true),
+            vec![vec![None], vec![None], vec![Some("x ".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_forward_fill_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("fill.xlsx");
+        let output = dir.path().join("fill.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["region", "city", "note"],
+                    vec!["North", "Harbin", "a"],
+                    vec!["", "Dalian"],
+                    vec!["", "Shenyang", ""],
+                    vec!["South", "Guangzhou"],
+                    vec!["", "Shenzhen", "b"],
+                ],
+            )],
+        );
+
+        // 批次大小为 2，填充需跨批次延续；未指定的列不受影响
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(2),
+            forward_fill_columns: vec!["region".to_string()],
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        let (_, rows) = read_parquet(&output);
+        let region: Vec<_> = rows.iter().map(|row| row[0].as_deref()).collect();
+        assert_eq!(
+            region,
+            vec![
+                Some("North"),
+                Some("North"),
+                Some("North"),
+                Some("South"),
+                Some("South")
+            ]
+        );
+        assert!(rows.iter().skip(1).take(3).all(|row| row[2].is_none()));
+
+        let options = ConvertExcelToParquetOptions {
+            forward_fill_columns: vec!["missing".to_string()],
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        assert!(convert_xlsx_to_parquet(options).is_err());
+    }
+
+    #[test]
+    fn test_header_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("detect.parquet");
+        // 字符串单元格写为文本，其余写为数字
+        let write = |name: &str, rows: &[&[&str]]| {
+            let path = dir.path().join(name);
+            let mut workbook = Workbook::new();
+            let worksheet = workbook.add_worksheet();
+            for (r, row) in rows.iter().enumerate() {
+                for (c, value) in row.iter().enumerate() {
+                    match value.parse::<f64>() {
+                        Ok(number) => worksheet.write_number(r as u32, c as u16, number),
+                        Err(_) => worksheet.write_string(r as u32, c as u16, *value),
+                    }
+                    .unwrap();
+                }
+            }
+            workbook.save(&path).unwrap();
+            path
+        };
+        let with_header = write(
+            "with_header.xlsx",
+            &[&["id", "city"], &["1", "Paris"], &["2", "Oslo"]],
+        );
+        let without_header = write("without_header.xlsx", &[&["1", "Paris"], &["2", "Oslo"]]);
+        // 第一行全是文本，但第二行在第一行为空的列上有值
+        let gap = write(
+            "gap.xlsx",
+            &[
+                &["Paris", "", "x"],
+                &["Oslo", "y", "z"],
+                &["Rome", "w", "v"],
+            ],
+        );
 
-    // Get sheet name using Reader trait
-    let sheet_name = get_sheet_name(&workbook, &options)?;
-    println!("Processing sheet: {}", sheet_name);
+        let convert = |input: &Path, header_detection: HeaderDetection| {
+            let options = ConvertExcelToParquetOptions {
+                header_detection,
+                ..ConvertExcelToParquetOptions::new(input, &output)
+            };
+            let report = convert_xlsx_to_parquet(options).unwrap();
+            let (columns, rows) = read_parquet(&output);
+            (report.has_header, report.total_rows, columns, rows)
+        };
 
-    let mut cells_reader = workbook
-        .worksheet_cells_reader(&sheet_name)
-        .context("Failed to get worksheet cells reader")?;
+        let (has_header, total_rows, columns, rows) = convert(&with_header, HeaderDetection::Guess);
+        assert!(has_header);
+        assert_eq!(total_rows, 2);
+        assert_eq!(columns, vec!["id", "city"]);
+        assert_eq!(rows[0], vec![Some("1".into()), Some("Paris".into())]);
 
-    let dimensions = cells_reader.dimensions();
-    let mut context = ConversionContext::new(&options, dimensions)?;
+        let (has_header, total_rows, columns, rows) =
+            convert(&without_header, HeaderDetection::Guess);
+        assert!(!has_header);
+        assert_eq!(total_rows, 2);
+        assert_eq!(columns, vec!["Field_0", "Field_1"]);
+        assert_eq!(rows[0], vec![Some("1".into()), Some("Paris".into())]);
 
-    while let Some(cell) = cells_reader.next_cell().context("Failed to read cell")? {
-        let (row, col) = cell.get_position();
-        let value = cell_to_string(cell.get_value());
-        context.process_cell(row, col, value)?;
-    }
+        let (has_header, total_rows, ..) = convert(&gap, HeaderDetection::Guess);
+        assert!(!has_header);
+        assert_eq!(total_rows, 3);
 
-    context.finish()?;
+        let (has_header, total_rows, columns, _) = convert(&with_header, HeaderDetection::None);
+        assert!(!has_header);
+        assert_eq!(total_rows, 3);
+        assert_eq!(columns, vec!["Field_0", "Field_1"]);
+        assert!(convert(&with_header, HeaderDetection::FirstRow).0);
+    }
 
-    println!(
-        "Successfully converted {} to {} (processed ~{} rows)",
-        options.excel_file.to_string_lossy(),
-        options.output_path.to_string_lossy(),
-        context.total_rows
-    );
+    #[cfg(feature = "styles")]
+    #[test]
+    fn test_header_by_style() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("bold.xlsx");
+        let output = dir.path().join("bold.parquet");
+        // 标题行与空行之后是加粗的年份表头，内容上与下面的数字行无法区分
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        let bold = rust_xlsxwriter::Format::new().set_bold();
+        worksheet.write_string(0, 0, "Sales report").unwrap();
+        worksheet
+            .write_number_with_format(2, 0, 2023, &bold)
+            .unwrap();
+        worksheet
+            .write_number_with_format(2, 1, 2024, &bold)
+            .unwrap();
+        for (r, values) in [[10, 12], [20, 25]].iter().enumerate() {
+            for (c, value) in values.iter().enumerate() {
+                worksheet
+                    .write_number(r as u32 + 3, c as u16, *value)
+                    .unwrap();
+            }
+        }
+        workbook.save(&input).unwrap();
 
-    Ok(())
-}
+        let options = ConvertExcelToParquetOptions {
+            header_detection: HeaderDetection::ByStyle,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options.clone()).unwrap();
+        assert!(report.has_header);
+        assert_eq!(report.total_rows, 2);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["2023", "2024"]);
+        assert_eq!(rows[1], vec![Some("20".into()), Some("25".into())]);
 
-/// 将 Xlsb 文件转换为 Parquet
-pub fn convert_xlsb_to_parquet(options: ConvertExcelToParquetOptions) -> Result<()> {
-    println!(
-        "Starting conversion for (XLSB): {}",
-        options.excel_file.display()
-    );
-    let mut workbook: Xlsb<_> =
-        open_workbook(options.excel_file).context("Failed to open Excel file")?;
+        // 从内存读取时没有文件可读样式，按第一行是表头处理（没有单元格的空行不输出）
+        let (_, report) =
+            convert_reader_to_bytes(File::open(&input).unwrap(), ExcelFormat::Xlsx, options)
+                .unwrap();
+        assert_eq!(report.total_rows, 3);
+    }
 
-    let sheet_name = get_sheet_name(&workbook, &options)?;
-    println!("Processing sheet: {}", sheet_name);
+    #[test]
+    fn test_header_index_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("offset.xlsx");
+        let output = dir.path().join("offset.parquet");
+        // 数据从 C 列开始，D 列表头为空
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 2, "id").unwrap();
+        worksheet.write_string(0, 4, "name").unwrap();
+        for col in 2..5 {
+            worksheet.write_string(1, col, "x").unwrap();
+        }
+        workbook.save(&input).unwrap();
 
-    let mut cells_reader = workbook
-        .worksheet_cells_reader(&sheet_name)
-        .context("Failed to get worksheet cells reader")?;
+        let convert = |header_index_base: HeaderIndexBase| {
+            let options = ConvertExcelToParquetOptions {
+                header_index_base,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            read_parquet(&output).0
+        };
+        assert_eq!(
+            convert(HeaderIndexBase::RegionRelative),
+            vec!["id", "Field_1", "name"]
+        );
+        assert_eq!(
+            convert(HeaderIndexBase::ColumnLetter),
+            vec!["id", "Field_D", "name"]
+        );
+    }
 
-    let dimensions = cells_reader.dimensions();
-    let mut context = ConversionContext::new(&options, dimensions)?;
+    #[test]
+    fn test_case_insensitive_dedup() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("case.xlsx");
+        let output = dir.path().join("case.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![vec!["ID", "id", "Id", "name"], vec!["1", "2", "3", "a"]],
+            )],
+        );
 
-    while let Some(cell) = cells_reader.next_cell().context("Failed to read cell")? {
-        let (row, col) = cell.get_position();
-        let value = cell_to_string(cell.get_value());
-        context.process_cell(row, col, value)?;
+        let convert = |case_insensitive_dedup: bool| {
+            let options = ConvertExcelToParquetOptions {
+                case_insensitive_dedup,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            read_parquet(&output).0
+        };
+        assert_eq!(convert(false), vec!["ID", "id", "Id", "name"]);
+        assert_eq!(convert(true), vec!["ID", "id_2", "Id_3", "name"]);
     }
 
-    context.finish()?;
+    #[test]
+    fn test_dedup_strategy() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("dupes.xlsx");
+        let output = dir.path().join("dupes.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Data",
+                vec![
+                    vec!["amount", "name", "amount", "amount"],
+                    vec!["1", "a", "2", "3"],
+                ],
+            )],
+        );
 
-    println!(
-        "Successfully converted {} to {} (processed ~{} rows)",
-        options.excel_file.to_string_lossy(),
-        options.output_path.to_string_lossy(),
-        context.total_rows
-    );
+        let convert = |dedup_strategy: DedupStrategy| {
+            let options = ConvertExcelToParquetOptions {
+                dedup_strategy,
+                error_mode: ErrorMode::Collect { max_errors: 10 },
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).map(|report| (report, read_parquet(&output)))
+        };
+        let (_, (columns, rows)) = convert(DedupStrategy::default()).unwrap();
+        assert_eq!(columns, vec!["amount", "name", "amount_2", "amount_3"]);
+        assert_eq!(rows[0][3].as_deref(), Some("3"));
+        let (_, (columns, _)) = convert(DedupStrategy::NumericSuffix { base: 1 }).unwrap();
+        assert_eq!(columns, vec!["amount", "name", "amount_1", "amount_2"]);
+        let (_, (columns, _)) = convert(DedupStrategy::NumericSuffix { base: 0 }).unwrap();
+        assert_eq!(columns, vec!["amount", "name", "amount_0", "amount_1"]);
+        let (_, (columns, _)) = convert(DedupStrategy::LetterSuffix).unwrap();
+        assert_eq!(columns, vec!["amount", "name", "amount_b", "amount_c"]);
 
-    Ok(())
-}
+        let err = convert(DedupStrategy::Error).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Duplicate header 'amount' in columns A and C"),
+            "{}",
+            err
+        );
 
-// 辅助函数：获取 Sheet Name
-fn get_sheet_name<R>(workbook: &R, options: &ConvertExcelToParquetOptions) -> Result<String>
-where
-    R: Reader<std::io::BufReader<File>>,
-{
-    if let Some(sheet_name) = &options.sheet_name {
-        Ok(sheet_name.clone())
-    } else if let Some(index) = options.sheet_index {
-        workbook
-            .sheet_names()
-            .get(index)
-            .context(format!("Sheet index {} out of bounds", index))
-            .map(|s| s.clone())
-    } else {
-        workbook
-            .sheet_names()
-            .first()
-            .context("No worksheets found")
-            .map(|s| s.clone())
+        // 丢弃的列中的单元格不记为问题
+        let (report, (columns, rows)) = convert(DedupStrategy::KeepFirstDropRest).unwrap();
+        assert_eq!(columns, vec!["amount", "name"]);
+        assert_eq!(rows, vec![vec![Some("1".into()), Some("a".into())]]);
+        assert_eq!(report.error_count, 0);
     }
-}
 
-/// 转换上下文，管理状态和线程
-struct ConversionContext {
-    // Config
-    header_row_idx: u32,
-    num_cols: usize,
-    start_col: u32,
-    batch_size: usize,
+    #[test]
+    fn test_output_column_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("order.xlsx");
+        write_workbook(
+            &input,
+            &[
+                (
+                    "A",
+                    vec![
+                        vec!["name", "id", "amount", "amount"],
+                        vec!["a", "1", "10", "11"],
+                    ],
+                ),
+                (
+                    "B",
+                    vec![
+                        vec!["amount", "id", "amount", "name"],
+                        vec!["20", "2", "21", "b"],
+                    ],
+                ),
+            ],
+        );
 
-    // State
-    current_row: Option<u32>,
-    current_row_cells: HashMap<u32, String>, // Header building
-    raw_cells_buffer: Vec<RawCell>,
-    current_batch_rows: usize,
-    batch_counter: usize,
-    workers_started: bool,
-    pub total_rows: usize,
+        let convert = |sheet: &str, output_column_order: OutputColumnOrder| {
+            let output = dir.path().join(format!("{}.parquet", sheet));
+            let options = ConvertExcelToParquetOptions {
+                sheet_name: Some(sheet.to_string()),
+                include_sheet_column: Some("sheet".to_string()),
+                output_column_order,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+            let builder =
+                ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap()).unwrap();
+            let arrow_schema = builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .and_then(|kv| kv.iter().find(|kv| kv.key == "ARROW:schema"))
+                .and_then(|kv| kv.value.clone())
+                .unwrap();
+            (arrow_schema, read_parquet(&output))
+        };
 
-    // Channels & Threads
-    work_tx: Option<mpsc::SyncSender<RawBatch>>, // Option allows dropping to signal EOF
-    result_tx: Option<mpsc::SyncSender<ProcessedBatch>>, // Option allows dropping
-    worker_threads: Vec<thread::JoinHandle<Result<()>>>,
-    writer_thread: Option<thread::JoinHandle<Result<()>>>,
+        // 先去重再排序，两个 Sheet 的 schema 逐字节相同，数据随列一起重排
+        let (schema_a, (columns, rows_a)) = convert("A", OutputColumnOrder::Sorted);
+        let (schema_b, (_, rows_b)) = convert("B", OutputColumnOrder::Sorted);
+        assert_eq!(columns, vec!["amount", "amount_2", "id", "name", "sheet"]);
+        assert_eq!(schema_a, schema_b);
+        let row = |values: &[&str]| -> Vec<Option<String>> {
+            values.iter().map(|v| Some(v.to_string())).collect()
+        };
+        assert_eq!(rows_a, vec![row(&["10", "11", "1", "a", "A"])]);
+        assert_eq!(rows_b, vec![row(&["20", "21", "2", "b", "B"])]);
 
-    // Shared for init
-    work_rx: Option<Arc<std::sync::Mutex<mpsc::Receiver<RawBatch>>>>,
-    result_rx: Option<mpsc::Receiver<ProcessedBatch>>,
-    output_path: PathBuf,
-}
+        let (unsorted_a, _) = convert("A", OutputColumnOrder::SheetOrder);
+        let (unsorted_b, _) = convert("B", OutputColumnOrder::SheetOrder);
+        assert_ne!(unsorted_a, unsorted_b);
 
-impl ConversionContext {
-    fn new(
-        options: &ConvertExcelToParquetOptions,
-        dimensions: calamine::Dimensions,
-    ) -> Result<Self> {
-        let num_cols = (dimensions.end.1 - dimensions.start.1 + 1) as usize;
-        let start_col = dimensions.start.1;
-        let header_row_idx = dimensions.start.0 + options.skip_rows as u32;
+        let order = OutputColumnOrder::Custom(vec!["sheet".to_string(), "id".to_string()]);
+        let (_, (columns, rows)) = convert("A", order);
+        assert_eq!(columns, vec!["sheet", "id", "name", "amount", "amount_2"]);
+        assert_eq!(rows, vec![row(&["A", "1", "a", "10", "11"])]);
 
-        println!(
-            "Sheet dimensions: rows {}-{}, cols {}-{}",
-            dimensions.start.0, dimensions.end.0, dimensions.start.1, dimensions.end.1
+        let output = dir.path().join("missing.parquet");
+        let options = ConvertExcelToParquetOptions {
+            output_column_order: OutputColumnOrder::Custom(vec!["missing".to_string()]),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let err = convert_xlsx_to_parquet(options).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Output order column 'missing' not found in headers"),
+            "{}",
+            err
         );
+    }
 
-        let num_workers = 8;
-        let (work_tx, work_rx) = mpsc::sync_channel::<RawBatch>(num_workers * 2);
-        let (result_tx, result_rx) = mpsc::sync_channel::<ProcessedBatch>(num_workers * 2);
+    #[test]
+    fn test_on_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("done.xlsx");
+        let output = dir.path().join("done.parquet");
+        write_workbook(&input, &[("Data", vec![vec!["id"], vec!["1"], vec!["2"]])]);
 
-        Ok(Self {
-            header_row_idx,
-            num_cols,
-            start_col,
-            batch_size: options.batch_size,
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = calls.clone();
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(1),
+            on_complete: Some(Arc::new(move |path, report| {
+                // 回调时输出文件已就位
+                let (_, rows) = read_parquet(path);
+                sink.lock()
+                    .unwrap()
+                    .push((path.to_path_buf(), report.total_rows, rows.len()))
+            })),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options.clone()).unwrap();
+        assert_eq!(report.total_rows, 2);
+        assert_eq!(*calls.lock().unwrap(), vec![(output.clone(), 2, 2)]);
 
-            current_row: None,
-            current_row_cells: HashMap::new(),
-            raw_cells_buffer: Vec::with_capacity(options.batch_size * num_cols),
-            current_batch_rows: 0,
-            batch_counter: 0,
-            workers_started: false,
-            total_rows: 0,
+        // 不写文件的转换不触发回调
+        convert_reader_to_bytes(File::open(&input).unwrap(), ExcelFormat::Xlsx, options).unwrap();
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
 
-            work_tx: Some(work_tx),
-            result_tx: Some(result_tx),
-            worker_threads: Vec::new(),
-            writer_thread: None,
+    #[test]
+    fn test_progress_estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("padded.xlsx");
+        let output = dir.path().join("padded.parquet");
 
-            work_rx: Some(Arc::new(std::sync::Mutex::new(work_rx))),
-            result_rx: Some(result_rx),
-            output_path: options.output_path.to_path_buf(),
+        // 数据隔行分布，其后是大量只有格式的空白行，dimensions() 报告 1000 行
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "id").unwrap();
+        for i in 1..=40u32 {
+            worksheet.write_number(i * 2 - 1, 0, i as f64).unwrap();
+        }
+        let format = rust_xlsxwriter::Format::new().set_bold();
+        for row in 80..1000 {
+            worksheet.write_blank(row, 0, &format).unwrap();
+        }
+        workbook.save(&input).unwrap();
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let report = convert_xlsx_to_parquet(ConvertExcelToParquetOptions {
+            batch_size: Some(4),
+            drop_trailing_blank_rows: true,
+            progress: Some(Arc::new(move |progress| {
+                sink.lock().unwrap().push(progress)
+            })),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
         })
-    }
+        .unwrap();
+        assert_eq!(report.total_rows, 40);
 
-    fn process_cell(&mut self, row: u32, col: u32, value: String) -> Result<()> {
-        if !self.workers_started {
-            self.handle_header_phase(row, col, value)
-        } else {
-            self.handle_worker_phase(row, col, value)
-        }
+        let reports = reports.lock().unwrap();
+        assert!(reports.len() > PROGRESS_SAMPLE_BATCHES);
+        assert!(reports.iter().all(|progress| progress.fraction() <= 1.0));
+        assert!(
+            reports
+                .windows(2)
+                .all(|pair| pair[0].rows_read <= pair[1].rows_read)
+        );
+        // 首个批次读到第 7 行：已读 4 行 + 剩余 992 行
+        assert_eq!(reports[0].estimated_total_rows, 996);
+        // 采样后按一半的行密度修正分母
+        assert!(reports[PROGRESS_SAMPLE_BATCHES].estimated_total_rows < 600);
+        // 填充区内估计逐步收敛到实际行数
+        let padding: Vec<_> = reports[..reports.len() - 1]
+            .iter()
+            .filter(|progress| progress.rows_read == 40)
+            .collect();
+        assert!(padding.len() > 100);
+        assert!(
+            padding
+                .windows(2)
+                .all(|pair| pair[0].estimated_total_rows >= pair[1].estimated_total_rows)
+        );
+        assert!(padding.last().unwrap().estimated_total_rows <= 42);
+        assert_eq!(
+            reports.last().unwrap(),
+            &Progress {
+                rows_read: 40,
+                estimated_total_rows: 40
+            }
+        );
     }
 
-    fn handle_header_phase(&mut self, row: u32, col: u32, value: String) -> Result<()> {
-        if self.current_row.is_none() {
-            self.current_row = Some(row);
-        } else if self.current_row != Some(row) {
-            // 换行了，检查上一行是不是 header
-            let prev_row = self.current_row.unwrap();
+    #[test]
+    fn test_max_distinct_per_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("ids.xlsx");
+        let output = dir.path().join("ids.parquet");
+        let ids: Vec<String> = (0..200).map(|i| format!("id-{}", i)).collect();
+        let mut rows = vec![vec!["id", "status"]];
+        rows.extend(
+            ids.iter()
+                .enumerate()
+                .map(|(i, id)| vec![id.as_str(), if i % 2 == 0 { "open" } else { "closed" }]),
+        );
+        write_workbook(&input, &[("Data", rows)]);
 
-            if prev_row == self.header_row_idx {
-                self.start_workers_and_writer()?;
-                self.workers_started = true;
-            }
+        let convert = |strict_distinct_check: bool| {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(100),
+                max_distinct_per_column: Some(50),
+                strict_distinct_check,
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options)
+        };
+        let report = convert(false).unwrap();
+        assert_eq!(report.high_cardinality_columns, vec!["id"]);
 
-            self.current_row_cells.clear();
-            self.current_row = Some(row);
+        let reader =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap()).unwrap();
+        for row_group in reader.metadata().row_groups() {
+            assert!(row_group.column(0).dictionary_page_offset().is_none());
+            assert!(row_group.column(1).dictionary_page_offset().is_some());
         }
-        self.current_row_cells.insert(col, value);
-        Ok(())
+        assert_eq!(read_parquet(&output).1.len(), 200);
+
+        assert!(convert(true).is_err());
     }
 
-    fn start_workers_and_writer(&mut self) -> Result<()> {
-        let headers = build_headers(&self.current_row_cells, self.num_cols, self.start_col);
-        println!("Found headers: {} columns", headers.len());
+    #[test]
+    fn test_spill_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("spilled.parquet");
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Utf8, true)]));
+        let batch = |id: usize| {
+            let values: StringArray = (0..100)
+                .map(|row| Some(format!("{}-{}", id, row)))
+                .collect();
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(values)]).unwrap()
+        };
 
-        let schema = Arc::new(Schema::new(
-            headers
-                .iter()
-                .map(|name| Field::new(name, DataType::Utf8, true))
-                .collect::<Vec<Field>>(),
-        ));
+        // 批次完全倒序到达：除最后到达的 0 号批次外全部溢出到磁盘
+        let buffer = SharedBuffer::default();
+        let sink: Box<dyn Write + Send> = Box::new(buffer.clone());
+        let sink = ArrowWriter::try_new(sink, schema.clone(), None).unwrap();
+        let mut writer = OrderedWriter::new(
+            BatchSink::Parquet(Box::new(sink)),
+            schema.clone(),
+            None,
+            RowGroupSplit::BySize,
+        )
+        .with_spill()
+        .unwrap();
+        let spill_dir = writer.spill_dir().unwrap().to_path_buf();
+        // 每个溢出目录都是新建的，名称不可预测，且只有所有者可以访问
+        let other = crate::spill::SpillDir::create().unwrap();
+        assert_ne!(other.path(), spill_dir);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&spill_dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        drop(other);
+        for id in (1..10).rev() {
+            writer.push(id, batch(id)).unwrap();
+        }
+        assert_eq!(writer.buffered_batches(), (0, 9));
+        assert_eq!(std::fs::read_dir(&spill_dir).unwrap().count(), 9);
+        writer.push(0, batch(0)).unwrap();
+        assert_eq!(writer.buffered_batches(), (0, 0));
+        writer.finish().unwrap();
+        assert!(!spill_dir.exists());
 
-        // Start Workers
-        let num_workers = 8;
-        let work_rx = self.work_rx.take().unwrap(); // Take the rx to share
+        std::fs::write(&output, buffer.take()).unwrap();
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows.len(), 1000);
+        assert_eq!(rows[0][0].as_deref(), Some("0-0"));
+        assert_eq!(rows[999][0].as_deref(), Some("9-99"));
 
-        for _ in 0..num_workers {
-            let work_rx_clone = work_rx.clone();
-            let result_tx_clone = self.result_tx.as_ref().unwrap().clone();
-            let schema_clone = schema.clone();
-            let headers_len = headers.len();
-            let start_col_val = self.start_col;
+        // 通过选项启用时输出不变
+        let input = dir.path().join("spill.xlsx");
+        let mut rows = vec![vec!["id"]];
+        let ids: Vec<String> = (0..500).map(|i| i.to_string()).collect();
+        rows.extend(ids.iter().map(|id| vec![id.as_str()]));
+        write_workbook(&input, &[("Data", rows)]);
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(10),
+            spill_to_disk: true,
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+        let (_, rows) = read_parquet(&output);
+        let read_ids: Vec<_> = rows
+            .into_iter()
+            .map(|row| row[0].clone().unwrap())
+            .collect();
+        assert_eq!(read_ids, ids);
+    }
 
-            let handle = thread::spawn(move || -> Result<()> {
-                loop {
-                    let msg = {
-                        let lock = work_rx_clone.lock().unwrap();
-                        match lock.recv() {
-                            Ok(m) => m,
-                            Err(_) => break,
-                        }
-                    };
-                    let (id, cells) = msg;
-                    let record_batch = create_record_batch_from_cells(
-                        &schema_clone,
-                        headers_len,
-                        &cells,
-                        start_col_val,
-                    )?;
-                    if result_tx_clone.send((id, record_batch)).is_err() {
-                        break;
-                    }
-                }
-                Ok(())
-            });
-            self.worker_threads.push(handle);
-        }
+    #[test]
+    fn test_memory_pressure() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("pressure.parquet");
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Utf8, true)]));
+        let batch = |id: usize| {
+            let values: StringArray = (0..10).map(|row| Some(format!("{}-{}", id, row))).collect();
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(values)]).unwrap()
+        };
 
-        // Start Writer
-        let output_path = self.output_path.clone();
-        let batch_size = self.batch_size;
-        let schema_clone = schema.clone();
-        let result_rx = self.result_rx.take().unwrap();
+        // 模拟的压力信号：只在第 3 次检查（收到 1 号批次）时报告内存紧张
+        let checks = Arc::new(AtomicUsize::new(0));
+        let pressure: MemoryPressureCallback = {
+            let checks = checks.clone();
+            Arc::new(move || checks.fetch_add(1, Ordering::Relaxed) == 2)
+        };
+        let buffer = SharedBuffer::default();
+        let sink: Box<dyn Write + Send> = Box::new(buffer.clone());
+        let sink = ArrowWriter::try_new(sink, schema.clone(), None).unwrap();
+        let mut writer = OrderedWriter::new(
+            BatchSink::Parquet(Box::new(sink)),
+            schema.clone(),
+            None,
+            RowGroupSplit::BySize,
+        )
+        .with_memory_pressure(pressure);
+        writer.push(0, batch(0)).unwrap();
+        writer.push(2, batch(2)).unwrap();
+        assert_eq!(writer.buffered_batches(), (1, 0));
+        writer.push(1, batch(1)).unwrap();
+        // 压力出现时 2 号批次被溢出，0 号批次单独成为一个 row group
+        writer.push(4, batch(4)).unwrap();
+        assert_eq!(writer.buffered_batches(), (0, 1));
+        writer.push(3, batch(3)).unwrap();
+        let written = writer.finish().unwrap();
+        assert_eq!(written.memory_pressure_flushes, 1);
+        let row_groups: Vec<_> = written
+            .row_group_stats
+            .iter()
+            .map(|stats| stats.num_rows)
+            .collect();
+        assert_eq!(row_groups, vec![10, 40]);
 
-        self.writer_thread = Some(thread::spawn(move || -> Result<()> {
-            let props = WriterProperties::builder()
-                .set_compression(Compression::ZSTD(ZstdLevel::default()))
-                .set_max_row_group_size(batch_size)
-                .build();
-
-            let file = File::create(output_path).context("Failed to create output file")?;
-            let mut writer = ArrowWriter::try_new(file, schema_clone, Some(props))
-                .context("Failed to create parquet writer")?;
-
-            let mut buffer: HashMap<usize, RecordBatch> = HashMap::new();
-            let mut next_expected_id = 0;
-            let mut total_written_rows = 0;
-
-            while let Ok((id, batch)) = result_rx.recv() {
-                if id == next_expected_id {
-                    let batch_rows = batch.num_rows();
-                    writer
-                        .write(&batch)
-                        .context("Failed to write record batch")?;
-                    total_written_rows += batch_rows;
-                    println!(
-                        "Writer: wrote batch {} ({} rows). Total written: {}",
-                        id, batch_rows, total_written_rows
-                    );
-                    next_expected_id += 1;
-
-                    while let Some(next_batch) = buffer.remove(&next_expected_id) {
-                        let next_rows = next_batch.num_rows();
-                        writer
-                            .write(&next_batch)
-                            .context("Failed to write buffered batch")?;
-                        total_written_rows += next_rows;
-                        println!(
-                            "Writer: wrote buffered batch {} ({} rows). Total written: {}",
-                            next_expected_id, next_rows, total_written_rows
-                        );
-                        next_expected_id += 1;
-                    }
-                } else {
-                    buffer.insert(id, batch);
-                }
-            }
+        std::fs::write(&output, buffer.take()).unwrap();
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows.len(), 50);
+        assert_eq!(rows[49][0].as_deref(), Some("4-9"));
+    }
+
+    #[test]
+    fn test_sheet_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("versions.xlsx");
+        let output = dir.path().join("diff.parquet");
+        write_workbook(
+            &input,
+            &[
+                (
+                    "Old",
+                    vec![
+                        vec!["id", "name", "qty"],
+                        vec!["1", "apple", "3"],
+                        vec!["2", "pear", "5"],
+                        vec!["3", "plum", "7"],
+                    ],
+                ),
+                (
+                    "New",
+                    vec![
+                        vec!["id", "name", "qty"],
+                        vec!["4", "fig", "1"],
+                        vec!["1", "apple", "3"],
+                        vec!["2", "pear", "6"],
+                    ],
+                ),
+            ],
+        );
 
-            if !buffer.is_empty() {
-                eprintln!("Warning: Writer finished with buffered batches remaining!");
+        let report = convert_sheet_diff(
+            &input,
+            "Old",
+            &input,
+            "New",
+            "id",
+            ConvertExcelToParquetOptions::new(&input, &output),
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            SheetDiffReport {
+                added: 1,
+                removed: 1,
+                changed: 1,
+                unchanged: 1
             }
-            writer.close()?;
-            println!("Writer thread: finished.");
-            Ok(())
-        }));
+        );
 
-        Ok(())
-    }
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec![DIFF_STATUS_COLUMN, "id", "name", "qty"]);
+        let row = |values: [&str; 4]| values.map(|v| Some(v.to_string())).to_vec();
+        assert_eq!(
+            rows,
+            vec![
+                row(["added", "4", "fig", "1"]),
+                row(["changed", "2", "pear", "6"]),
+                row(["removed", "3", "plum", "7"])
+            ]
+        );
 
-    fn handle_worker_phase(&mut self, row: u32, col: u32, value: String) -> Result<()> {
-        if self.current_row != Some(row) {
-            self.current_batch_rows += 1;
-            self.current_row = Some(row);
-            self.total_rows += 1;
+        let options = ConvertExcelToParquetOptions::new(&input, &output);
+        assert!(convert_sheet_diff(&input, "Old", &input, "New", "sku", options).is_err());
 
-            if self.current_batch_rows >= self.batch_size {
-                self.send_batch()?;
+        // 只在一边出现的列不参与比较，另一边的行在这些列中为 null
+        write_workbook(
+            &input,
+            &[
+                (
+                    "Old",
+                    vec![
+                        vec!["id", "qty", "legacy"],
+                        vec!["1", "3", "x"],
+                        vec!["2", "5", "y"],
+                    ],
+                ),
+                (
+                    "New",
+                    vec![
+                        vec!["id", "qty", "note"],
+                        vec!["1", "3", "ok"],
+                        vec!["2", "6", "up"],
+                    ],
+                ),
+            ],
+        );
+        let report = convert_sheet_diff(
+            &input,
+            "Old",
+            &input,
+            "New",
+            "id",
+            ConvertExcelToParquetOptions::new(&input, &output),
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            SheetDiffReport {
+                added: 0,
+                removed: 0,
+                changed: 1,
+                unchanged: 1
             }
-        }
-        self.raw_cells_buffer.push((row, col, value));
-        Ok(())
+        );
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(
+            columns,
+            vec![DIFF_STATUS_COLUMN, "id", "qty", "legacy", "note"]
+        );
+        assert_eq!(
+            rows,
+            vec![vec![
+                Some("changed".to_string()),
+                Some("2".to_string()),
+                Some("6".to_string()),
+                None,
+                Some("up".to_string())
+            ]]
+        );
     }
 
-    fn send_batch(&mut self) -> Result<()> {
-        if let Some(tx) = &self.work_tx {
-            let buffer = std::mem::replace(
-                &mut self.raw_cells_buffer,
-                Vec::with_capacity(self.batch_size * self.num_cols),
-            );
-            if tx.send((self.batch_counter, buffer)).is_err() {
-                return Err(anyhow::anyhow!("Worker channel closed"));
-            }
-            self.batch_counter += 1;
-            self.current_batch_rows = 0;
+    #[test]
+    fn test_atomic_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("atomic.xlsx");
+        let output = dir.path().join("atomic.parquet");
+        let options = ConvertExcelToParquetOptions {
+            batch_size: Some(1),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let dimensions = calamine::Dimensions::new((0, 0), (3, 0));
+
+        // 写入线程已启动但转换中途被放弃：目标路径与临时文件都不应留下
+        let mut context = ConversionContext::new(&options, dimensions, None).unwrap();
+        for (row, value) in ["id", "1", "2"].into_iter().enumerate() {
+            context
+                .process_cell(row as u32, 0, value.into(), CellKind::String)
+                .unwrap();
         }
-        Ok(())
-    }
+        assert!(context.writer.is_some());
+        drop(context);
+        assert!(!output.exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
 
-    fn finish(&mut self) -> Result<()> {
-        // Send remaining
-        if !self.raw_cells_buffer.is_empty() {
-            self.send_batch()?;
+        let mut context = ConversionContext::new(&options, dimensions, None).unwrap();
+        for (row, value) in ["id", "1", "2"].into_iter().enumerate() {
+            context
+                .process_cell(row as u32, 0, value.into(), CellKind::String)
+                .unwrap();
         }
+        context.finish("Sheet1".into()).unwrap();
+        let (_, rows) = read_parquet(&output);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
 
-        // Drop work_tx to signal workers to stop
-        self.work_tx = None;
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use std::sync::Mutex;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
 
-        // Wait for workers
-        for handle in self.worker_threads.drain(..) {
-            let _ = handle.join();
+        /// 记录所有 span 名称与事件数量的测试 subscriber
+        #[derive(Default)]
+        struct Capture {
+            next_id: AtomicU64,
+            spans: Arc<Mutex<Vec<String>>>,
+            events: Arc<AtomicU64>,
         }
 
-        // Drop our result_tx copy so the writer knows when all workers are done
-        self.result_tx = None;
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+                self.spans
+                    .lock()
+                    .unwrap()
+                    .push(attrs.metadata().name().to_string());
+                Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {
+                self.events.fetch_add(1, Ordering::Relaxed);
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("traced.xlsx");
+        let output = dir.path().join("traced.parquet");
+        write_workbook(
+            &input,
+            &[("Data", vec![vec!["id"], vec!["1"], vec!["2"], vec!["3"]])],
+        );
 
-        if let Some(handle) = self.writer_thread.take() {
-            handle.join().unwrap()?;
+        let capture = Capture::default();
+        let spans = capture.spans.clone();
+        let events = capture.events.clone();
+        tracing::subscriber::with_default(capture, || {
+            let options = ConvertExcelToParquetOptions {
+                batch_size: Some(2),
+                ..ConvertExcelToParquetOptions::new(&input, &output)
+            };
+            convert_xlsx_to_parquet(options).unwrap();
+        });
+
+        // 工作线程与写入线程中的 span 也应送达调用方的 subscriber
+        let spans = spans.lock().unwrap();
+        for name in ["open", "convert_sheet", "header", "batch", "writer_close"] {
+            assert!(spans.iter().any(|s| s == name), "missing span {}", name);
         }
-        Ok(())
+        assert!(events.load(Ordering::Relaxed) > 0);
     }
-}
 
-/// 将单元格值转为字符串
-fn cell_to_string(cell: &calamine::DataRef) -> String {
-    match cell {
-        calamine::DataRef::Int(i) => i.to_string(),
-        calamine::DataRef::Float(f) => f.to_string(),
-        calamine::DataRef::String(s) => s.clone(),
-        calamine::DataRef::SharedString(s) => s.to_string(),
-        calamine::DataRef::Bool(b) => b.to_string(),
-        calamine::DataRef::DateTime(dt) => dt.to_string(),
-        calamine::DataRef::DurationIso(d) => d.to_string(),
-        calamine::DataRef::DateTimeIso(dt) => dt.to_string(),
-        calamine::DataRef::Error(e) => format!("{:?}", e),
-        calamine::DataRef::Empty => String::new(),
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_columns_require_key() {
+        use parquet::arrow::arrow_reader::ArrowReaderOptions;
+        use parquet::encryption::decrypt::FileDecryptionProperties;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("staff.xlsx");
+        let output = dir.path().join("staff.parquet");
+        write_workbook(
+            &input,
+            &[(
+                "Staff",
+                vec![vec!["name", "ssn"], vec!["alice", "123-45-6789"]],
+            )],
+        );
+
+        let footer_key = b"0123456789012345".to_vec();
+        let ssn_key = b"1234567890123450".to_vec();
+        let options = ConvertExcelToParquetOptions {
+            encryption: Some(ParquetEncryption {
+                footer_key: footer_key.clone(),
+                encrypt_columns: HashMap::from([("ssn".to_string(), ssn_key.clone())]),
+            }),
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        convert_xlsx_to_parquet(options).unwrap();
+
+        // 没有密钥无法读取
+        let file = File::open(&output).unwrap();
+        assert!(ParquetRecordBatchReaderBuilder::try_new(file).is_err());
+
+        let decryption = FileDecryptionProperties::builder(footer_key)
+            .with_column_key("ssn", ssn_key)
+            .build()
+            .unwrap();
+        let reader_options = ArrowReaderOptions::new().with_file_decryption_properties(decryption);
+        let file = File::open(&output).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new_with_options(file, reader_options)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        let ssn = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(ssn.value(0), "123-45-6789");
     }
-}
 
-// 新的 Worker 函数：从 RawCell 构建 RecordBatch
-fn create_record_batch_from_cells(
-    schema: &Arc<Schema>,
-    num_header_cols: usize,
-    cells: &[(u32, u32, String)],
-    start_col: u32,
-) -> Result<RecordBatch> {
-    let mut row_map: HashMap<u32, HashMap<u32, String>> = HashMap::new();
-    let mut row_indices: Vec<u32> = Vec::new();
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_convert_url() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
 
-    for (r, c, v) in cells {
-        let row_entry = row_map.entry(*r).or_insert_with(|| {
-            row_indices.push(*r);
-            HashMap::new()
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("remote.xlsx");
+        write_workbook(
+            &input,
+            &[("Data", vec![vec!["id", "name"], vec!["1", "alice"]])],
+        );
+        let body = std::fs::read(&input).unwrap();
+
+        // 简易 HTTP 服务：/old 重定向到 /book.xlsx，其它路径返回 404
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                }
+                let target = request_line.split_whitespace().nth(1).unwrap_or("");
+                let path = target.split('?').next().unwrap_or(target);
+                let (head, payload): (String, &[u8]) = match path {
+                    "/old.xlsx" => (
+                        "HTTP/1.1 302 Found\r\nLocation: /book.xlsx\r\n".to_string(),
+                        b"",
+                    ),
+                    "/book.xlsx" => ("HTTP/1.1 200 OK\r\n".to_string(), &body),
+                    _ => ("HTTP/1.1 404 Not Found\r\n".to_string(), b""),
+                };
+                let _ = write!(
+                    stream,
+                    "{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    head,
+                    payload.len()
+                );
+                let _ = stream.write_all(payload);
+            }
         });
-        row_entry.insert(*c, v.clone());
-    }
 
-    row_indices.sort_unstable();
+        let output = dir.path().join("remote.parquet");
+        let report = convert_url(
+            &format!("{}/old.xlsx?download=1", base),
+            ConvertExcelToParquetOptions::new(Path::new("remote.xlsx"), &output),
+        )
+        .unwrap();
+        assert_eq!(report.sheet_name, "Data");
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows, vec![vec![Some("1".into()), Some("alice".into())]]);
 
-    let mut columns: Vec<ArrayRef> = Vec::with_capacity(num_header_cols);
+        let err = convert_url(
+            &format!("{}/missing.xlsx", base),
+            ConvertExcelToParquetOptions::new(Path::new("missing.xlsx"), &output),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("HTTP 404"), "{}", err);
+    }
 
-    for i in 0..num_header_cols {
-        let target_col_idx = start_col + i as u32;
-        let mut col_values: Vec<Option<String>> = Vec::with_capacity(row_indices.len());
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_convert_zip() {
+        use zip::write::{SimpleFileOptions, ZipWriter};
 
-        for row_idx in &row_indices {
-            let val = row_map
-                .get(row_idx)
-                .and_then(|cols| cols.get(&target_col_idx).cloned());
-            col_values.push(val);
-        }
+        let dir = tempfile::tempdir().unwrap();
+        let write_zip = |path: &Path, entries: &[(&str, &str)]| {
+            let mut zip = ZipWriter::new(File::create(path).unwrap());
+            for (name, id) in entries {
+                let book = dir.path().join("book.xlsx");
+                write_workbook(&book, &[("Data", vec![vec!["id"], vec![id]])]);
+                zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+                zip.write_all(&std::fs::read(&book).unwrap()).unwrap();
+            }
+            zip.finish().unwrap();
+        };
+        let output = dir.path().join("zipped.parquet");
+        let options = || ConvertExcelToParquetOptions::new(Path::new("export.xlsx"), &output);
+
+        let single = dir.path().join("single.zip");
+        write_zip(&single, &[("readme.txt", ""), ("export/data.xlsx", "1")]);
+        convert_zip(&single, None, options()).unwrap();
+        assert_eq!(read_parquet(&output).1, vec![vec![Some("1".into())]]);
 
-        columns.push(Arc::new(StringArray::from(col_values)));
+        let multiple = dir.path().join("multiple.zip");
+        write_zip(
+            &multiple,
+            &[("2024-01.xlsx", "jan"), ("2024-02.xlsx", "feb")],
+        );
+        convert_zip(&multiple, None, options()).unwrap();
+        assert_eq!(read_parquet(&output).1, vec![vec![Some("jan".into())]]);
+        convert_zip(&multiple, Some("*-02.xlsx"), options()).unwrap();
+        assert_eq!(read_parquet(&output).1, vec![vec![Some("feb".into())]]);
+        assert!(convert_zip(&multiple, Some("*-03.xlsx"), options()).is_err());
     }
 
-    RecordBatch::try_new(schema.clone(), columns).context("Failed to create record batch")
-}
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_convert_html_table() {
+        // 旧系统以 .xls 为扩展名导出的 HTML 表格
+        let html = r#"<html xmlns:x="urn:schemas-microsoft-com:office:excel">
+<head><meta http-equiv="Content-Type" content="text/html; charset=utf-8">
+<style>.num { mso-number-format: "0.00"; }</style></head>
+<body>
+<table border="1" id="report">
+  <tr><th>Region</th><th>Product</th><th colspan="2">Q1</th></tr>
+  <tr><td rowspan="2">North</td><td>Widgets &amp; Co</td><td class="num">10.00</td><td>12</td></tr>
+  <tr><td>Gadgets</td><td class="num">&nbsp;</td><td>7</td></tr>
+  <tr><td>South</td><td><b>Widgets</b></td><td class="num">3.50</td><td>4</td></tr>
+</table>
+</body></html>"#;
+        // 同一份数据的 CSV 导出
+        let csv = "Region,Product,Q1,Q1_2\n\
+                   North,Widgets & Co,10.00,12\n\
+                   North,Gadgets,,7\n\
+                   South,Widgets,3.50,4";
+        let mut lines = csv.lines().map(|line| line.trim().split(','));
+        let expected_columns: Vec<String> = lines.next().unwrap().map(String::from).collect();
+        let expected_rows: Vec<Vec<Option<String>>> = lines
+            .map(|fields| {
+                fields
+                    .map(|field| (!field.is_empty()).then(|| field.to_string()))
+                    .collect()
+            })
+            .collect();
 
-fn build_headers(cells: &HashMap<u32, String>, num_cols: usize, start_col: u32) -> Vec<String> {
-    let mut headers: Vec<String> = (0..num_cols)
-        .map(|i| {
-            let col = start_col + i as u32;
-            cells.get(&col).cloned().unwrap_or_default()
-        })
-        .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("report.xls");
+        std::fs::write(&input, html).unwrap();
+        let output = dir.path().join("report.parquet");
+        let report = convert_html_table(
+            &input,
+            Some("report"),
+            ConvertExcelToParquetOptions::new(&input, &output),
+        )
+        .unwrap();
+        assert_eq!(report.rows_written, 3);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, expected_columns);
+        assert_eq!(rows, expected_rows);
 
-    for (i, header) in headers.iter_mut().enumerate() {
-        if header.is_empty() {
-            *header = format!("Field_{}", i);
-        }
+        assert!(
+            convert_html_table(
+                &input,
+                Some("missing"),
+                ConvertExcelToParquetOptions::new(&input, &output),
+            )
+            .is_err()
+        );
+
+        // 从内存读取
+        let report = convert_html_reader(
+            html.as_bytes(),
+            Some("report"),
+            ConvertExcelToParquetOptions::new(&input, &output),
+        )
+        .unwrap();
+        assert_eq!(report.rows_written, 3);
+        assert_eq!(read_parquet(&output).1, expected_rows);
+
+        // .xls 扩展名按内容识别为 HTML，按格式分派的入口同样可用
+        assert_eq!(ExcelFormat::from_path(&input).unwrap(), ExcelFormat::Html);
+        let profiles = profile_only(ConvertExcelToParquetOptions::new(&input, &output)).unwrap();
+        assert_eq!(profiles.len(), 4);
+        let (bytes, report) = convert_reader_to_bytes(
+            std::io::Cursor::new(format!("\u{feff}\n{}", html)),
+            ExcelFormat::Html,
+            ConvertExcelToParquetOptions::new(&input, &output),
+        )
+        .unwrap();
+        assert_eq!(report.rows_written, 3);
+        assert!(bytes.starts_with(b"PAR1"));
+        let binary = dir.path().join("binary.xls");
+        std::fs::write(&binary, b"\xD0\xCF\x11\xE0").unwrap();
+        assert!(ExcelFormat::from_path(&binary).is_err());
     }
 
-    let mut seen: HashMap<String, i32> = HashMap::new();
-    for i in 0..headers.len() {
-        let header = &headers[i];
-        let count = seen.entry(header.clone()).or_insert(0);
-        *count += 1;
-        if *count > 1 {
-            headers[i] = format!("{}_{}", header, count);
+    #[cfg(feature = "hidden")]
+    #[test]
+    fn test_skip_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("filtered.xlsx");
+        let output = dir.path().join("filtered.parquet");
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("Other").unwrap();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("Sales & Returns").unwrap();
+        let rows = [
+            ["region", "internal", "amount"],
+            ["north", "x", "10"],
+            ["south", "y", "20"],
+            ["east", "z", "30"],
+            ["west", "w", "40"],
+        ];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                worksheet.write_string(r as u32, c as u16, *value).unwrap();
+            }
         }
+        worksheet.set_column_hidden(1).unwrap();
+        worksheet.set_row_hidden(2).unwrap();
+        worksheet.set_row_hidden(3).unwrap();
+        workbook.save(&input).unwrap();
+
+        let options = ConvertExcelToParquetOptions {
+            sheet_name: Some("Sales & Returns".to_string()),
+            skip_hidden: true,
+            error_mode: ErrorMode::Collect { max_errors: 10 },
+            ..ConvertExcelToParquetOptions::new(&input, &output)
+        };
+        let report = convert_xlsx_to_parquet(options.clone()).unwrap();
+        assert_eq!(report.total_rows, 2);
+        assert_eq!(report.error_count, 0);
+        let (columns, rows) = read_parquet(&output);
+        assert_eq!(columns, vec!["region", "amount"]);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("north".into()), Some("10".into())],
+                vec![Some("west".into()), Some("40".into())],
+            ]
+        );
+
+        // 从内存读取时无法取得隐藏状态，返回错误而不是悄悄输出隐藏的行列
+        let err =
+            convert_reader_to_parquet(File::open(&input).unwrap(), ExcelFormat::Xlsx, options)
+                .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("skip_hidden needs the workbook file")
+        );
     }
-    headers
 }
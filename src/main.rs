@@ -1,13 +1,17 @@
 use clap::Parser;
+#[cfg(feature = "html")]
+use data_to_parquet::convert_html_table;
 use data_to_parquet::{
-    ConvertExcelToParquetOptions, convert_xlsb_to_parquet, convert_xlsx_to_parquet,
+    ConvertExcelToParquetOptions, ConvertPreset, DedupMode, DedupStrategy, ErrorMode, ExcelFormat,
+    FormulaMode, HeaderDetection, HeaderIndexBase, OutputColumnOrder, RepeatedHeader, SampleSpec,
+    SortOrder, TitleBand, convert_xlsb_to_parquet, convert_xlsx_to_parquet, validate_options,
 };
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Input Excel file path (.xlsx or .xlsb)
+    /// Input Excel file path (.xlsx or .xlsb; an HTML table saved as .xls with the html feature)
     #[arg(short, long)]
     input: PathBuf,
 
@@ -27,9 +31,260 @@ struct Args {
     #[arg(long, default_value_t = 0)]
     skip_rows: usize,
 
-    /// Batch size for processing
+    /// Number of data rows to drop at the end (e.g. total rows)
+    #[arg(long, default_value_t = 0)]
+    skip_trailing_rows: usize,
+
+    /// Drop formatted but empty rows after the last data row
+    #[arg(long)]
+    drop_trailing_blank_rows: bool,
+
+    /// Rows per batch / row group
     #[arg(long, default_value_t = 5000)]
     batch_size: usize,
+
+    /// Sort output rows by this column (buffers the whole sheet in memory)
+    #[arg(long)]
+    sort_by: Option<String>,
+
+    /// Sort in descending order (used with --sort-by)
+    #[arg(long, requires = "sort_by")]
+    descending: bool,
+
+    /// Only convert the sheet's defined print area
+    #[arg(long)]
+    respect_print_area: bool,
+
+    /// Only convert the area of this workbook-defined name (selects its sheet too)
+    #[arg(long, value_name = "NAME")]
+    named_range: Option<String>,
+
+    /// Trim the sheet to the bounding box of non-empty cells (requires an extra pass over the sheet)
+    #[arg(long)]
+    auto_trim: bool,
+
+    /// Collect up to N non-fatal errors and keep going instead of failing fast
+    #[arg(long, value_name = "N")]
+    collect_errors: Option<usize>,
+
+    /// Output formula text (e.g. =SUM(A1:A3)) instead of cached results
+    #[arg(long)]
+    formula_text: bool,
+
+    /// Round floating-point cells to N decimal places (e.g. 0.30000000000000004 -> 0.30 with 2)
+    #[arg(long, value_name = "N")]
+    float_precision: Option<usize>,
+
+    /// Text for boolean cells as TRUE,FALSE (e.g. 1,0 or Y,N)
+    #[arg(long, value_name = "TRUE,FALSE", value_parser = parse_bool_format)]
+    bool_format: Option<(String, String)>,
+
+    /// Write cells containing only whitespace as null
+    #[arg(long)]
+    whitespace_as_null: bool,
+
+    /// Fill empty cells in these columns with the last value above (comma-separated)
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    fill_down: Vec<String>,
+
+    /// Only output the columns from FIRST through LAST (header names, inclusive)
+    #[arg(long, value_name = "FIRST:LAST", value_parser = parse_column_window)]
+    column_window: Option<(String, String)>,
+
+    /// Drop columns that have no values (requires an extra pass over the sheet)
+    #[arg(long)]
+    skip_empty_columns: bool,
+
+    /// Treat headers differing only in case as duplicates (e.g. ID, id -> ID, id_2)
+    #[arg(long)]
+    case_insensitive_dedup: bool,
+
+    /// How to handle duplicate headers: numeric (id_2), numeric:BASE (numeric:1 gives id_1),
+    /// letter (id_b), error or keep-first
+    #[arg(long, value_name = "STRATEGY", value_parser = parse_dedup_strategy)]
+    dedup_headers: Option<DedupStrategy>,
+
+    /// The sheet has no header row; name every column Field_N
+    #[arg(long, conflicts_with = "guess_header")]
+    no_header: bool,
+
+    /// Guess from cell types whether the first row is a header
+    #[arg(long)]
+    guess_header: bool,
+
+    /// Name empty-header columns after their sheet column letter (Field_D) instead of their offset
+    #[arg(long)]
+    field_letters: bool,
+
+    /// Collect cells right of the last non-empty header into this column as a JSON array
+    #[arg(long, value_name = "NAME")]
+    overflow_column: Option<String>,
+
+    /// Add a constant column with this name holding the sheet name
+    #[arg(long, value_name = "NAME")]
+    sheet_column: Option<String>,
+
+    /// Add a constant column; VALUE may use {source_file}, {sheet} and {now} (repeatable)
+    #[arg(long = "constant", value_name = "NAME=VALUE", value_parser = parse_constant)]
+    constants: Vec<(String, String)>,
+
+    /// Add a constant column for each cell of the print title rows above the header
+    #[arg(long)]
+    print_title_columns: bool,
+
+    /// Add a <col>__type column holding each cell's original Excel type
+    #[arg(long)]
+    type_columns: bool,
+
+    /// Write directly to the output path instead of a temp file renamed on success
+    #[arg(long)]
+    in_place: bool,
+
+    /// Only keep rows where this column is truthy (not empty, 0 or false)
+    #[arg(long, value_name = "COLUMN")]
+    mask_column: Option<String>,
+
+    /// Keep the mask column in the output (used with --mask-column)
+    #[arg(long, requires = "mask_column")]
+    keep_mask_column: bool,
+
+    /// Write rows dropped by --mask-column to this Parquet file with a reject_reason column
+    #[arg(long, value_name = "PATH")]
+    rejects: Option<PathBuf>,
+
+    /// Skip data rows that repeat the header row (e.g. on every printed page)
+    #[arg(long)]
+    skip_repeated_headers: bool,
+
+    /// Skip section-title rows where only the first column has a value
+    #[arg(long)]
+    skip_title_bands: bool,
+
+    /// Keep only every Nth data row (the 1st, N+1th, ...)
+    #[arg(long, value_name = "N", conflicts_with = "sample_fraction")]
+    sample_every: Option<usize>,
+
+    /// Keep each data row with this probability (0 to 1)
+    #[arg(long, value_name = "F")]
+    sample_fraction: Option<f64>,
+
+    /// Seed for --sample-fraction, so the same rows are picked on every run
+    #[arg(long, value_name = "SEED", requires = "sample_fraction")]
+    sample_seed: Option<u64>,
+
+    /// Treat the row below the header as units, stored in field metadata instead of data
+    #[arg(long)]
+    units_row: bool,
+
+    /// Drop rows that exactly repeat an earlier row
+    #[arg(long, conflicts_with = "dedup_by")]
+    deduplicate: bool,
+
+    /// Keep only the first row for each combination of these columns (comma-separated)
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    dedup_by: Option<Vec<String>>,
+
+    /// Sort output columns alphabetically so sheets with the same columns share one schema
+    #[arg(long, conflicts_with = "column_order")]
+    sort_columns: bool,
+
+    /// Put these columns first in this order, followed by the rest (comma-separated)
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    column_order: Option<Vec<String>>,
+
+    /// Writer preset: fast-write (Snappy, no dictionary), small-file (ZSTD 19) or compatibility
+    #[arg(long, value_name = "PRESET", value_parser = parse_preset)]
+    preset: Option<ConvertPreset>,
+
+    /// Truncate string min/max statistics in the footer to this many bytes
+    #[arg(long, value_name = "BYTES")]
+    statistics_truncate_length: Option<usize>,
+
+    /// Re-read the written file and fail if its row count does not match
+    #[arg(long)]
+    verify: bool,
+
+    /// Check that every column of each batch has the expected number of rows
+    #[arg(long)]
+    strict_batches: bool,
+
+    /// Log uncompressed vs compressed size for each row group and time spent per phase
+    #[arg(long)]
+    stats: bool,
+
+    /// Process every batch on the main thread (deterministic, for debugging)
+    #[arg(long)]
+    single_threaded: bool,
+
+    /// Keep out-of-order batches in temp files instead of memory
+    #[arg(long)]
+    spill_to_disk: bool,
+
+    /// Buffer writes to the output file in chunks of this many bytes
+    #[arg(long, value_name = "BYTES")]
+    write_buffer: Option<usize>,
+
+    /// Stop early once the output reaches about this many bytes, keeping the rows written so far
+    #[arg(long, value_name = "BYTES")]
+    max_output_bytes: Option<usize>,
+
+    /// Only check the options for conflicts, without reading the input
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// 解析 `TRUE,FALSE` 形式的布尔输出文本
+fn parse_bool_format(arg: &str) -> Result<(String, String), String> {
+    arg.split_once(',')
+        .map(|(true_text, false_text)| (true_text.to_string(), false_text.to_string()))
+        .ok_or_else(|| format!("expected TRUE,FALSE, got '{}'", arg))
+}
+
+/// 解析 `FIRST:LAST` 形式的列窗口
+fn parse_column_window(arg: &str) -> Result<(String, String), String> {
+    arg.split_once(':')
+        .map(|(first, last)| (first.to_string(), last.to_string()))
+        .ok_or_else(|| format!("expected FIRST:LAST, got '{}'", arg))
+}
+
+/// 解析 `NAME=VALUE` 形式的常量列
+fn parse_constant(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", arg))
+}
+
+/// 解析 `--dedup-headers` 的取值
+fn parse_dedup_strategy(arg: &str) -> Result<DedupStrategy, String> {
+    match arg {
+        "numeric" => Ok(DedupStrategy::default()),
+        "letter" => Ok(DedupStrategy::LetterSuffix),
+        "error" => Ok(DedupStrategy::Error),
+        "keep-first" => Ok(DedupStrategy::KeepFirstDropRest),
+        _ => match arg.strip_prefix("numeric:") {
+            Some(base) => base
+                .parse()
+                .map(|base| DedupStrategy::NumericSuffix { base })
+                .map_err(|_| format!("invalid suffix base '{}'", base)),
+            None => Err(format!(
+                "expected numeric, numeric:BASE, letter, error or keep-first, got '{}'",
+                arg
+            )),
+        },
+    }
+}
+
+/// 解析 `--preset` 的取值
+fn parse_preset(arg: &str) -> Result<ConvertPreset, String> {
+    match arg {
+        "fast-write" => Ok(ConvertPreset::FastWrite),
+        "small-file" => Ok(ConvertPreset::SmallFile),
+        "compatibility" => Ok(ConvertPreset::Compatibility),
+        _ => Err(format!(
+            "expected fast-write, small-file or compatibility, got '{}'",
+            arg
+        )),
+    }
 }
 
 fn main() {
@@ -39,31 +294,144 @@ fn main() {
     let output_path = args.output.as_path();
 
     let options = ConvertExcelToParquetOptions {
-        excel_file: input_path,
-        output_path,
         skip_rows: args.skip_rows,
-        batch_size: args.batch_size,
+        skip_trailing_rows: args.skip_trailing_rows,
+        drop_trailing_blank_rows: args.drop_trailing_blank_rows,
+        batch_size: Some(args.batch_size),
         sheet_name: args.sheet_name,
         sheet_index: args.sheet_index,
+        named_range: args.named_range,
+        sort_by: args.sort_by.map(|column| {
+            let order = if args.descending {
+                SortOrder::Descending
+            } else {
+                SortOrder::Ascending
+            };
+            (column, order)
+        }),
+        respect_print_area: args.respect_print_area,
+        auto_trim_region: args.auto_trim,
+        error_mode: match args.collect_errors {
+            Some(max_errors) => ErrorMode::Collect { max_errors },
+            None => ErrorMode::FailFast,
+        },
+        formula_results: if args.formula_text {
+            FormulaMode::AsFormula
+        } else {
+            FormulaMode::CachedValue
+        },
+        float_precision: args.float_precision,
+        bool_string_format: args.bool_format,
+        whitespace_as_null: args.whitespace_as_null,
+        forward_fill_columns: args.fill_down,
+        column_window: args.column_window,
+        skip_empty_columns: args.skip_empty_columns,
+        type_sidecar_columns: args.type_columns,
+        header_detection: if args.no_header {
+            HeaderDetection::None
+        } else if args.guess_header {
+            HeaderDetection::Guess
+        } else {
+            HeaderDetection::FirstRow
+        },
+        overflow_column: args.overflow_column,
+        include_sheet_column: args.sheet_column,
+        constant_columns: args.constants.into_iter().collect(),
+        print_title_columns: args.print_title_columns,
+        case_insensitive_dedup: args.case_insensitive_dedup,
+        dedup_strategy: args.dedup_headers.unwrap_or_default(),
+        header_index_base: if args.field_letters {
+            HeaderIndexBase::ColumnLetter
+        } else {
+            HeaderIndexBase::RegionRelative
+        },
+        mask_column: args.mask_column,
+        keep_mask_column: args.keep_mask_column,
+        rejects_output: args.rejects,
+        repeated_header: args
+            .skip_repeated_headers
+            .then_some(RepeatedHeader::SameAsHeader),
+        skip_title_bands: args.skip_title_bands.then(TitleBand::default),
+        sample: args
+            .sample_every
+            .map(SampleSpec::SystematicEvery)
+            .or(args.sample_fraction.map(SampleSpec::RandomFraction)),
+        sample_seed: args.sample_seed,
+        units_row: args.units_row,
+        deduplicate: match args.dedup_by {
+            Some(columns) => Some(DedupMode::ByColumns(columns)),
+            None => args.deduplicate.then_some(DedupMode::FullRow),
+        },
+        output_column_order: match args.column_order {
+            Some(columns) => OutputColumnOrder::Custom(columns),
+            None if args.sort_columns => OutputColumnOrder::Sorted,
+            None => OutputColumnOrder::SheetOrder,
+        },
+        verify_output: args.verify,
+        strict_batch_validation: args.strict_batches,
+        collect_stats: args.stats,
+        preset: args.preset,
+        statistics_truncate_length: args.statistics_truncate_length,
+        atomic_output: !args.in_place,
+        single_threaded: args.single_threaded,
+        spill_to_disk: args.spill_to_disk,
+        write_buffer_size: args.write_buffer,
+        max_output_bytes: args.max_output_bytes,
+        ..ConvertExcelToParquetOptions::new(input_path, output_path)
     };
 
-    let extension = input_path
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase());
+    if args.dry_run {
+        match validate_options(&options) {
+            Ok(()) => println!("Options OK"),
+            Err(conflicts) => {
+                eprintln!("Error: {} option conflicts:", conflicts.len());
+                for conflict in &conflicts {
+                    eprintln!("  {}", conflict);
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let result = match extension.as_deref() {
-        Some("xlsx") => convert_xlsx_to_parquet(options),
-        Some("xlsb") => convert_xlsb_to_parquet(options),
-        _ => {
-            eprintln!("Error: Unsupported file extension. Please use .xlsx or .xlsb");
+    let result = match ExcelFormat::from_path(input_path) {
+        Ok(ExcelFormat::Xlsx) => convert_xlsx_to_parquet(options),
+        Ok(ExcelFormat::Xlsb) => convert_xlsb_to_parquet(options),
+        #[cfg(feature = "html")]
+        Ok(ExcelFormat::Html) => convert_html_table(input_path, None, options),
+        Err(e) => {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     };
 
-    if let Err(e) = result {
-        eprintln!("Error converting file: {}", e);
-        std::process::exit(1);
+    match result {
+        Ok(report) => {
+            if report.output_limit_reached {
+                eprintln!(
+                    "Warning: output size limit reached, wrote {} rows",
+                    report.rows_written
+                );
+            }
+            if report.error_count > 0 {
+                eprintln!(
+                    "Warning: {} non-fatal errors (showing {}):",
+                    report.error_count,
+                    report.errors.len()
+                );
+                for issue in &report.errors {
+                    match (issue.cell_address(), issue.row) {
+                        (Some(address), _) => eprintln!("  {}: {}", address, issue.message),
+                        (None, Some(row)) => eprintln!("  row {}: {}", row + 1, issue.message),
+                        (None, None) => eprintln!("  {}", issue.message),
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error converting file: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
@@ -82,14 +450,7 @@ mod tests {
             return;
         }
 
-        let options = ConvertExcelToParquetOptions {
-            excel_file: input,
-            output_path: output,
-            skip_rows: 0,
-            batch_size: 5000,
-            sheet_name: None,
-            sheet_index: None,
-        };
+        let options = ConvertExcelToParquetOptions::new(input, output);
 
         let result = convert_xlsb_to_parquet(options);
         assert!(result.is_ok(), "Failed to convert xlsb: {:?}", result.err());
@@ -105,14 +466,7 @@ mod tests {
             return;
         }
 
-        let options = ConvertExcelToParquetOptions {
-            excel_file: input,
-            output_path: output,
-            skip_rows: 0,
-            batch_size: 5000,
-            sheet_name: None,
-            sheet_index: None,
-        };
+        let options = ConvertExcelToParquetOptions::new(input, output);
 
         let result = convert_xlsx_to_parquet(options);
         assert!(result.is_ok(), "Failed to convert xlsx: {:?}", result.err());
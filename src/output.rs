@@ -0,0 +1,79 @@
+//! 输出文件的原子写入：先写临时文件，成功后再重命名到目标路径
+
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// 同目录下的临时输出文件，未提交即被丢弃时自动删除
+pub(crate) struct AtomicOutput {
+    temp_path: PathBuf,
+    target_path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicOutput {
+    /// 临时文件位于目标文件旁，命名为 `<文件名>.tmp-<pid>`
+    pub(crate) fn new(target_path: &Path) -> Self {
+        let file_name = target_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temp_path = target_path.with_file_name(format!("{}.tmp-{}", file_name, process_id()));
+        Self {
+            temp_path,
+            target_path: target_path.to_path_buf(),
+            committed: false,
+        }
+    }
+
+    pub(crate) fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    /// 将临时文件移动到目标路径；跨文件系统时退化为复制后删除
+    pub(crate) fn commit(mut self) -> Result<()> {
+        match std::fs::rename(&self.temp_path, &self.target_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+                std::fs::copy(&self.temp_path, &self.target_path).with_context(|| {
+                    format!(
+                        "Failed to copy {} to {} across filesystems",
+                        self.temp_path.display(),
+                        self.target_path.display()
+                    )
+                })?;
+                let _ = std::fs::remove_file(&self.temp_path);
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to rename {} to {}",
+                        self.temp_path.display(),
+                        self.target_path.display()
+                    )
+                });
+            }
+        }
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AtomicOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// 当前进程号，用于区分临时文件名；`wasm32` 目标上没有进程号，总是 0
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn process_id() -> u32 {
+    std::process::id()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn process_id() -> u32 {
+    0
+}
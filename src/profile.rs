@@ -0,0 +1,110 @@
+//! 列概况统计：在不写出 Parquet 的情况下汇总每列的 null 个数、最值与不同值个数
+
+use arrow::array::{Array, StringArray};
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// 单列的统计概况，由 [`crate::profile_only`] 生成
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    pub name: String,
+    /// 有值的单元格数，包括仅有格式的空单元格（空字符串）
+    pub non_null_count: usize,
+    pub null_count: usize,
+    /// 最小值与最大值，忽略空字符串。所有值都能解析为数字时按数值比较，否则按字符串比较
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// 不同值个数的估计：按 64 位哈希计数，哈希冲突可能使结果略微偏小
+    pub distinct_count: usize,
+}
+
+/// 单列统计的累加器，按批次依次更新
+pub(crate) struct ProfileAccumulator {
+    name: String,
+    non_null_count: usize,
+    null_count: usize,
+    all_numeric: bool,
+    numeric_min: Option<(f64, String)>,
+    numeric_max: Option<(f64, String)>,
+    min: Option<String>,
+    max: Option<String>,
+    hashes: HashSet<u64>,
+}
+
+impl ProfileAccumulator {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            non_null_count: 0,
+            null_count: 0,
+            all_numeric: true,
+            numeric_min: None,
+            numeric_max: None,
+            min: None,
+            max: None,
+            hashes: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn update(&mut self, column: &StringArray) {
+        self.null_count += column.null_count();
+        for value in column.iter().flatten() {
+            self.non_null_count += 1;
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            self.hashes.insert(hasher.finish());
+
+            if value.is_empty() {
+                continue;
+            }
+            if self.min.as_deref().is_none_or(|min| value < min) {
+                self.min = Some(value.to_string());
+            }
+            if self.max.as_deref().is_none_or(|max| value > max) {
+                self.max = Some(value.to_string());
+            }
+            if !self.all_numeric {
+                continue;
+            }
+            match value.parse::<f64>() {
+                Ok(number) if !number.is_nan() => {
+                    if self
+                        .numeric_min
+                        .as_ref()
+                        .is_none_or(|(min, _)| number < *min)
+                    {
+                        self.numeric_min = Some((number, value.to_string()));
+                    }
+                    if self
+                        .numeric_max
+                        .as_ref()
+                        .is_none_or(|(max, _)| number > *max)
+                    {
+                        self.numeric_max = Some((number, value.to_string()));
+                    }
+                }
+                _ => self.all_numeric = false,
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> ColumnProfile {
+        let (min, max) = if self.all_numeric {
+            (
+                self.numeric_min.map(|(_, value)| value),
+                self.numeric_max.map(|(_, value)| value),
+            )
+        } else {
+            (self.min, self.max)
+        };
+        ColumnProfile {
+            name: self.name,
+            non_null_count: self.non_null_count,
+            null_count: self.null_count,
+            min,
+            max,
+            distinct_count: self.hashes.len(),
+        }
+    }
+}
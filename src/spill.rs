@@ -0,0 +1,101 @@
+//! 写入线程乱序批次的磁盘溢出：以 Arrow IPC 文件暂存，轮到时再读回
+
+use anyhow::{Context, Result};
+use arrow::compute::concat_batches;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use std::fs::{DirBuilder, File};
+use std::hash::{BuildHasher, RandomState};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 溢出目录名冲突时的最大重试次数
+const CREATE_ATTEMPTS: usize = 16;
+
+/// 等待写出的乱序批次
+pub(crate) enum BufferedBatch {
+    Memory(RecordBatch),
+    /// 已写入溢出目录的 Arrow IPC 文件
+    Spilled(PathBuf),
+}
+
+impl BufferedBatch {
+    /// 取回批次，溢出的批次读回后删除其文件
+    pub(crate) fn load(self) -> Result<RecordBatch> {
+        let path = match self {
+            Self::Memory(batch) => return Ok(batch),
+            Self::Spilled(path) => path,
+        };
+        let file = File::open(&path).context("Failed to open spilled batch")?;
+        let reader = FileReader::try_new(file, None).context("Failed to read spilled batch")?;
+        let schema = reader.schema();
+        let batches = reader
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read spilled batch")?;
+        let _ = std::fs::remove_file(&path);
+        concat_batches(&schema, &batches).context("Failed to read spilled batch")
+    }
+}
+
+/// 系统临时目录下的溢出目录 `data-to-parquet-spill-<pid>-<随机后缀>`，丢弃时连同其中的文件一起删除
+pub(crate) struct SpillDir {
+    path: PathBuf,
+}
+
+impl SpillDir {
+    /// 新建溢出目录。目录名带随机后缀，且只接受由本次调用新建的目录（Unix 上仅所有者可访问），
+    /// 不会写入他人预先创建的同名目录或符号链接
+    pub(crate) fn create() -> Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let mut builder = DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        let mut attempts = 0;
+        loop {
+            // RandomState 的键由系统随机数初始化，每次调用都会变化
+            let suffix = RandomState::new().hash_one(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+            let path = std::env::temp_dir().join(format!(
+                "data-to-parquet-spill-{}-{:016x}",
+                crate::output::process_id(),
+                suffix
+            ));
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(err)
+                    if err.kind() == ErrorKind::AlreadyExists && attempts < CREATE_ATTEMPTS =>
+                {
+                    attempts += 1;
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("Failed to create spill directory {}", path.display())
+                    });
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// 将编号为 `id` 的批次写入溢出目录
+    pub(crate) fn spill(&self, id: usize, batch: &RecordBatch) -> Result<BufferedBatch> {
+        let path = self.path.join(format!("batch-{}.arrow", id));
+        let file = File::create(&path).context("Failed to create spill file")?;
+        let mut writer =
+            FileWriter::try_new(file, &batch.schema()).context("Failed to spill batch")?;
+        writer.write(batch).context("Failed to spill batch")?;
+        writer.finish().context("Failed to spill batch")?;
+        Ok(BufferedBatch::Spilled(path))
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
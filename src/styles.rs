@@ -0,0 +1,170 @@
+//! 按单元格样式识别加粗的表头行（需启用 `styles` feature）
+//!
+//! calamine 不提供单元格样式，这里从 `xl/styles.xml` 读出加粗的字体（`<fonts>` 中含 `<b/>` 的 `<font>`）
+//! 与各单元格格式使用的字体（`<cellXfs>` 中 `<xf>` 的 `fontId`），再扫描 Sheet 开头若干行单元格的 `s` 属性。
+
+use crate::xlsx_xml::{attribute, local_name, scan_tags, sheet_part};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// 从起始行开始最多检查的行数
+pub(crate) const SCAN_ROWS: u32 = 10;
+
+/// 在 `path` 中名为 `sheet_name` 的 Sheet 的 `first_row..first_row + SCAN_ROWS` 行（0 起始）中，
+/// 找第一个非空单元格过半加粗的行。工作簿没有样式时为 `None`
+pub(crate) fn find_bold_row(path: &Path, sheet_name: &str, first_row: u32) -> Result<Option<u32>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = ZipArchive::new(file).context("Failed to read workbook archive")?;
+    if archive.index_for_name("xl/styles.xml").is_none() {
+        return Ok(None);
+    }
+    let bold = bold_formats(BufReader::new(archive.by_name("xl/styles.xml")?))?;
+    let part = sheet_part(&mut archive, sheet_name)?;
+    let entry = archive
+        .by_name(&part)
+        .with_context(|| format!("Worksheet part '{}' not found", part))?;
+    scan_bold_rows(BufReader::new(entry), &bold, first_row)
+}
+
+/// 各单元格格式（`<cellXfs>` 中 `<xf>` 的下标，即单元格的 `s` 属性）是否使用加粗字体
+fn bold_formats(reader: impl BufRead) -> Result<Vec<bool>> {
+    let mut fonts = Vec::new();
+    let mut font_ids = Vec::new();
+    // 当前所在的 `fonts` 或 `cellXfs` 段，`dxfs`、`cellStyleXfs` 中的同名标签不计入
+    let mut section = None;
+    scan_tags(reader, |tag| {
+        if let Some(closing) = tag.strip_prefix('/') {
+            if section == Some(local_name(closing)) {
+                section = None;
+            }
+            return Ok(true);
+        }
+        match (section, local_name(tag)) {
+            (_, "fonts") if !tag.ends_with('/') => section = Some("fonts"),
+            (_, "cellXfs") if !tag.ends_with('/') => section = Some("cellXfs"),
+            (Some("fonts"), "font") => fonts.push(false),
+            (Some("fonts"), "b") => {
+                if let Some(bold) = fonts.last_mut() {
+                    *bold = !matches!(attribute(tag, "val"), Some("0" | "false"));
+                }
+            }
+            (Some("cellXfs"), "xf") => {
+                font_ids.push(attribute(tag, "fontId").and_then(|id| id.parse::<usize>().ok()));
+            }
+            _ => {}
+        }
+        Ok(true)
+    })?;
+    Ok(font_ids
+        .into_iter()
+        .map(|id| id.is_some_and(|id| fonts.get(id).copied().unwrap_or(false)))
+        .collect())
+}
+
+/// 扫描 Sheet 的 XML，返回 `first_row` 起第一个非空单元格过半使用加粗格式的行
+fn scan_bold_rows(reader: impl BufRead, bold: &[bool], first_row: u32) -> Result<Option<u32>> {
+    // 上一个 `<row>` 的行号（1 起始），省略 `r` 属性时行号顺延
+    let mut last_row = 0u32;
+    // 当前行的（加粗的单元格数, 非空单元格数）
+    let mut counts = (0usize, 0usize);
+    // 当前单元格是否加粗、是否有值
+    let mut cell = None;
+    let mut found = None;
+    let is_bold_row = |row: u32, (bold, populated): (usize, usize)| {
+        row > first_row && populated > 0 && bold * 2 > populated
+    };
+    scan_tags(reader, |tag| {
+        if let Some(closing) = tag.strip_prefix('/') {
+            match local_name(closing) {
+                "c" => {
+                    if let Some((bold, true)) = cell.take() {
+                        counts.0 += bold as usize;
+                        counts.1 += 1;
+                    }
+                }
+                "sheetData" => return Ok(false),
+                _ => {}
+            }
+            return Ok(true);
+        }
+        match local_name(tag) {
+            "row" => {
+                if is_bold_row(last_row, counts) {
+                    found = Some(last_row - 1);
+                    return Ok(false);
+                }
+                last_row = match attribute(tag, "r") {
+                    Some(r) => r.parse().context("Invalid row number in worksheet")?,
+                    None => last_row + 1,
+                };
+                counts = (0, 0);
+                if last_row > first_row + SCAN_ROWS {
+                    return Ok(false);
+                }
+            }
+            "c" if !tag.ends_with('/') => {
+                let format = attribute(tag, "s").map_or(Ok(0), str::parse::<usize>);
+                let format = format.context("Invalid cell style in worksheet")?;
+                cell = Some((bold.get(format).copied().unwrap_or(false), false));
+            }
+            "v" | "is" => {
+                if let Some((_, has_value)) = &mut cell {
+                    *has_value = true;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    })?;
+    if found.is_none() && last_row <= first_row + SCAN_ROWS && is_bold_row(last_row, counts) {
+        found = Some(last_row - 1);
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bold_formats() {
+        let xml = r#"<?xml version="1.0"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+  <fonts count="3">
+    <font><sz val="11"/><name val="Calibri"/></font>
+    <font><b/><sz val="11"/></font>
+    <font><b val="0"/></font>
+  </fonts>
+  <cellStyleXfs count="1"><xf numFmtId="0" fontId="1"/></cellStyleXfs>
+  <cellXfs count="4">
+    <xf numFmtId="0" fontId="0" xfId="0"/>
+    <xf numFmtId="0" fontId="1" xfId="0" applyFont="1"/>
+    <xf numFmtId="14" fontId="2" xfId="0"><alignment horizontal="left"/></xf>
+    <xf numFmtId="0" fontId="9"/>
+  </cellXfs>
+  <dxfs count="1"><dxf><font><b/></font></dxf></dxfs>
+</styleSheet>"#;
+        assert_eq!(
+            bold_formats(xml.as_bytes()).unwrap(),
+            vec![false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn test_scan_bold_rows() {
+        let xml = r#"<worksheet><sheetData>
+<row r="1"><c r="A1" s="1" t="s"><v>0</v></c></row>
+<row r="3"><c r="A3" s="1" t="s"><v>1</v></c><c r="B3" s="1" t="inlineStr"><is><t>b</t></is></c><c r="C3"><v>2</v></c><c r="D3" s="1"/></row>
+<row r="4"><c r="A4"><v>1</v></c></row>
+</sheetData></worksheet>"#;
+        let bold = [false, true];
+        // 第 1 行只有一个加粗的单元格也算过半；从第 2 行起找到第 3 行（2 对 1，空单元格不计）
+        assert_eq!(scan_bold_rows(xml.as_bytes(), &bold, 0).unwrap(), Some(0));
+        assert_eq!(scan_bold_rows(xml.as_bytes(), &bold, 1).unwrap(), Some(2));
+        assert_eq!(scan_bold_rows(xml.as_bytes(), &bold, 3).unwrap(), None);
+        assert_eq!(scan_bold_rows(xml.as_bytes(), &[false], 0).unwrap(), None);
+    }
+}
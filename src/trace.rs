@@ -0,0 +1,85 @@
+//! 日志与 tracing 的统一出口
+//!
+//! 启用 `tracing` feature 时输出结构化的 span 与事件，否则回退到标准输出，
+//! span 相关的宏与类型均为空操作。
+
+#[cfg(feature = "tracing")]
+macro_rules! info {
+    ($($arg:tt)+) => { tracing::info!($($arg)+) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! info {
+    ($($arg:tt)+) => { println!($($arg)+) };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! warn {
+    ($($arg:tt)+) => { tracing::warn!($($arg)+) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! warn {
+    ($($arg:tt)+) => { eprintln!("Warning: {}", format_args!($($arg)+)) };
+}
+
+/// 创建并进入一个 info 级别的 span，返回的守卫离开作用域时退出
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($($arg:tt)+) => { tracing::info_span!($($arg)+).entered() };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($($arg:tt)+) => {
+        ()
+    };
+}
+
+/// 调用方的 subscriber 与当前 span，用于在工作线程中延续同一条链路
+#[cfg(feature = "tracing")]
+#[derive(Clone)]
+pub(crate) struct TraceContext {
+    dispatch: tracing::Dispatch,
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl TraceContext {
+    pub(crate) fn current() -> Self {
+        Self {
+            dispatch: tracing::dispatcher::get_default(|dispatch| dispatch.clone()),
+            span: tracing::Span::current(),
+        }
+    }
+
+    /// 在当前线程上恢复调用方的 subscriber 并进入父 span
+    pub(crate) fn attach(
+        &self,
+    ) -> (
+        tracing::dispatcher::DefaultGuard,
+        tracing::span::EnteredSpan,
+    ) {
+        let guard = tracing::dispatcher::set_default(&self.dispatch);
+        (guard, self.span.clone().entered())
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone)]
+pub(crate) struct TraceContext;
+
+#[cfg(not(feature = "tracing"))]
+impl TraceContext {
+    pub(crate) fn current() -> Self {
+        Self
+    }
+
+    pub(crate) fn attach(&self) -> TraceGuard {
+        TraceGuard
+    }
+}
+
+/// 未启用 `tracing` 时 `attach` 返回的空守卫
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct TraceGuard;
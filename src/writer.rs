@@ -0,0 +1,734 @@
+//! 写入线程：按批次编号顺序写出 Parquet（或统计列概况），供转换与外部代码共用
+
+use crate::output::AtomicOutput;
+use crate::profile::{ColumnProfile, ProfileAccumulator};
+use crate::spill::{BufferedBatch, SpillDir};
+use crate::trace::TraceContext;
+use crate::{
+    MemoryPressureCallback, ProcessedBatch, RowGroupStats, SortOrder, default_writer_properties,
+    sort_record_batch, start_timer,
+};
+use anyhow::{Context, Result};
+use arrow::array::{Array, AsArray, BooleanArray, StringArray};
+use arrow::compute::{concat_batches, filter_record_batch};
+use arrow::datatypes::SchemaRef;
+use arrow::json::LineDelimitedWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_writer::ArrowWriter;
+use parquet::file::metadata::SortingColumn;
+use parquet::file::properties::WriterProperties;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+/// 可在线程间共享的内存输出缓冲区，用于不落盘的转换
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// 取出已写入的全部字节
+    pub(crate) fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 可在线程间共享的批次收集器，用于在内存中取得转换结果
+#[derive(Clone, Default)]
+pub(crate) struct SharedBatches(Arc<Mutex<Vec<RecordBatch>>>);
+
+impl SharedBatches {
+    /// 取出已收集的全部批次
+    pub(crate) fn take(&self) -> Vec<RecordBatch> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    pub(crate) fn push(&self, batch: RecordBatch) {
+        self.0.lock().unwrap().push(batch);
+    }
+}
+
+/// 写入线程按顺序接收批次的目标
+pub(crate) enum BatchSink {
+    Parquet(Box<ArrowWriter<Box<dyn Write + Send>>>),
+    /// 排序输出时推迟创建的 Parquet 写入器：要等排序完成才知道能否在 footer 中记录排序列
+    DeferredParquet(Option<Box<DeferredParquet>>),
+    Profile(Vec<ProfileAccumulator>),
+    Batches(SharedBatches),
+    /// 按列拆分的多个文件，每个批次投影后分别写入
+    ColumnSplit(Vec<ColumnPart>),
+    /// 每行一个 JSON 对象，null 值也写出键
+    Ndjson(Box<LineDelimitedWriter<Box<dyn Write + Send>>>),
+}
+
+/// 尚未创建写入器的 Parquet 输出
+pub(crate) struct DeferredParquet {
+    pub(crate) file: Box<dyn Write + Send>,
+    pub(crate) schema: SchemaRef,
+    pub(crate) props: WriterProperties,
+}
+
+/// 按列拆分时的一个输出文件
+pub(crate) struct ColumnPart {
+    /// 写入该文件的列（批次 schema 中的下标）
+    pub(crate) columns: Vec<usize>,
+    pub(crate) writer: ArrowWriter<Box<dyn Write + Send>>,
+}
+
+/// 写入线程划分 row group 的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum RowGroupSplit {
+    /// 由写入属性的 `max_row_group_size` 决定
+    #[default]
+    BySize,
+    /// 每个批次单独成为一个 row group
+    PerBatch,
+    /// 同 `PerBatch`，但最后一个批次少于给定行数时并入前一个 row group
+    PerBatchCoalescing(usize),
+}
+
+/// 写入线程结束时的产出
+#[derive(Default)]
+pub(crate) struct WriterOutput {
+    pub(crate) row_group_stats: Vec<RowGroupStats>,
+    pub(crate) column_profiles: Vec<ColumnProfile>,
+    /// 结束时仍在等待的批次编号，以及因此未写出的批次数
+    pub(crate) next_batch_id: usize,
+    pub(crate) unwritten_batches: usize,
+    /// 排序、编码压缩与写出所用的时间，未调用 `with_timing` 时为零
+    pub(crate) write_time: Duration,
+    /// 去重时丢弃的重复行数
+    pub(crate) duplicate_rows: usize,
+    /// 写出的行数，以及是否因达到字节上限而丢弃了之后的批次
+    pub(crate) rows_written: usize,
+    pub(crate) byte_limit_reached: bool,
+    /// 因内存压力提前结束 row group 的次数
+    pub(crate) memory_pressure_flushes: usize,
+}
+
+impl BatchSink {
+    /// 创建推迟的 Parquet 写入器，`sorting_columns` 记录到每个 row group 的元数据中
+    fn start_deferred(&mut self, sorting_columns: Option<Vec<SortingColumn>>) -> Result<()> {
+        let Self::DeferredParquet(deferred) = self else {
+            return Ok(());
+        };
+        let DeferredParquet {
+            file,
+            schema,
+            props,
+        } = *deferred.take().unwrap();
+        let props = props
+            .into_builder()
+            .set_sorting_columns(sorting_columns)
+            .build();
+        let writer = ArrowWriter::try_new(file, schema, Some(props))
+            .context("Failed to create parquet writer")?;
+        *self = Self::Parquet(Box::new(writer));
+        Ok(())
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        match self {
+            Self::Parquet(writer) => writer.write(batch)?,
+            Self::DeferredParquet(_) => {
+                self.start_deferred(None)?;
+                self.write(batch)?;
+            }
+            Self::Profile(columns) => {
+                for (column, array) in columns.iter_mut().zip(batch.columns()) {
+                    column.update(array.as_string::<i32>());
+                }
+            }
+            Self::Batches(batches) => batches.push(batch.clone()),
+            Self::ColumnSplit(parts) => {
+                for part in parts {
+                    part.writer.write(&batch.project(&part.columns)?)?;
+                }
+            }
+            Self::Ndjson(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    /// 结束当前 row group
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            Self::Parquet(writer) => writer.flush().context("Failed to flush row group")?,
+            Self::ColumnSplit(parts) => {
+                for part in parts {
+                    part.writer.flush().context("Failed to flush row group")?;
+                }
+            }
+            Self::DeferredParquet(_) | Self::Profile(_) | Self::Batches(_) | Self::Ndjson(_) => {}
+        }
+        Ok(())
+    }
+
+    /// 已写入输出的字节数（只含已结束的 row group，不含页索引与 footer）与这些 row group 的行数。
+    /// 按列拆分时字节数为各文件之和
+    fn flushed(&self) -> (usize, usize) {
+        let flushed_rows = |writer: &ArrowWriter<Box<dyn Write + Send>>| -> usize {
+            writer
+                .flushed_row_groups()
+                .iter()
+                .map(|row_group| row_group.num_rows() as usize)
+                .sum()
+        };
+        match self {
+            Self::Parquet(writer) => (writer.bytes_written(), flushed_rows(writer)),
+            Self::ColumnSplit(parts) => (
+                parts.iter().map(|part| part.writer.bytes_written()).sum(),
+                parts.first().map_or(0, |part| flushed_rows(&part.writer)),
+            ),
+            Self::DeferredParquet(_) | Self::Profile(_) | Self::Batches(_) | Self::Ndjson(_) => {
+                (0, 0)
+            }
+        }
+    }
+
+    /// 当前 row group 中尚未写出的行数
+    fn in_progress_rows(&self) -> usize {
+        match self {
+            Self::Parquet(writer) => writer.in_progress_rows(),
+            Self::ColumnSplit(parts) => parts
+                .first()
+                .map_or(0, |part| part.writer.in_progress_rows()),
+            Self::DeferredParquet(_) | Self::Profile(_) | Self::Batches(_) | Self::Ndjson(_) => 0,
+        }
+    }
+
+    /// 按 `split` 写入一个批次并在需要时结束 row group，`tail` 表示这是最后一个批次
+    fn write_batch(&mut self, batch: &RecordBatch, split: RowGroupSplit, tail: bool) -> Result<()> {
+        match split {
+            RowGroupSplit::BySize => self.write(batch),
+            RowGroupSplit::PerBatch => {
+                self.write(batch)?;
+                self.flush()
+            }
+            // 推迟到下一个批次到来时才结束 row group，过小的最后一个批次因此可以并入当前 row group
+            RowGroupSplit::PerBatchCoalescing(min_rows) => {
+                if !tail || batch.num_rows() >= min_rows {
+                    self.flush()?;
+                }
+                self.write(batch)
+            }
+        }
+    }
+
+    fn close(mut self) -> Result<WriterOutput> {
+        self.start_deferred(None)?;
+        match self {
+            Self::Parquet(writer) => {
+                let metadata = writer.close()?;
+                Ok(WriterOutput {
+                    row_group_stats: metadata
+                        .row_groups()
+                        .iter()
+                        .map(|row_group| RowGroupStats {
+                            num_rows: row_group.num_rows() as usize,
+                            uncompressed_bytes: row_group.total_byte_size() as u64,
+                            compressed_bytes: row_group.compressed_size() as u64,
+                        })
+                        .collect(),
+                    ..WriterOutput::default()
+                })
+            }
+            Self::DeferredParquet(_) => unreachable!("deferred writer was started"),
+            Self::Profile(columns) => Ok(WriterOutput {
+                column_profiles: columns
+                    .into_iter()
+                    .map(ProfileAccumulator::finish)
+                    .collect(),
+                ..WriterOutput::default()
+            }),
+            Self::Batches(_) => Ok(WriterOutput::default()),
+            Self::Ndjson(mut writer) => {
+                writer.finish()?;
+                writer
+                    .into_inner()
+                    .flush()
+                    .context("Failed to flush NDJSON output")?;
+                Ok(WriterOutput::default())
+            }
+            // 各文件的 row group 划分相同，统计按 row group 合计
+            Self::ColumnSplit(parts) => {
+                let mut row_group_stats: Vec<RowGroupStats> = Vec::new();
+                for part in parts {
+                    let metadata = part.writer.close()?;
+                    for (i, row_group) in metadata.row_groups().iter().enumerate() {
+                        if i == row_group_stats.len() {
+                            row_group_stats.push(RowGroupStats {
+                                num_rows: row_group.num_rows() as usize,
+                                uncompressed_bytes: 0,
+                                compressed_bytes: 0,
+                            });
+                        }
+                        row_group_stats[i].uncompressed_bytes += row_group.total_byte_size() as u64;
+                        row_group_stats[i].compressed_bytes += row_group.compressed_size() as u64;
+                    }
+                }
+                Ok(WriterOutput {
+                    row_group_stats,
+                    ..WriterOutput::default()
+                })
+            }
+        }
+    }
+}
+
+/// 行去重：记录已写出行在键列上的取值，丢弃键已出现过的行
+///
+/// 批次按编号顺序经过去重，因此保留的总是文件中靠前的行。已见过的键全部保存在内存中。
+struct RowDeduper {
+    columns: Vec<usize>,
+    seen: HashSet<Vec<Option<String>>>,
+    removed: usize,
+}
+
+impl RowDeduper {
+    fn filter(&mut self, batch: RecordBatch) -> Result<RecordBatch> {
+        let arrays: Vec<&StringArray> = self
+            .columns
+            .iter()
+            .map(|&column| batch.column(column).as_string::<i32>())
+            .collect();
+        let keep: BooleanArray = (0..batch.num_rows())
+            .map(|row| {
+                let key = arrays
+                    .iter()
+                    .map(|array| array.is_valid(row).then(|| array.value(row).to_string()))
+                    .collect();
+                Some(self.seen.insert(key))
+            })
+            .collect();
+        let kept = keep.true_count();
+        if kept == batch.num_rows() {
+            return Ok(batch);
+        }
+        self.removed += batch.num_rows() - kept;
+        filter_record_batch(&batch, &keep).context("Failed to remove duplicate rows")
+    }
+}
+
+/// 输出字节上限：预计写入下一个批次后超出上限时丢弃该批次及之后的所有批次
+struct ByteLimit {
+    max_bytes: usize,
+    /// 达到上限时置位，通知读取线程停止读取
+    reached: Arc<AtomicBool>,
+}
+
+impl ByteLimit {
+    /// 按已写出 row group 的平均每行字节数，预计写入 `rows` 行后输出是否超出上限。
+    /// 还没有结束任何 row group 时无从估计，总是允许写入
+    fn exceeded_by(&self, sink: &BatchSink, rows: usize) -> bool {
+        let (bytes, flushed_rows) = sink.flushed();
+        let pending_rows = sink.in_progress_rows() + rows;
+        flushed_rows > 0 && bytes + (bytes * pending_rows).div_ceil(flushed_rows) > self.max_bytes
+    }
+}
+
+/// 按批次编号顺序写出批次：乱序到达的批次暂存，按编号从 0 开始依次写出
+///
+/// 设置 `sort_by` 时收集全部批次，结束时排序后一次写出；`split` 决定批次与 row group 的对应关系。
+/// 启用溢出时乱序批次写入临时目录而不是留在内存中。设置字节上限后，达到上限之后的批次都被丢弃。
+/// 设置内存压力回调后，每收到一个批次检查一次，内存紧张时提前结束 row group 并开始溢出。
+/// 额外的输出（`fan_out`）按同样的顺序与 row group 划分收到每个写出的批次，字节上限只按主输出计算。
+pub(crate) struct OrderedWriter {
+    sink: BatchSink,
+    fan_out: Vec<BatchSink>,
+    schema: SchemaRef,
+    sort_by: Option<(usize, SortOrder)>,
+    split: RowGroupSplit,
+    buffer: HashMap<usize, BufferedBatch>,
+    spill: Option<SpillDir>,
+    next_expected_id: usize,
+    total_written_rows: usize,
+    // 排序模式下按顺序收集所有批次，结束时统一排序写入
+    sort_buffer: Option<Vec<RecordBatch>>,
+    // `PerBatchCoalescing` 下暂存最近的一个批次，直到确认它是否为最后一个
+    held_tail: Option<RecordBatch>,
+    // 只在 `timed` 时累计
+    timed: bool,
+    dedup: Option<RowDeduper>,
+    byte_limit: Option<ByteLimit>,
+    memory_pressure: Option<MemoryPressureCallback>,
+    pressure_flushes: usize,
+    write_time: Duration,
+}
+
+impl OrderedWriter {
+    pub(crate) fn new(
+        sink: BatchSink,
+        schema: SchemaRef,
+        sort_by: Option<(usize, SortOrder)>,
+        split: RowGroupSplit,
+    ) -> Self {
+        Self {
+            sink,
+            fan_out: Vec::new(),
+            schema,
+            sort_by,
+            split,
+            buffer: HashMap::new(),
+            spill: None,
+            next_expected_id: 0,
+            total_written_rows: 0,
+            sort_buffer: sort_by.map(|_| Vec::new()),
+            held_tail: None,
+            timed: false,
+            dedup: None,
+            byte_limit: None,
+            memory_pressure: None,
+            pressure_flushes: 0,
+            write_time: Duration::ZERO,
+        }
+    }
+
+    /// 统计写出耗时，见 `WriterOutput::write_time`
+    pub(crate) fn with_timing(mut self) -> Self {
+        self.timed = true;
+        self
+    }
+
+    /// 写出前按 `columns`（schema 中的列下标）去重，只保留每个键第一次出现的行
+    pub(crate) fn with_dedup(mut self, columns: Vec<usize>) -> Self {
+        self.dedup = Some(RowDeduper {
+            columns,
+            seen: HashSet::new(),
+            removed: 0,
+        });
+        self
+    }
+
+    /// 输出达到约 `max_bytes` 字节后丢弃之后的批次，并置位 `reached` 通知读取线程停止。
+    /// 不能与排序同时使用
+    pub(crate) fn with_byte_limit(mut self, max_bytes: usize, reached: Arc<AtomicBool>) -> Self {
+        self.byte_limit = Some(ByteLimit { max_bytes, reached });
+        self
+    }
+
+    /// 乱序到达的批次写入临时目录，轮到时再读回，内存中不再暂存批次
+    pub(crate) fn with_spill(mut self) -> Result<Self> {
+        self.spill = Some(SpillDir::create()?);
+        Ok(self)
+    }
+
+    /// 写入主输出的每个批次同时写入 `sinks`，任一输出出错都会中止写入
+    pub(crate) fn with_fan_out(mut self, sinks: Vec<BatchSink>) -> Self {
+        self.fan_out = sinks;
+        self
+    }
+
+    /// 每收到一个批次调用一次 `pressure`，返回 `true` 时释放写入线程占用的内存
+    pub(crate) fn with_memory_pressure(mut self, pressure: MemoryPressureCallback) -> Self {
+        self.memory_pressure = Some(pressure);
+        self
+    }
+
+    /// 结束当前 row group，并将内存中暂存的乱序批次溢出到磁盘；溢出目录不存在时创建，
+    /// 之后乱序批次都直接溢出
+    fn relieve_memory_pressure(&mut self) -> Result<()> {
+        for sink in std::iter::once(&mut self.sink).chain(&mut self.fan_out) {
+            sink.flush()?;
+        }
+        let spill = match &mut self.spill {
+            Some(spill) => spill,
+            None => self.spill.insert(SpillDir::create()?),
+        };
+        let mut spilled = 0;
+        for (id, buffered) in self.buffer.iter_mut() {
+            if let BufferedBatch::Memory(batch) = buffered {
+                *buffered = spill.spill(*id, batch)?;
+                spilled += 1;
+            }
+        }
+        self.pressure_flushes += 1;
+        info!(
+            "Writer: memory pressure, flushed row group and spilled {} batches",
+            spilled
+        );
+        Ok(())
+    }
+
+    /// 内存中暂存的乱序批次数与溢出到磁盘的批次数
+    #[cfg(test)]
+    pub(crate) fn buffered_batches(&self) -> (usize, usize) {
+        let spilled = self
+            .buffer
+            .values()
+            .filter(|batch| matches!(batch, BufferedBatch::Spilled(_)))
+            .count();
+        (self.buffer.len() - spilled, spilled)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn spill_dir(&self) -> Option<&Path> {
+        self.spill.as_ref().map(SpillDir::path)
+    }
+
+    pub(crate) fn push(&mut self, id: usize, batch: RecordBatch) -> Result<()> {
+        if self
+            .memory_pressure
+            .as_ref()
+            .is_some_and(|pressure| pressure())
+        {
+            self.relieve_memory_pressure()?;
+        }
+        if id != self.next_expected_id {
+            if id < self.next_expected_id || self.buffer.contains_key(&id) {
+                return Err(anyhow::anyhow!("Batch {} was received twice", id));
+            }
+            let buffered = match &self.spill {
+                Some(spill) => spill.spill(id, &batch)?,
+                None => BufferedBatch::Memory(batch),
+            };
+            self.buffer.insert(id, buffered);
+            return Ok(());
+        }
+
+        let batch_rows = self
+            .write_next(batch)
+            .context("Failed to write record batch")?;
+        self.total_written_rows += batch_rows;
+        info!(
+            "Writer: wrote batch {} ({} rows). Total written: {}",
+            id, batch_rows, self.total_written_rows
+        );
+        self.next_expected_id += 1;
+
+        while let Some(buffered) = self.buffer.remove(&self.next_expected_id) {
+            let next_batch = buffered.load()?;
+            let next_rows = self
+                .write_next(next_batch)
+                .context("Failed to write buffered batch")?;
+            self.total_written_rows += next_rows;
+            info!(
+                "Writer: wrote buffered batch {} ({} rows). Total written: {}",
+                self.next_expected_id, next_rows, self.total_written_rows
+            );
+            self.next_expected_id += 1;
+        }
+        Ok(())
+    }
+
+    /// 写出（或在排序模式下收集）下一个按顺序的批次，返回去重后的行数；
+    /// 因字节上限丢弃批次时返回 0
+    fn write_next(&mut self, batch: RecordBatch) -> Result<usize> {
+        if let Some(limit) = &self.byte_limit {
+            if limit.reached.load(Ordering::Relaxed) {
+                return Ok(0);
+            }
+            if limit.exceeded_by(&self.sink, batch.num_rows()) {
+                info!(
+                    "Writer: output size limit of {} bytes reached, dropping remaining batches",
+                    limit.max_bytes
+                );
+                limit.reached.store(true, Ordering::Relaxed);
+                return Ok(0);
+            }
+        }
+        let batch = match self.dedup.as_mut() {
+            Some(dedup) => dedup.filter(batch)?,
+            None => batch,
+        };
+        let rows = batch.num_rows();
+        if let Some(sort_buffer) = self.sort_buffer.as_mut() {
+            sort_buffer.push(batch);
+        } else {
+            let batch = match self.split {
+                RowGroupSplit::PerBatchCoalescing(_) => match self.held_tail.replace(batch) {
+                    Some(previous) => previous,
+                    None => return Ok(rows),
+                },
+                _ => batch,
+            };
+            let started = start_timer(self.timed);
+            for sink in std::iter::once(&mut self.sink).chain(&mut self.fan_out) {
+                sink.write_batch(&batch, self.split, false)?;
+            }
+            if let Some(started) = started {
+                self.write_time += started.elapsed();
+            }
+        }
+        Ok(rows)
+    }
+
+    pub(crate) fn finish(mut self) -> Result<WriterOutput> {
+        if !self.buffer.is_empty() {
+            warn!("Writer finished with buffered batches remaining!");
+        }
+        let started = start_timer(self.timed);
+        if let Some(tail) = self.held_tail.take() {
+            for sink in std::iter::once(&mut self.sink).chain(&mut self.fan_out) {
+                sink.write_batch(&tail, self.split, true)
+                    .context("Failed to write record batch")?;
+            }
+        }
+        if let (Some(batches), Some((column, order))) = (self.sort_buffer.take(), self.sort_by) {
+            let combined = concat_batches(&self.schema, &batches)
+                .context("Failed to combine batches for sorting")?;
+            let (sorted, numeric) = sort_record_batch(&combined, column, order)?;
+            // 按数值排序的结果与 Parquet 对字符串的字节序比较不一致，此时不声明排序列
+            let sorting_columns = (!numeric).then(|| {
+                vec![SortingColumn {
+                    column_idx: column as i32,
+                    descending: order == SortOrder::Descending,
+                    nulls_first: false,
+                }]
+            });
+            for sink in std::iter::once(&mut self.sink).chain(&mut self.fan_out) {
+                sink.start_deferred(sorting_columns.clone())?;
+                sink.write(&sorted)
+                    .context("Failed to write sorted batch")?;
+            }
+            info!("Writer: wrote {} sorted rows", sorted.num_rows());
+        }
+        let _span = enter_span!("writer_close", rows = self.total_written_rows);
+        let output = self.sink.close()?;
+        for sink in self.fan_out {
+            sink.close()?;
+        }
+        info!("Writer thread: finished.");
+        Ok(WriterOutput {
+            next_batch_id: self.next_expected_id,
+            unwritten_batches: self.buffer.len(),
+            write_time: self.write_time + started.map_or(Duration::ZERO, |s| s.elapsed()),
+            duplicate_rows: self.dedup.as_ref().map_or(0, |dedup| dedup.removed),
+            rows_written: self.total_written_rows,
+            memory_pressure_flushes: self.pressure_flushes,
+            byte_limit_reached: self
+                .byte_limit
+                .as_ref()
+                .is_some_and(|limit| limit.reached.load(Ordering::Relaxed)),
+            ..output
+        })
+    }
+}
+
+/// 在独立线程中运行 [`OrderedWriter`]，从 `result_rx` 接收 `(批次编号, 批次)` 直到通道关闭
+pub(crate) fn spawn_writer(
+    mut writer: OrderedWriter,
+    result_rx: mpsc::Receiver<ProcessedBatch>,
+    trace: TraceContext,
+) -> thread::JoinHandle<Result<WriterOutput>> {
+    thread::spawn(move || -> Result<WriterOutput> {
+        let _trace = trace.attach();
+        while let Ok((id, batch)) = result_rx.recv() {
+            writer.push(id, batch)?;
+        }
+        writer.finish()
+    })
+}
+
+/// 将外部代码生成的 `RecordBatch` 流式写出为 Parquet，复用转换时的写入线程
+///
+/// 批次可以乱序推送，写入线程按 `batch_id` 从 0 开始依次写出，尚未轮到的批次暂存在内存中。
+/// 未指定写入属性时与转换使用相同的默认值（ZSTD 压缩、本 crate 的 `created_by`）。
+/// 输出先写入临时文件，`finish` 成功后才重命名为目标文件；未调用 `finish` 就丢弃时不会留下输出文件。
+/// 需要写入线程，`wasm32` 目标上不可用。
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ParquetBatchWriter {
+    result_tx: mpsc::SyncSender<ProcessedBatch>,
+    writer_thread: Option<thread::JoinHandle<Result<WriterOutput>>>,
+    output: AtomicOutput,
+    // 小于 `next_unpushed` 的编号都已推送；`pushed_ahead` 为之后已推送的编号
+    next_unpushed: usize,
+    pushed_ahead: HashSet<usize>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ParquetBatchWriter {
+    /// 创建写入器并启动写入线程，所有批次都必须符合 `schema`
+    pub fn try_new(
+        output_path: &Path,
+        schema: SchemaRef,
+        props: Option<WriterProperties>,
+    ) -> Result<Self> {
+        let output = AtomicOutput::new(output_path);
+        let props = props.unwrap_or_else(|| default_writer_properties().build());
+        let file = File::create(output.temp_path()).context("Failed to create output file")?;
+        let file: Box<dyn Write + Send> = Box::new(file);
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))
+            .context("Failed to create parquet writer")?;
+
+        // 与转换时 worker 到写入线程的通道容量一致
+        let (result_tx, result_rx) = mpsc::sync_channel(16);
+        let writer = OrderedWriter::new(
+            BatchSink::Parquet(Box::new(writer)),
+            schema,
+            None,
+            RowGroupSplit::BySize,
+        );
+        let writer_thread = spawn_writer(writer, result_rx, TraceContext::current());
+        Ok(Self {
+            result_tx,
+            writer_thread: Some(writer_thread),
+            output,
+            next_unpushed: 0,
+            pushed_ahead: HashSet::new(),
+        })
+    }
+
+    /// 推送一个批次。`batch_id` 从 0 开始连续编号，每个编号只能推送一次，重复推送立即返回错误；
+    /// 写入线程繁忙时会阻塞，写入线程已出错时返回该错误
+    pub fn push(&mut self, batch_id: usize, batch: RecordBatch) -> Result<()> {
+        if batch_id < self.next_unpushed || !self.pushed_ahead.insert(batch_id) {
+            return Err(anyhow::anyhow!("Batch {} was already pushed", batch_id));
+        }
+        while self.pushed_ahead.remove(&self.next_unpushed) {
+            self.next_unpushed += 1;
+        }
+        if self.result_tx.send((batch_id, batch)).is_ok() {
+            return Ok(());
+        }
+        match self.writer_thread.take().map(|handle| handle.join()) {
+            Some(Ok(Err(e))) => Err(e),
+            Some(Err(_)) => Err(anyhow::anyhow!("Writer thread panicked")),
+            _ => Err(anyhow::anyhow!("Writer thread has stopped")),
+        }
+    }
+
+    /// 等待所有批次写出并关闭文件，返回每个 row group 的统计
+    ///
+    /// 编号不连续（有批次未推送）时返回错误，且不会生成输出文件。
+    pub fn finish(self) -> Result<Vec<RowGroupStats>> {
+        let Self {
+            result_tx,
+            writer_thread,
+            output,
+            ..
+        } = self;
+        drop(result_tx);
+
+        let Some(handle) = writer_thread else {
+            return Err(anyhow::anyhow!("Writer thread has stopped"));
+        };
+        let written = handle
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Writer thread panicked")))?;
+        if written.unwritten_batches > 0 {
+            return Err(anyhow::anyhow!(
+                "Batch {} was never pushed; {} later batches were not written",
+                written.next_batch_id,
+                written.unwritten_batches
+            ));
+        }
+        output.commit()?;
+        Ok(written.row_group_stats)
+    }
+}
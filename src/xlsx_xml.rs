@@ -0,0 +1,126 @@
+//! 直接读取 xlsx 压缩包中的 XML 部件（隐藏行列与单元格样式共用）
+//!
+//! calamine 不解析的信息从工作簿关系、样式与 Sheet 的 XML 中按标签扫描得到，不做完整的 XML 解析。
+
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::io::{BufRead, Read};
+use zip::ZipArchive;
+
+/// 读取压缩包条目的全部文本
+pub(crate) fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String> {
+    let mut text = String::new();
+    archive
+        .by_name(name)
+        .with_context(|| format!("'{}' not found in workbook", name))?
+        .read_to_string(&mut text)
+        .with_context(|| format!("Failed to read '{}'", name))?;
+    Ok(text)
+}
+
+/// 由 `xl/workbook.xml` 与其关系文件找到 Sheet 对应的压缩包条目，如 `xl/worksheets/sheet1.xml`
+pub(crate) fn sheet_part(archive: &mut ZipArchive<File>, sheet_name: &str) -> Result<String> {
+    let workbook = read_entry(archive, "xl/workbook.xml")?;
+    let relationship = tags(&workbook, "sheet")
+        .find(|tag| attribute(tag, "name").map(unescape).as_deref() == Some(sheet_name))
+        .and_then(|tag| attribute(tag, "r:id"))
+        .ok_or_else(|| anyhow!("Sheet '{}' not found in workbook.xml", sheet_name))?;
+    let rels = read_entry(archive, "xl/_rels/workbook.xml.rels")?;
+    let target = tags(&rels, "Relationship")
+        .find(|tag| attribute(tag, "Id") == Some(relationship))
+        .and_then(|tag| attribute(tag, "Target"))
+        .ok_or_else(|| anyhow!("Relationship '{}' not found", relationship))?;
+    Ok(match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("xl/{}", target),
+    })
+}
+
+/// 文本中名为 `name`（忽略命名空间前缀）的开始标签的内容
+pub(crate) fn tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> {
+    xml.split('<').filter_map(move |part| {
+        let tag = &part[..part.find('>')?];
+        (local_name(tag) == name).then_some(tag)
+    })
+}
+
+/// 标签名去掉命名空间前缀，如 `x:row` 为 `row`
+pub(crate) fn local_name(tag: &str) -> &str {
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or("");
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// 读取带引号的属性值
+pub(crate) fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let before = rest[..pos].chars().next_back();
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let end = value[1..].find(quote)?;
+        return Some(&value[1..end + 1]);
+    }
+    None
+}
+
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 逐个标签扫描 XML，`f` 收到去掉 `<`、`>` 的标签内容（结束标签以 `/` 开头），返回 `false` 时停止
+pub(crate) fn scan_tags(
+    mut reader: impl BufRead,
+    mut f: impl FnMut(&str) -> Result<bool>,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'<', &mut buf)? == 0 {
+            break;
+        }
+        buf.clear();
+        if reader.read_until(b'>', &mut buf)? == 0 {
+            break;
+        }
+        let tag = String::from_utf8_lossy(&buf);
+        if !f(tag.trim_end_matches('>'))? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute() {
+        let tag = r#"sheet name="Q1 &amp; Q2" sheetId="2" r:id='rId5'"#;
+        assert_eq!(
+            attribute(tag, "name").map(unescape).as_deref(),
+            Some("Q1 & Q2")
+        );
+        assert_eq!(attribute(tag, "r:id"), Some("rId5"));
+        assert_eq!(attribute(tag, "id"), None);
+        assert_eq!(attribute(tag, "Id"), None);
+    }
+}
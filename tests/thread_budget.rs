@@ -0,0 +1,52 @@
+//! 进程内默认预算是全局状态，单独放在这个测试二进制中，不影响 lib 中并行运行的其他测试
+
+use data_to_parquet::{
+    ConvertExcelToParquetOptions, convert_xlsx_to_parquet, global_thread_budget,
+    set_global_thread_budget,
+};
+use rust_xlsxwriter::Workbook;
+use std::path::PathBuf;
+use std::thread;
+
+#[test]
+fn test_global_thread_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let inputs: Vec<PathBuf> = (0..2)
+        .map(|i| {
+            let input = dir.path().join(format!("global{}.xlsx", i));
+            let mut workbook = Workbook::new();
+            let worksheet = workbook.add_worksheet();
+            worksheet.write_string(0, 0, "id").unwrap();
+            for row in 1..=200 {
+                worksheet.write_string(row, 0, "1").unwrap();
+            }
+            workbook.save(&input).unwrap();
+            input
+        })
+        .collect();
+
+    // 两个转换都不设置 thread_budget，各自想要的工作线程数远超默认预算
+    set_global_thread_budget(2);
+    let handles: Vec<_> = inputs
+        .into_iter()
+        .map(|input| {
+            thread::spawn(move || {
+                let output = input.with_extension("parquet");
+                let options = ConvertExcelToParquetOptions {
+                    batch_size: Some(5),
+                    ..ConvertExcelToParquetOptions::new(&input, &output)
+                };
+                assert!(options.thread_budget.is_none());
+                convert_xlsx_to_parquet(options).unwrap().total_rows
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 200);
+    }
+    let peak = global_thread_budget().unwrap().peak();
+    assert!((1..=2).contains(&peak), "peak workers {}", peak);
+
+    set_global_thread_budget(0);
+    assert!(global_thread_budget().is_none());
+}